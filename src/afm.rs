@@ -0,0 +1,210 @@
+use std::collections::HashMap;
+
+/// Metrics of a single glyph read from the `CharMetrics` section.
+#[derive(Debug, Clone)]
+pub struct AfmCharMetric {
+    pub code: i32,
+    pub width: f32,
+    pub name: String,
+}
+
+/// Font metrics read from an Adobe Font Metrics (`.afm`) file.
+///
+/// All values are expressed in the 1000 units per em glyph space used by Type 1 fonts.
+#[derive(Debug, Clone)]
+pub struct AfmMetrics {
+    pub font_name: String,
+    pub family_name: String,
+    pub weight: String,
+    pub italic_angle: f32,
+    pub is_fixed_pitch: bool,
+    pub font_bbox: [i32; 4],
+    pub cap_height: Option<i32>,
+    pub x_height: Option<i32>,
+    pub ascender: i32,
+    pub descender: i32,
+    pub stem_v: i32,
+    pub encoding_scheme: String,
+    pub char_metrics: Vec<AfmCharMetric>,
+    /// `KPX` kerning pairs: left and right glyph names and the adjustment of their spacing.
+    pub kern_pairs: Vec<(String, String, f32)>,
+    widths_by_name: HashMap<String, f32>,
+}
+
+impl AfmMetrics {
+
+    pub fn parse(data: &str) -> Result<AfmMetrics, String> {
+        let mut metrics = AfmMetrics {
+            font_name: String::new(),
+            family_name: String::new(),
+            weight: String::new(),
+            italic_angle: 0.0,
+            is_fixed_pitch: false,
+            font_bbox: [0, 0, 0, 0],
+            cap_height: None,
+            x_height: None,
+            ascender: 0,
+            descender: 0,
+            stem_v: 0,
+            encoding_scheme: String::new(),
+            char_metrics: Vec::new(),
+            kern_pairs: Vec::new(),
+            widths_by_name: HashMap::new(),
+        };
+
+        let mut lines = data.lines().map(str::trim);
+        if !lines.next().is_some_and(|line| line.starts_with("StartFontMetrics")) {
+            return Err("AFM data must start with StartFontMetrics".to_string());
+        }
+
+        let mut ascender = None;
+        let mut descender = None;
+        let mut in_char_metrics = false;
+
+        for line in lines {
+            if line.is_empty() || line.starts_with("Comment") {
+                continue;
+            }
+
+            if in_char_metrics {
+                if line.starts_with("EndCharMetrics") {
+                    in_char_metrics = false;
+                } else {
+                    metrics.char_metrics.push(parse_char_metric(line)?);
+                }
+                continue;
+            }
+
+            let (key, value) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let value = value.trim();
+            match key {
+                "FontName" => metrics.font_name = value.to_string(),
+                "FamilyName" => metrics.family_name = value.to_string(),
+                "Weight" => metrics.weight = value.to_string(),
+                "ItalicAngle" => metrics.italic_angle = parse_number(key, value)?,
+                "IsFixedPitch" => metrics.is_fixed_pitch = value == "true",
+                "FontBBox" => {
+                    let values = value.split_whitespace()
+                        .map(|v| parse_number::<f32>(key, v).map(|v| v.round() as i32))
+                        .collect::<Result<Vec<i32>, String>>()?;
+                    metrics.font_bbox = values.try_into()
+                        .map_err(|_| "FontBBox must have four values".to_string())?;
+                }
+                "CapHeight" => metrics.cap_height = Some(parse_number::<f32>(key, value)?.round() as i32),
+                "XHeight" => metrics.x_height = Some(parse_number::<f32>(key, value)?.round() as i32),
+                "Ascender" => ascender = Some(parse_number::<f32>(key, value)?.round() as i32),
+                "Descender" => descender = Some(parse_number::<f32>(key, value)?.round() as i32),
+                "StdVW" => metrics.stem_v = parse_number::<f32>(key, value)?.round() as i32,
+                "EncodingScheme" => metrics.encoding_scheme = value.to_string(),
+                "StartCharMetrics" => in_char_metrics = true,
+                // `KPX A y -40`, inside the StartKernPairs section.
+                "KPX" => {
+                    let fields: Vec<&str> = value.split_whitespace().collect();
+                    let [left, right, adjustment] = fields[..] else {
+                        return Err(format!("Invalid kerning pair: {}", value));
+                    };
+                    metrics.kern_pairs.push((left.to_string(), right.to_string(), parse_number(key, adjustment)?));
+                }
+                "EndFontMetrics" => break,
+                _ => {}
+            }
+        }
+
+        if metrics.font_name.is_empty() {
+            return Err("AFM data has no FontName".to_string());
+        }
+
+        // Ascender and Descender are optional, the font bounding box is the usual fallback.
+        metrics.ascender = ascender.unwrap_or(metrics.font_bbox[3]);
+        metrics.descender = descender.unwrap_or(metrics.font_bbox[1]);
+        if metrics.stem_v == 0 {
+            metrics.stem_v = 80;
+        }
+
+        metrics.widths_by_name = metrics.char_metrics.iter()
+            .map(|metric| (metric.name.clone(), metric.width))
+            .collect();
+
+        Ok(metrics)
+    }
+
+    pub fn width_of_glyph(&self, name: &str) -> Option<f32> {
+        self.widths_by_name.get(name).copied()
+    }
+
+    pub fn width_of_code(&self, code: u8) -> Option<f32> {
+        self.char_metrics.iter()
+            .find(|metric| metric.code == code as i32)
+            .map(|metric| metric.width)
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(key: &str, value: &str) -> Result<T, String> {
+    value.parse::<T>().map_err(|_| format!("Invalid value for {}: {}", key, value))
+}
+
+// A CharMetrics line looks like `C 65 ; WX 667 ; N A ; B 14 0 654 718 ;`
+fn parse_char_metric(line: &str) -> Result<AfmCharMetric, String> {
+    let mut metric = AfmCharMetric {
+        code: -1,
+        width: 0.0,
+        name: String::new(),
+    };
+
+    for entry in line.split(';').map(str::trim).filter(|entry| !entry.is_empty()) {
+        let (key, value) = entry.split_once(char::is_whitespace).unwrap_or((entry, ""));
+        let value = value.trim();
+        match key {
+            "C" => metric.code = parse_number(key, value)?,
+            "CH" => {
+                let hex = value.trim_start_matches('<').trim_end_matches('>');
+                metric.code = i32::from_str_radix(hex, 16)
+                    .map_err(|_| format!("Invalid value for CH: {}", value))?;
+            }
+            "WX" | "W0X" => metric.width = parse_number(key, value)?,
+            "N" => metric.name = value.to_string(),
+            _ => {}
+        }
+    }
+
+    Ok(metric)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AfmMetrics;
+
+    #[test]
+    fn it_parses_header_and_char_metrics() {
+        let metrics = AfmMetrics::parse(include_str!("../assets/afm/Helvetica.afm")).unwrap();
+        assert_eq!(metrics.font_name, "Helvetica");
+        assert_eq!(metrics.font_bbox, [-166, -225, 1000, 931]);
+        assert_eq!(metrics.ascender, 718);
+        assert_eq!(metrics.descender, -207);
+        assert_eq!(metrics.width_of_glyph("A"), Some(667.0));
+        assert_eq!(metrics.width_of_code(32), Some(278.0));
+    }
+
+    #[test]
+    fn it_falls_back_to_bbox_without_ascender() {
+        let metrics = AfmMetrics::parse("StartFontMetrics 4.1\nFontName Test\nFontBBox -10 -200 900 800\nStartCharMetrics 1\nC 32 ; WX 250 ; N space ;\nEndCharMetrics\nEndFontMetrics\n").unwrap();
+        assert_eq!(metrics.ascender, 800);
+        assert_eq!(metrics.descender, -200);
+        assert_eq!(metrics.width_of_glyph("space"), Some(250.0));
+    }
+
+    #[test]
+    fn it_parses_kerning_pairs() {
+        let metrics = AfmMetrics::parse("StartFontMetrics 4.1\nFontName Test\nStartKernData\nStartKernPairs 2\nKPX A y -40\nKPX T o -80.5\nEndKernPairs\nEndKernData\nEndFontMetrics\n").unwrap();
+        assert_eq!(metrics.kern_pairs, vec![
+            ("A".to_string(), "y".to_string(), -40.0),
+            ("T".to_string(), "o".to_string(), -80.5),
+        ]);
+        assert!(AfmMetrics::parse("StartFontMetrics 4.1\nFontName Test\nKPX A\n").is_err());
+    }
+
+    #[test]
+    fn it_rejects_data_without_header() {
+        assert!(AfmMetrics::parse("FontName Test\n").is_err());
+    }
+}
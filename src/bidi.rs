@@ -0,0 +1,101 @@
+use std::ops::Range;
+use unicode_bidi::{Level, ParagraphBidiInfo};
+
+/// Base direction of a paragraph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Direction {
+    /// Taken from the first strong character of the text (rules P2 and P3 of UAX #9),
+    /// left to right when there is none.
+    #[default]
+    Auto,
+    LeftToRight,
+    RightToLeft,
+}
+
+impl Direction {
+
+    /// Whether the paragraph `text` is right to left.
+    pub fn is_rtl(&self, text: &str) -> bool {
+        match self {
+            Direction::Auto => ParagraphBidiInfo::new(text, None).paragraph_level.is_rtl(),
+            Direction::LeftToRight => false,
+            Direction::RightToLeft => true,
+        }
+    }
+
+    fn level(&self) -> Option<Level> {
+        match self {
+            Direction::Auto => None,
+            Direction::LeftToRight => Some(Level::ltr()),
+            Direction::RightToLeft => Some(Level::rtl()),
+        }
+    }
+}
+
+/// A run of text with a single direction, as returned by `visual_runs`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BidiRun<'a> {
+    /// Text of the run, in logical order.
+    pub text: &'a str,
+    /// Byte range of the run in the paragraph.
+    pub range: Range<usize>,
+    pub rtl: bool,
+}
+
+/// Splits the paragraph `text` into directional runs with the Unicode Bidirectional
+/// Algorithm (UAX #9) and returns them in visual order, from left to right. Runs keep their
+/// text in logical order: right to left runs still have to be drawn reversed.
+pub fn visual_runs(text: &str, direction: Direction) -> Vec<BidiRun<'_>> {
+    let info = ParagraphBidiInfo::new(text, direction.level());
+    if text.is_empty() || (info.is_pure_ltr && !info.paragraph_level.is_rtl()) {
+        return vec![BidiRun { text, range: 0..text.len(), rtl: false }];
+    }
+
+    let (levels, runs) = info.visual_runs(0..text.len());
+    runs.into_iter()
+        .map(|range| BidiRun {
+            text: &text[range.clone()],
+            rtl: levels[range.start].is_rtl(),
+            range,
+        })
+        .collect()
+}
+
+/// Characters of a right to left run in drawing order: reversed, with mirrored characters
+/// such as brackets swapped for their counterpart.
+pub fn reverse_mirrored(text: &str) -> String {
+    text.chars()
+        .rev()
+        .map(|c| unicode_bidi_mirroring::get_mirrored(c).unwrap_or(c))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{reverse_mirrored, visual_runs, Direction};
+
+    #[test]
+    fn it_detects_the_paragraph_direction() {
+        assert!(Direction::Auto.is_rtl("שלום world"));
+        assert!(!Direction::Auto.is_rtl("world שלום"));
+        assert!(!Direction::Auto.is_rtl("1234"));
+        assert!(Direction::RightToLeft.is_rtl("world"));
+    }
+
+    #[test]
+    fn it_orders_runs_visually() {
+        // "order ABC-123" in Hebrew, the product code stays left to right.
+        let runs = visual_runs("הזמנה ABC-123", Direction::Auto);
+        let texts: Vec<(&str, bool)> = runs.iter().map(|run| (run.text, run.rtl)).collect();
+        assert_eq!(texts, vec![("ABC-123", false), ("הזמנה ", true)]);
+
+        let runs = visual_runs("Total: 42", Direction::Auto);
+        assert_eq!(runs.len(), 1);
+        assert!(!runs[0].rtl);
+    }
+
+    #[test]
+    fn it_reverses_and_mirrors_rtl_runs() {
+        assert_eq!(reverse_mirrored("(אב)"), "(בא)");
+    }
+}
@@ -0,0 +1,167 @@
+/// `(Registry, Ordering, Supplement)` of a CID-keyed CFF font.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CidSystemInfo {
+    pub registry: String,
+    pub ordering: String,
+    pub supplement: i32,
+}
+
+impl Default for CidSystemInfo {
+    fn default() -> Self {
+        CidSystemInfo {
+            registry: String::from("Adobe"),
+            ordering: String::from("Identity"),
+            supplement: 0,
+        }
+    }
+}
+
+// Number of strings predefined by the CFF specification, custom SIDs start here.
+const STANDARD_STRINGS: usize = 391;
+
+const ROS_OPERATOR: u16 = 1230;
+
+/// Reads the `ROS` operator of the Top DICT of a bare CFF table.
+///
+/// Returns `None` when the data can't be read or the font is not CID-keyed.
+pub fn cid_system_info(cff: &[u8]) -> Option<CidSystemInfo> {
+    let header_size = *cff.get(2)? as usize;
+    let (_, offset) = read_index(cff, header_size)?;
+    let (top_dicts, offset) = read_index(cff, offset)?;
+    let (strings, _) = read_index(cff, offset)?;
+
+    let operands = read_dict_operator(top_dicts.first()?, ROS_OPERATOR)?;
+    let [registry, ordering, supplement] = operands.as_slice() else {
+        return None;
+    };
+
+    let string = |sid: f64| -> Option<String> {
+        let index = (sid as usize).checked_sub(STANDARD_STRINGS)?;
+        strings.get(index).map(|data| String::from_utf8_lossy(data).to_string())
+    };
+
+    Some(CidSystemInfo {
+        registry: string(*registry)?,
+        ordering: string(*ordering)?,
+        supplement: *supplement as i32,
+    })
+}
+
+// Returns the objects of the INDEX starting at `offset` and the offset right after it.
+fn read_index(data: &[u8], offset: usize) -> Option<(Vec<&[u8]>, usize)> {
+    let count = u16::from_be_bytes([*data.get(offset)?, *data.get(offset + 1)?]) as usize;
+    if count == 0 {
+        return Some((Vec::new(), offset + 2));
+    }
+
+    let offset_size = *data.get(offset + 2)? as usize;
+    let offsets_start = offset + 3;
+    let read_offset = |index: usize| -> Option<usize> {
+        let start = offsets_start + index * offset_size;
+        data.get(start..start + offset_size)
+            .map(|bytes| bytes.iter().fold(0usize, |value, byte| (value << 8) | *byte as usize))
+    };
+
+    // Offsets are relative to the byte preceding the object data.
+    let data_start = offsets_start + (count + 1) * offset_size - 1;
+    let mut objects = Vec::with_capacity(count);
+    for index in 0..count {
+        let start = data_start + read_offset(index)?;
+        let end = data_start + read_offset(index + 1)?;
+        objects.push(data.get(start..end)?);
+    }
+
+    let end = data_start + read_offset(count)?;
+    Some((objects, end))
+}
+
+fn read_dict_operator(dict: &[u8], operator: u16) -> Option<Vec<f64>> {
+    let mut operands = Vec::new();
+    let mut position = 0;
+
+    while position < dict.len() {
+        let b0 = dict[position];
+        position += 1;
+        match b0 {
+            0..=21 => {
+                let op = if b0 == 12 {
+                    position += 1;
+                    1200 + *dict.get(position - 1)? as u16
+                } else {
+                    b0 as u16
+                };
+                if op == operator {
+                    return Some(operands);
+                }
+                operands.clear();
+            }
+            28 => {
+                let bytes = dict.get(position..position + 2)?;
+                operands.push(i16::from_be_bytes([bytes[0], bytes[1]]) as f64);
+                position += 2;
+            }
+            29 => {
+                let bytes = dict.get(position..position + 4)?;
+                operands.push(i32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64);
+                position += 4;
+            }
+            30 => {
+                // Real numbers are packed as nibbles and end with a 0xf nibble, the value
+                // itself is never needed for ROS so it is skipped.
+                while (*dict.get(position)? & 0x0f) != 0x0f && (*dict.get(position)? >> 4) != 0x0f {
+                    position += 1;
+                }
+                position += 1;
+                operands.push(0.0);
+            }
+            32..=246 => operands.push(b0 as f64 - 139.0),
+            247..=250 => {
+                operands.push((b0 as f64 - 247.0) * 256.0 + *dict.get(position)? as f64 + 108.0);
+                position += 1;
+            }
+            251..=254 => {
+                operands.push(-(b0 as f64 - 251.0) * 256.0 - *dict.get(position)? as f64 - 108.0);
+                position += 1;
+            }
+            _ => return None,
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cid_system_info, CidSystemInfo};
+
+    fn cff_with_top_dict(top_dict: &[u8]) -> Vec<u8> {
+        let mut data = vec![1, 0, 4, 1];
+        // Name INDEX with a single "A".
+        data.extend_from_slice(&[0, 1, 1, 1, 2, b'A']);
+        // Top DICT INDEX.
+        data.extend_from_slice(&[0, 1, 1, 1, top_dict.len() as u8 + 1]);
+        data.extend_from_slice(top_dict);
+        // String INDEX with "Adobe" and "Japan1".
+        data.extend_from_slice(&[0, 2, 1, 1, 6, 12]);
+        data.extend_from_slice(b"AdobeJapan1");
+        data
+    }
+
+    #[test]
+    fn it_reads_registry_ordering_supplement() {
+        // 391 392 6 ROS
+        let cff = cff_with_top_dict(&[28, 0x01, 0x87, 28, 0x01, 0x88, 145, 12, 30]);
+        assert_eq!(cid_system_info(&cff), Some(CidSystemInfo {
+            registry: String::from("Adobe"),
+            ordering: String::from("Japan1"),
+            supplement: 6,
+        }));
+    }
+
+    #[test]
+    fn it_returns_none_for_name_keyed_fonts() {
+        // 391 FullName
+        let cff = cff_with_top_dict(&[28, 0x01, 0x87, 2]);
+        assert_eq!(cid_system_info(&cff), None);
+    }
+}
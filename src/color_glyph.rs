@@ -0,0 +1,246 @@
+use ttf_parser::colr::{ClipBox, CompositeMode, Paint, Painter};
+use ttf_parser::{Face, GlyphId, RasterImageFormat, RgbaColor};
+use crate::outline::{concat, PathBuilder, Transform, IDENTITY};
+use crate::png::{self, Bitmap};
+
+/// How a glyph is drawn in the glyph description (`/CharProcs` stream) of a `Type3` font.
+/// Coordinates are in 1/1000 em.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GlyphDrawing {
+    /// `COLR` layers filled with their palette colors. Layers in the foreground color are
+    /// filled with the current color, the fill color of the text. Translucent layers select
+    /// `/A{alpha}` graphics states, with the alpha values listed in `opacities`.
+    Layers { operators: String, opacities: Vec<u8> },
+    /// `sbix` or `CBDT` image, drawn with the `Do` operator after `transform` maps the unit
+    /// square to its place.
+    Bitmap { image: Bitmap, transform: Transform },
+    /// Plain outline, filled with the fill color of the text.
+    Outline(String),
+}
+
+/// Whether the font has color glyphs: `COLR`/`CPAL` layers or `sbix`/`CBDT` images.
+pub fn has_color_glyphs(face: &Face) -> bool {
+    let tables = face.tables();
+    tables.colr.is_some() || tables.sbix.is_some() || tables.cbdt.is_some()
+}
+
+/// Drawing of glyph `glyph_id`, trying color layers first, then images and then the outline.
+/// Returns `None` for blank glyphs such as the space.
+pub fn draw_glyph<'a>(face: &'a Face<'a>, glyph_id: u16) -> Option<GlyphDrawing> {
+    if face.is_color_glyph(GlyphId(glyph_id)) {
+        if let Some(drawing) = draw_layers(face, glyph_id) {
+            return Some(drawing);
+        }
+    }
+
+    // The largest strike gives the sharpest image once scaled.
+    if let Some(raster) = face.glyph_raster_image(GlyphId(glyph_id), u16::MAX) {
+        let image = match raster.format {
+            RasterImageFormat::PNG => png::decode_png(raster.data).ok(),
+            RasterImageFormat::BitmapPremulBgra32 => Some(unpremultiply_bgra(raster.data, raster.width, raster.height)),
+            _ => None,
+        };
+        if let Some(image) = image {
+            let scale = 1000.0 / raster.pixels_per_em as f32;
+            // Offsets place the bottom left corner of the image, in pixels.
+            let transform = [
+                image.width as f32 * scale,
+                0.0,
+                0.0,
+                image.height as f32 * scale,
+                raster.x as f32 * scale,
+                raster.y as f32 * scale,
+            ];
+            return Some(GlyphDrawing::Bitmap { image, transform });
+        }
+    }
+
+    crate::outline::glyph_path(face, glyph_id)
+        .filter(|path| !path.is_empty())
+        .map(GlyphDrawing::Outline)
+}
+
+fn draw_layers<'a>(face: &'a Face<'a>, glyph_id: u16) -> Option<GlyphDrawing> {
+    // Layers painted with the foreground color are told apart by painting the glyph with two
+    // different foreground colors.
+    let paint = |foreground: RgbaColor| -> Option<Vec<Operation>> {
+        let mut painter = LayerPainter::new(face);
+        face.paint_color_glyph(GlyphId(glyph_id), 0, foreground, &mut painter)?;
+        Some(painter.operations)
+    };
+    let black = paint(RgbaColor::new(0, 0, 0, 255))?;
+    let white = paint(RgbaColor::new(255, 255, 255, 255))?;
+
+    let mut operators = String::new();
+    let mut opacities: Vec<u8> = Vec::new();
+    for (operation, other) in black.iter().zip(&white) {
+        match (operation, other) {
+            (Operation::Fill { path, color }, Operation::Fill { color: other_color, .. }) if color != other_color => {
+                operators.push_str(&format!("{}f\n", path));
+            }
+            (Operation::Fill { path, color }, _) => {
+                operators.push_str(&format!(
+                    "q\n{:.3} {:.3} {:.3} rg\n",
+                    color.red as f32 / 255.0,
+                    color.green as f32 / 255.0,
+                    color.blue as f32 / 255.0
+                ));
+                if color.alpha < 255 {
+                    operators.push_str(&format!("/A{} gs\n", color.alpha));
+                    if !opacities.contains(&color.alpha) {
+                        opacities.push(color.alpha);
+                    }
+                }
+                operators.push_str(&format!("{}f\nQ\n", path));
+            }
+            (Operation::Raw(raw), _) => operators.push_str(raw),
+        }
+    }
+
+    Some(GlyphDrawing::Layers { operators, opacities })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Operation {
+    Raw(String),
+    Fill { path: String, color: RgbaColor },
+}
+
+// Records the paint operations of a COLR glyph as PDF operators. Each paint fills the last
+// outline, clipped by the enclosing clips; gradients are approximated by the average color
+// of their stops.
+struct LayerPainter<'a> {
+    face: &'a Face<'a>,
+    transforms: Vec<Transform>,
+    outline: Option<String>,
+    operations: Vec<Operation>,
+}
+
+impl<'a> LayerPainter<'a> {
+
+    fn new(face: &'a Face<'a>) -> LayerPainter<'a> {
+        let scale = 1000.0 / face.units_per_em() as f32;
+        LayerPainter {
+            face,
+            transforms: vec![[scale, 0.0, 0.0, scale, 0.0, 0.0]],
+            outline: None,
+            operations: Vec::new(),
+        }
+    }
+
+    fn transform(&self) -> Transform {
+        *self.transforms.last().unwrap_or(&IDENTITY)
+    }
+}
+
+fn average_color(colors: impl Iterator<Item = RgbaColor>) -> RgbaColor {
+    let mut sum = [0u32; 4];
+    let mut count = 0;
+    for color in colors {
+        sum[0] += color.red as u32;
+        sum[1] += color.green as u32;
+        sum[2] += color.blue as u32;
+        sum[3] += color.alpha as u32;
+        count += 1;
+    }
+    if count == 0 {
+        return RgbaColor::new(0, 0, 0, 0);
+    }
+    RgbaColor::new((sum[0] / count) as u8, (sum[1] / count) as u8, (sum[2] / count) as u8, (sum[3] / count) as u8)
+}
+
+impl<'a> Painter<'a> for LayerPainter<'a> {
+    fn outline_glyph(&mut self, glyph_id: GlyphId) {
+        let mut builder = PathBuilder::new(self.transform());
+        self.outline = self.face.outline_glyph(glyph_id, &mut builder).map(|_| builder.finish());
+    }
+
+    fn paint(&mut self, paint: Paint<'a>) {
+        let coords = self.face.variation_coordinates();
+        let color = match paint {
+            Paint::Solid(color) => color,
+            Paint::LinearGradient(gradient) => average_color(gradient.stops(0, coords).map(|stop| stop.color)),
+            Paint::RadialGradient(gradient) => average_color(gradient.stops(0, coords).map(|stop| stop.color)),
+            Paint::SweepGradient(gradient) => average_color(gradient.stops(0, coords).map(|stop| stop.color)),
+        };
+        if let Some(path) = self.outline.clone() {
+            self.operations.push(Operation::Fill { path, color });
+        }
+    }
+
+    fn push_clip(&mut self) {
+        let path = self.outline.clone().unwrap_or_default();
+        self.operations.push(Operation::Raw(format!("q\n{}W n\n", path)));
+    }
+
+    fn push_clip_box(&mut self, clipbox: ClipBox) {
+        let mut builder = PathBuilder::new(self.transform());
+        ttf_parser::OutlineBuilder::move_to(&mut builder, clipbox.x_min, clipbox.y_min);
+        ttf_parser::OutlineBuilder::line_to(&mut builder, clipbox.x_max, clipbox.y_min);
+        ttf_parser::OutlineBuilder::line_to(&mut builder, clipbox.x_max, clipbox.y_max);
+        ttf_parser::OutlineBuilder::line_to(&mut builder, clipbox.x_min, clipbox.y_max);
+        ttf_parser::OutlineBuilder::close(&mut builder);
+        let path = builder.finish();
+        // A paint right inside a clip box fills the whole box.
+        self.outline = Some(path.clone());
+        self.operations.push(Operation::Raw(format!("q\n{}W n\n", path)));
+    }
+
+    fn pop_clip(&mut self) {
+        self.operations.push(Operation::Raw("Q\n".to_string()));
+    }
+
+    // Composite modes other than source over are not supported, layers are drawn in order.
+    fn push_layer(&mut self, _mode: CompositeMode) {}
+
+    fn pop_layer(&mut self) {}
+
+    fn push_transform(&mut self, transform: ttf_parser::Transform) {
+        let transform = [transform.a, transform.b, transform.c, transform.d, transform.e, transform.f];
+        self.transforms.push(concat(&transform, &self.transform()));
+    }
+
+    fn pop_transform(&mut self) {
+        self.transforms.pop();
+    }
+}
+
+// Premultiplied BGRA pixels, top row first as in images, to RGB and alpha.
+fn unpremultiply_bgra(data: &[u8], width: u16, height: u16) -> Bitmap {
+    let mut rgb = Vec::with_capacity(data.len() / 4 * 3);
+    let mut alpha = Vec::with_capacity(data.len() / 4);
+    for pixel in data.chunks_exact(4) {
+        let (blue, green, red, opacity) = (pixel[0] as u32, pixel[1] as u32, pixel[2] as u32, pixel[3] as u32);
+        let straight = |value: u32| (value * 255).checked_div(opacity).unwrap_or(0).min(255) as u8;
+        rgb.extend_from_slice(&[straight(red), straight(green), straight(blue)]);
+        alpha.push(opacity as u8);
+    }
+    Bitmap {
+        width: width as u32,
+        height: height as u32,
+        rgb,
+        alpha: Some(alpha),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{draw_glyph, has_color_glyphs, unpremultiply_bgra, GlyphDrawing};
+
+    #[test]
+    fn it_draws_plain_glyphs_as_outlines() {
+        let face = ttf_parser::Face::parse(include_bytes!("../assets/NotoSansMono.ttf"), 0).unwrap();
+        assert!(!has_color_glyphs(&face));
+        let glyph_a = face.glyph_index('A').unwrap().0;
+        assert!(matches!(draw_glyph(&face, glyph_a), Some(GlyphDrawing::Outline(path)) if path.ends_with("h\n")));
+        let space = face.glyph_index(' ').unwrap().0;
+        assert_eq!(draw_glyph(&face, space), None);
+    }
+
+    #[test]
+    fn it_unpremultiplies_bitmap_pixels() {
+        let image = unpremultiply_bgra(&[0, 64, 128, 128, 0, 0, 0, 0], 2, 1);
+        assert_eq!(image.rgb, vec![255, 127, 0, 0, 0, 0]);
+        assert_eq!(image.alpha, Some(vec![128, 0]));
+    }
+}
@@ -0,0 +1,207 @@
+/// Single byte encodings used by simple (non-composite) fonts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    /// `/WinAnsiEncoding`, the PDF flavour of Windows code page 1252.
+    WinAnsi,
+    /// Built-in encoding of the standard `Symbol` font.
+    Symbol,
+    /// Built-in encoding of the standard `ZapfDingbats` font.
+    ZapfDingbats,
+}
+
+impl Encoding {
+
+    /// Name written as `/Encoding` in the font dictionary, `None` when the font's
+    /// built-in encoding is used.
+    pub fn pdf_name(&self) -> Option<&'static str> {
+        match self {
+            Encoding::WinAnsi => Some("WinAnsiEncoding"),
+            Encoding::Symbol | Encoding::ZapfDingbats => None,
+        }
+    }
+
+    pub fn encode_char(&self, c: char) -> Option<u8> {
+        match self {
+            Encoding::WinAnsi => win_ansi_code(c),
+            Encoding::Symbol => lookup_code(&SYMBOL_CODES, c),
+            Encoding::ZapfDingbats => lookup_code(&ZAPF_DINGBATS_CODES, c),
+        }
+    }
+
+    pub fn decode_code(&self, code: u8) -> Option<char> {
+        match self {
+            Encoding::WinAnsi => win_ansi_char(code),
+            Encoding::Symbol => SYMBOL_CODES.iter().find(|(_, c)| *c == code).map(|(ch, _)| *ch),
+            Encoding::ZapfDingbats => ZAPF_DINGBATS_CODES.iter().find(|(_, c)| *c == code).map(|(ch, _)| *ch),
+        }
+    }
+
+    /// Glyph name for `code` in `/WinAnsiEncoding`. Fonts with a built-in encoding
+    /// address their glyphs by code directly and return `None`.
+    pub fn glyph_name(&self, code: u8) -> Option<&'static str> {
+        match self {
+            Encoding::WinAnsi => WIN_ANSI_GLYPH_NAMES[code as usize],
+            Encoding::Symbol | Encoding::ZapfDingbats => None,
+        }
+    }
+}
+
+fn win_ansi_code(c: char) -> Option<u8> {
+    match c as u32 {
+        0x20..=0x7E | 0xA0..=0xFF => Some(c as u8),
+        _ => CP1252_HIGH.iter()
+            .position(|high| *high == c && c != '\u{FFFD}')
+            .map(|index| 0x80 + index as u8),
+    }
+}
+
+fn win_ansi_char(code: u8) -> Option<char> {
+    match code {
+        0x20..=0x7E | 0xA0..=0xFF => Some(code as char),
+        0x80..=0x9F => Some(CP1252_HIGH[(code - 0x80) as usize]).filter(|c| *c != '\u{FFFD}'),
+        _ => None,
+    }
+}
+
+fn lookup_code(table: &[(char, u8)], c: char) -> Option<u8> {
+    table.binary_search_by_key(&c, |(ch, _)| *ch)
+        .ok()
+        .map(|index| table[index].1)
+}
+
+const WIN_ANSI_GLYPH_NAMES: [Option<&str>; 256] = [
+    None, None, None, None, None, None, None, None,
+    None, None, None, None, None, None, None, None,
+    None, None, None, None, None, None, None, None,
+    None, None, None, None, None, None, None, None,
+    Some("space"), Some("exclam"), Some("quotedbl"), Some("numbersign"), Some("dollar"), Some("percent"), Some("ampersand"), Some("quotesingle"),
+    Some("parenleft"), Some("parenright"), Some("asterisk"), Some("plus"), Some("comma"), Some("hyphen"), Some("period"), Some("slash"),
+    Some("zero"), Some("one"), Some("two"), Some("three"), Some("four"), Some("five"), Some("six"), Some("seven"),
+    Some("eight"), Some("nine"), Some("colon"), Some("semicolon"), Some("less"), Some("equal"), Some("greater"), Some("question"),
+    Some("at"), Some("A"), Some("B"), Some("C"), Some("D"), Some("E"), Some("F"), Some("G"),
+    Some("H"), Some("I"), Some("J"), Some("K"), Some("L"), Some("M"), Some("N"), Some("O"),
+    Some("P"), Some("Q"), Some("R"), Some("S"), Some("T"), Some("U"), Some("V"), Some("W"),
+    Some("X"), Some("Y"), Some("Z"), Some("bracketleft"), Some("backslash"), Some("bracketright"), Some("asciicircum"), Some("underscore"),
+    Some("grave"), Some("a"), Some("b"), Some("c"), Some("d"), Some("e"), Some("f"), Some("g"),
+    Some("h"), Some("i"), Some("j"), Some("k"), Some("l"), Some("m"), Some("n"), Some("o"),
+    Some("p"), Some("q"), Some("r"), Some("s"), Some("t"), Some("u"), Some("v"), Some("w"),
+    Some("x"), Some("y"), Some("z"), Some("braceleft"), Some("bar"), Some("braceright"), Some("asciitilde"), Some("bullet"),
+    Some("Euro"), Some("bullet"), Some("quotesinglbase"), Some("florin"), Some("quotedblbase"), Some("ellipsis"), Some("dagger"), Some("daggerdbl"),
+    Some("circumflex"), Some("perthousand"), Some("Scaron"), Some("guilsinglleft"), Some("OE"), Some("bullet"), Some("Zcaron"), Some("bullet"),
+    Some("bullet"), Some("quoteleft"), Some("quoteright"), Some("quotedblleft"), Some("quotedblright"), Some("bullet"), Some("endash"), Some("emdash"),
+    Some("tilde"), Some("trademark"), Some("scaron"), Some("guilsinglright"), Some("oe"), Some("bullet"), Some("zcaron"), Some("Ydieresis"),
+    Some("space"), Some("exclamdown"), Some("cent"), Some("sterling"), Some("currency"), Some("yen"), Some("brokenbar"), Some("section"),
+    Some("dieresis"), Some("copyright"), Some("ordfeminine"), Some("guillemotleft"), Some("logicalnot"), Some("hyphen"), Some("registered"), Some("macron"),
+    Some("degree"), Some("plusminus"), Some("twosuperior"), Some("threesuperior"), Some("acute"), Some("mu"), Some("paragraph"), Some("periodcentered"),
+    Some("cedilla"), Some("onesuperior"), Some("ordmasculine"), Some("guillemotright"), Some("onequarter"), Some("onehalf"), Some("threequarters"), Some("questiondown"),
+    Some("Agrave"), Some("Aacute"), Some("Acircumflex"), Some("Atilde"), Some("Adieresis"), Some("Aring"), Some("AE"), Some("Ccedilla"),
+    Some("Egrave"), Some("Eacute"), Some("Ecircumflex"), Some("Edieresis"), Some("Igrave"), Some("Iacute"), Some("Icircumflex"), Some("Idieresis"),
+    Some("Eth"), Some("Ntilde"), Some("Ograve"), Some("Oacute"), Some("Ocircumflex"), Some("Otilde"), Some("Odieresis"), Some("multiply"),
+    Some("Oslash"), Some("Ugrave"), Some("Uacute"), Some("Ucircumflex"), Some("Udieresis"), Some("Yacute"), Some("Thorn"), Some("germandbls"),
+    Some("agrave"), Some("aacute"), Some("acircumflex"), Some("atilde"), Some("adieresis"), Some("aring"), Some("ae"), Some("ccedilla"),
+    Some("egrave"), Some("eacute"), Some("ecircumflex"), Some("edieresis"), Some("igrave"), Some("iacute"), Some("icircumflex"), Some("idieresis"),
+    Some("eth"), Some("ntilde"), Some("ograve"), Some("oacute"), Some("ocircumflex"), Some("otilde"), Some("odieresis"), Some("divide"),
+    Some("oslash"), Some("ugrave"), Some("uacute"), Some("ucircumflex"), Some("udieresis"), Some("yacute"), Some("thorn"), Some("ydieresis"),
+];
+const CP1252_HIGH: [char; 32] = [
+    '\u{20AC}', '\u{FFFD}', '\u{201A}', '\u{0192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{02C6}', '\u{2030}', '\u{0160}', '\u{2039}', '\u{0152}', '\u{FFFD}', '\u{017D}', '\u{FFFD}',
+    '\u{FFFD}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{02DC}', '\u{2122}', '\u{0161}', '\u{203A}', '\u{0153}', '\u{FFFD}', '\u{017E}', '\u{0178}',
+];
+const SYMBOL_CODES: [(char, u8); 188] = [
+    ('\u{0020}', 32), ('\u{0021}', 33), ('\u{0023}', 35), ('\u{0025}', 37), ('\u{0026}', 38), ('\u{0028}', 40),
+    ('\u{0029}', 41), ('\u{002B}', 43), ('\u{002C}', 44), ('\u{002E}', 46), ('\u{002F}', 47), ('\u{0030}', 48),
+    ('\u{0031}', 49), ('\u{0032}', 50), ('\u{0033}', 51), ('\u{0034}', 52), ('\u{0035}', 53), ('\u{0036}', 54),
+    ('\u{0037}', 55), ('\u{0038}', 56), ('\u{0039}', 57), ('\u{003A}', 58), ('\u{003B}', 59), ('\u{003C}', 60),
+    ('\u{003D}', 61), ('\u{003E}', 62), ('\u{003F}', 63), ('\u{005B}', 91), ('\u{005D}', 93), ('\u{005F}', 95),
+    ('\u{007B}', 123), ('\u{007C}', 124), ('\u{007D}', 125), ('\u{00AC}', 216), ('\u{00B0}', 176), ('\u{00B1}', 177),
+    ('\u{00B5}', 109), ('\u{00D7}', 180), ('\u{00F7}', 184), ('\u{0192}', 166), ('\u{0391}', 65), ('\u{0392}', 66),
+    ('\u{0393}', 71), ('\u{0395}', 69), ('\u{0396}', 90), ('\u{0397}', 72), ('\u{0398}', 81), ('\u{0399}', 73),
+    ('\u{039A}', 75), ('\u{039B}', 76), ('\u{039C}', 77), ('\u{039D}', 78), ('\u{039E}', 88), ('\u{039F}', 79),
+    ('\u{03A0}', 80), ('\u{03A1}', 82), ('\u{03A3}', 83), ('\u{03A4}', 84), ('\u{03A5}', 85), ('\u{03A6}', 70),
+    ('\u{03A7}', 67), ('\u{03A8}', 89), ('\u{03B1}', 97), ('\u{03B2}', 98), ('\u{03B3}', 103), ('\u{03B4}', 100),
+    ('\u{03B5}', 101), ('\u{03B6}', 122), ('\u{03B7}', 104), ('\u{03B8}', 113), ('\u{03B9}', 105), ('\u{03BA}', 107),
+    ('\u{03BB}', 108), ('\u{03BD}', 110), ('\u{03BE}', 120), ('\u{03BF}', 111), ('\u{03C0}', 112), ('\u{03C1}', 114),
+    ('\u{03C2}', 86), ('\u{03C3}', 115), ('\u{03C4}', 116), ('\u{03C5}', 117), ('\u{03C6}', 102), ('\u{03C7}', 99),
+    ('\u{03C8}', 121), ('\u{03C9}', 119), ('\u{03D1}', 74), ('\u{03D2}', 161), ('\u{03D5}', 106), ('\u{03D6}', 118),
+    ('\u{2022}', 183), ('\u{2026}', 188), ('\u{2032}', 162), ('\u{2033}', 178), ('\u{2044}', 164), ('\u{2111}', 193),
+    ('\u{2118}', 195), ('\u{211C}', 194), ('\u{2126}', 87), ('\u{2135}', 192), ('\u{2190}', 172), ('\u{2191}', 173),
+    ('\u{2192}', 174), ('\u{2193}', 175), ('\u{2194}', 171), ('\u{21B5}', 191), ('\u{21D0}', 220), ('\u{21D1}', 221),
+    ('\u{21D2}', 222), ('\u{21D3}', 223), ('\u{21D4}', 219), ('\u{2200}', 34), ('\u{2202}', 182), ('\u{2203}', 36),
+    ('\u{2205}', 198), ('\u{2206}', 68), ('\u{2207}', 209), ('\u{2208}', 206), ('\u{2209}', 207), ('\u{220B}', 39),
+    ('\u{220F}', 213), ('\u{2211}', 229), ('\u{2212}', 45), ('\u{2217}', 42), ('\u{221A}', 214), ('\u{221D}', 181),
+    ('\u{221E}', 165), ('\u{2220}', 208), ('\u{2227}', 217), ('\u{2228}', 218), ('\u{2229}', 199), ('\u{222A}', 200),
+    ('\u{222B}', 242), ('\u{2234}', 92), ('\u{223C}', 126), ('\u{2245}', 64), ('\u{2248}', 187), ('\u{2260}', 185),
+    ('\u{2261}', 186), ('\u{2264}', 163), ('\u{2265}', 179), ('\u{2282}', 204), ('\u{2283}', 201), ('\u{2284}', 203),
+    ('\u{2286}', 205), ('\u{2287}', 202), ('\u{2295}', 197), ('\u{2297}', 196), ('\u{22A5}', 94), ('\u{22C5}', 215),
+    ('\u{2320}', 243), ('\u{2321}', 245), ('\u{2329}', 225), ('\u{232A}', 241), ('\u{25CA}', 224), ('\u{2660}', 170),
+    ('\u{2663}', 167), ('\u{2665}', 169), ('\u{2666}', 168), ('\u{F6D9}', 211), ('\u{F6DA}', 210), ('\u{F6DB}', 212),
+    ('\u{F8E5}', 96), ('\u{F8E6}', 189), ('\u{F8E7}', 190), ('\u{F8E8}', 226), ('\u{F8E9}', 227), ('\u{F8EA}', 228),
+    ('\u{F8EB}', 230), ('\u{F8EC}', 231), ('\u{F8ED}', 232), ('\u{F8EE}', 233), ('\u{F8EF}', 234), ('\u{F8F0}', 235),
+    ('\u{F8F1}', 236), ('\u{F8F2}', 237), ('\u{F8F3}', 238), ('\u{F8F4}', 239), ('\u{F8F5}', 244), ('\u{F8F6}', 246),
+    ('\u{F8F7}', 247), ('\u{F8F8}', 248), ('\u{F8F9}', 249), ('\u{F8FA}', 250), ('\u{F8FB}', 251), ('\u{F8FC}', 252),
+    ('\u{F8FD}', 253), ('\u{F8FE}', 254),
+];
+const ZAPF_DINGBATS_CODES: [(char, u8); 188] = [
+    ('\u{0020}', 32), ('\u{2192}', 213), ('\u{2194}', 214), ('\u{2195}', 215), ('\u{2460}', 172), ('\u{2461}', 173),
+    ('\u{2462}', 174), ('\u{2463}', 175), ('\u{2464}', 176), ('\u{2465}', 177), ('\u{2466}', 178), ('\u{2467}', 179),
+    ('\u{2468}', 180), ('\u{2469}', 181), ('\u{25A0}', 110), ('\u{25B2}', 115), ('\u{25BC}', 116), ('\u{25C6}', 117),
+    ('\u{25CF}', 108), ('\u{25D7}', 119), ('\u{2605}', 72), ('\u{260E}', 37), ('\u{261B}', 42), ('\u{261E}', 43),
+    ('\u{2660}', 171), ('\u{2663}', 168), ('\u{2665}', 170), ('\u{2666}', 169), ('\u{2701}', 33), ('\u{2702}', 34),
+    ('\u{2703}', 35), ('\u{2704}', 36), ('\u{2706}', 38), ('\u{2707}', 39), ('\u{2708}', 40), ('\u{2709}', 41),
+    ('\u{270C}', 44), ('\u{270D}', 45), ('\u{270E}', 46), ('\u{270F}', 47), ('\u{2710}', 48), ('\u{2711}', 49),
+    ('\u{2712}', 50), ('\u{2713}', 51), ('\u{2714}', 52), ('\u{2715}', 53), ('\u{2716}', 54), ('\u{2717}', 55),
+    ('\u{2718}', 56), ('\u{2719}', 57), ('\u{271A}', 58), ('\u{271B}', 59), ('\u{271C}', 60), ('\u{271D}', 61),
+    ('\u{271E}', 62), ('\u{271F}', 63), ('\u{2720}', 64), ('\u{2721}', 65), ('\u{2722}', 66), ('\u{2723}', 67),
+    ('\u{2724}', 68), ('\u{2725}', 69), ('\u{2726}', 70), ('\u{2727}', 71), ('\u{2729}', 73), ('\u{272A}', 74),
+    ('\u{272B}', 75), ('\u{272C}', 76), ('\u{272D}', 77), ('\u{272E}', 78), ('\u{272F}', 79), ('\u{2730}', 80),
+    ('\u{2731}', 81), ('\u{2732}', 82), ('\u{2733}', 83), ('\u{2734}', 84), ('\u{2735}', 85), ('\u{2736}', 86),
+    ('\u{2737}', 87), ('\u{2738}', 88), ('\u{2739}', 89), ('\u{273A}', 90), ('\u{273B}', 91), ('\u{273C}', 92),
+    ('\u{273D}', 93), ('\u{273E}', 94), ('\u{273F}', 95), ('\u{2740}', 96), ('\u{2741}', 97), ('\u{2742}', 98),
+    ('\u{2743}', 99), ('\u{2744}', 100), ('\u{2745}', 101), ('\u{2746}', 102), ('\u{2747}', 103), ('\u{2748}', 104),
+    ('\u{2749}', 105), ('\u{274A}', 106), ('\u{274B}', 107), ('\u{274D}', 109), ('\u{274F}', 111), ('\u{2750}', 112),
+    ('\u{2751}', 113), ('\u{2752}', 114), ('\u{2756}', 118), ('\u{2758}', 120), ('\u{2759}', 121), ('\u{275A}', 122),
+    ('\u{275B}', 123), ('\u{275C}', 124), ('\u{275D}', 125), ('\u{275E}', 126), ('\u{2761}', 161), ('\u{2762}', 162),
+    ('\u{2763}', 163), ('\u{2764}', 164), ('\u{2765}', 165), ('\u{2766}', 166), ('\u{2767}', 167), ('\u{2776}', 182),
+    ('\u{2777}', 183), ('\u{2778}', 184), ('\u{2779}', 185), ('\u{277A}', 186), ('\u{277B}', 187), ('\u{277C}', 188),
+    ('\u{277D}', 189), ('\u{277E}', 190), ('\u{277F}', 191), ('\u{2780}', 192), ('\u{2781}', 193), ('\u{2782}', 194),
+    ('\u{2783}', 195), ('\u{2784}', 196), ('\u{2785}', 197), ('\u{2786}', 198), ('\u{2787}', 199), ('\u{2788}', 200),
+    ('\u{2789}', 201), ('\u{278A}', 202), ('\u{278B}', 203), ('\u{278C}', 204), ('\u{278D}', 205), ('\u{278E}', 206),
+    ('\u{278F}', 207), ('\u{2790}', 208), ('\u{2791}', 209), ('\u{2792}', 210), ('\u{2793}', 211), ('\u{2794}', 212),
+    ('\u{2798}', 216), ('\u{2799}', 217), ('\u{279A}', 218), ('\u{279B}', 219), ('\u{279C}', 220), ('\u{279D}', 221),
+    ('\u{279E}', 222), ('\u{279F}', 223), ('\u{27A0}', 224), ('\u{27A1}', 225), ('\u{27A2}', 226), ('\u{27A3}', 227),
+    ('\u{27A4}', 228), ('\u{27A5}', 229), ('\u{27A6}', 230), ('\u{27A7}', 231), ('\u{27A8}', 232), ('\u{27A9}', 233),
+    ('\u{27AA}', 234), ('\u{27AB}', 235), ('\u{27AC}', 236), ('\u{27AD}', 237), ('\u{27AE}', 238), ('\u{27AF}', 239),
+    ('\u{27B1}', 241), ('\u{27B2}', 242), ('\u{27B3}', 243), ('\u{27B4}', 244), ('\u{27B5}', 245), ('\u{27B6}', 246),
+    ('\u{27B7}', 247), ('\u{27B8}', 248), ('\u{27B9}', 249), ('\u{27BA}', 250), ('\u{27BB}', 251), ('\u{27BC}', 252),
+    ('\u{27BD}', 253), ('\u{27BE}', 254),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::Encoding;
+
+    #[test]
+    fn it_encodes_win_ansi() {
+        assert_eq!(Encoding::WinAnsi.encode_char('A'), Some(65));
+        assert_eq!(Encoding::WinAnsi.encode_char('é'), Some(0xE9));
+        assert_eq!(Encoding::WinAnsi.encode_char('€'), Some(0x80));
+        assert_eq!(Encoding::WinAnsi.encode_char('—'), Some(0x97));
+        assert_eq!(Encoding::WinAnsi.encode_char('α'), None);
+        assert_eq!(Encoding::WinAnsi.glyph_name(0x80), Some("Euro"));
+    }
+
+    #[test]
+    fn it_decodes_win_ansi() {
+        assert_eq!(Encoding::WinAnsi.decode_code(0x93), Some('“'));
+        assert_eq!(Encoding::WinAnsi.decode_code(0x81), None);
+    }
+
+    #[test]
+    fn it_encodes_builtin_encodings() {
+        assert_eq!(Encoding::Symbol.encode_char('α'), Some(97));
+        assert_eq!(Encoding::ZapfDingbats.encode_char('✓'), Some(0x33));
+        assert_eq!(Encoding::Symbol.pdf_name(), None);
+    }
+}
@@ -1,8 +1,1061 @@
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::rc::Rc;
+use ttf_parser::Tag;
+use crate::afm::AfmMetrics;
+use crate::color_glyph;
+use crate::encoding::Encoding;
+use crate::kerning::Kerning;
 use crate::reference::Ref;
+use crate::sfnt;
+use crate::standard_font::StandardFont;
+use crate::type1::Type1Program;
+use crate::variation;
+use crate::vertical::{self, VerticalLayout};
+use crate::woff;
+
+/// How the characters of a text are turned into the codes written in content streams.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FontEncoding {
+    /// One byte per character through a simple font encoding.
+    Simple(Encoding),
+    /// Two bytes per glyph on a composite (`Type0`) font, `/Identity-H`.
+    Identity,
+    /// One byte per glyph on `Type3` fonts whose glyphs are drawn by content streams, used for
+    /// fonts with color glyphs. Codes are given to glyphs as they are used, 256 per font.
+    Type3,
+}
+
+/// Kind of font program behind a `Font`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FontFormat {
+    /// One of the standard 14 fonts, nothing is embedded.
+    Standard(StandardFont),
+    /// TrueType outlines, embedded as `/FontFile2`.
+    TrueType,
+    /// CFF flavoured OpenType, embedded as `/FontFile3 /Subtype /OpenType`.
+    OpenType,
+    /// CID-keyed CFF, the bare CFF table is embedded as `/FontFile3 /Subtype /CIDFontType0C`.
+    CidKeyedCff,
+    /// PostScript Type 1 program with AFM metrics, embedded whole as `/FontFile`.
+    Type1,
+}
+
+/// Values of the `/FontDescriptor` dictionary, in 1/1000 em.
+#[derive(Debug, Clone)]
+pub struct FontDescriptor {
+    pub flags: u32,
+    pub bbox: [i32; 4],
+    pub italic_angle: f32,
+    pub ascent: i32,
+    pub descent: i32,
+    pub cap_height: i32,
+    pub stem_v: i32,
+}
+
+/// Options used when adding an embedded font.
+#[derive(Debug, Clone, Default)]
+pub struct FontOptions {
+    /// Index of the face inside a font collection (`.ttc`/`.otc`), 0 for single fonts.
+    pub index: u32,
+    /// Axis values of variable fonts in user units, e.g. `(*b"wght", 700.0)`. When set, a static
+    /// instance at these coordinates is embedded instead of the default one.
+    pub variations: Vec<([u8; 4], f32)>,
+    /// Writes text as glyph ids through a composite (`Type0`) font instead of a one byte
+    /// encoding, so any glyph of the font can be shown. CID-keyed CFF fonts are always composite.
+    pub composite: bool,
+}
+
+/// A face of a font file, as listed by `list_faces`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FaceInfo {
+    pub index: u32,
+    pub family: String,
+    pub style: String,
+    pub postscript_name: String,
+    /// Weight class of the `OS/2` table, 400 for regular faces.
+    pub weight: u16,
+    pub italic: bool,
+}
+
+impl FaceInfo {
+
+    /// Whether `name` is the PostScript name or the "Family Style" full name of the face.
+    pub fn matches(&self, name: &str) -> bool {
+        self.postscript_name.eq_ignore_ascii_case(name)
+            || format!("{} {}", self.family, self.style).eq_ignore_ascii_case(name)
+    }
+}
+
+/// Lists the faces of a font file. Collections (`.ttc`/`.otc`) have one entry per face,
+/// any other font file has a single entry. WOFF and WOFF2 files are decoded first.
+pub fn list_faces(data: &[u8]) -> Result<Vec<FaceInfo>, String> {
+    let data = &*woff::to_sfnt(data)?;
+    let count = ttf_parser::fonts_in_collection(data).unwrap_or(1);
+
+    (0..count).map(|index| {
+        let face = ttf_parser::Face::parse(data, index)
+            .map_err(|_| format!("Error parsing face {} of the font data", index))?;
+
+        Ok(FaceInfo {
+            index,
+            family: face_name(&face, ttf_parser::name_id::TYPOGRAPHIC_FAMILY)
+                .or_else(|| face_name(&face, ttf_parser::name_id::FAMILY))
+                .unwrap_or_default(),
+            style: face_name(&face, ttf_parser::name_id::TYPOGRAPHIC_SUBFAMILY)
+                .or_else(|| face_name(&face, ttf_parser::name_id::SUBFAMILY))
+                .unwrap_or_default(),
+            postscript_name: face_name(&face, ttf_parser::name_id::POST_SCRIPT_NAME).unwrap_or_default(),
+            weight: face.weight().to_number(),
+            italic: face.is_italic(),
+        })
+    }).collect()
+}
+
+// Reads a `name` table entry, Unicode records first and Macintosh Roman ones as ASCII.
+fn face_name(face: &ttf_parser::Face, name_id: u16) -> Option<String> {
+    let names: Vec<ttf_parser::name::Name> = face.names().into_iter()
+        .filter(|name| name.name_id == name_id)
+        .collect();
+
+    names.iter()
+        .find_map(|name| name.to_string())
+        .or_else(|| names.iter()
+            .find(|name| name.platform_id == ttf_parser::PlatformId::Macintosh)
+            .map(|name| name.name.iter().map(|byte| *byte as char).collect()))
+        .filter(|name| !name.is_empty())
+}
+
+// PostScript name of the face (name ID 6), or else one made of the family and style names.
+fn postscript_name(face: &ttf_parser::Face) -> Option<String> {
+    let name = face_name(face, ttf_parser::name_id::POST_SCRIPT_NAME).or_else(|| {
+        let family = face_name(face, ttf_parser::name_id::FAMILY)?;
+        let style = face_name(face, ttf_parser::name_id::SUBFAMILY).unwrap_or_default();
+        Some(format!("{}-{}", family, style))
+    })?;
+    Some(sanitize_postscript_name(&name)).filter(|name| !name.is_empty())
+}
+
+/// Keeps the characters of `name` allowed in PostScript and PDF names: printable ASCII
+/// without spaces nor delimiters, at most 63 of them.
+pub fn sanitize_postscript_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| matches!(c, '!'..='~') && !"[](){}<>/%#".contains(*c))
+        .take(63)
+        .collect()
+}
+
+/// Wraps text showing `operators` in a marked-content sequence whose `/ActualText` is `text`,
+/// for glyphs that can't be mapped back to the text one by one (reordered or shaped text).
+pub fn with_actual_text(text: &str, operators: &str) -> String {
+    let actual_text: String = text.encode_utf16().map(|unit| format!("{:04X}", unit)).collect();
+    format!("/Span << /ActualText <FEFF{}> >> BDC\n{}\nEMC", actual_text, operators)
+}
+
+/// Character drawn by simple fonts instead of the characters their encoding has no code for.
+pub const REPLACEMENT_CHARACTER: char = '?';
+
+/// PDF literal string holding `bytes`. Parentheses and backslashes are escaped, and bytes
+/// outside printable ASCII are written in octal so the content stream stays ASCII.
+pub fn pdf_literal_string(bytes: &[u8]) -> String {
+    let mut string = String::with_capacity(bytes.len() + 2);
+    string.push('(');
+    for byte in bytes {
+        match byte {
+            b'(' | b')' | b'\\' => {
+                string.push('\\');
+                string.push(*byte as char);
+            }
+            0x20..=0x7E => string.push(*byte as char),
+            _ => string.push_str(&format!("\\{:03o}", byte)),
+        }
+    }
+    string.push(')');
+    string
+}
+
+/// Stroke width of synthetic bold text, relative to the font size.
+pub const SYNTHETIC_BOLD_STROKE: f32 = 0.03;
+/// Horizontal skew of synthetic oblique text, tan(12°).
+pub const SYNTHETIC_OBLIQUE_SKEW: f32 = 0.2126;
+
+/// Styles a font resource emulates because its family has no real face for them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct SyntheticStyle {
+    /// Drawn with fill+stroke rendering mode, see `SYNTHETIC_BOLD_STROKE`.
+    pub bold: bool,
+    /// Drawn with a skewed text matrix, see `SYNTHETIC_OBLIQUE_SKEW`.
+    pub oblique: bool,
+}
+
+impl SyntheticStyle {
+
+    pub fn is_synthetic(&self) -> bool {
+        self.bold || self.oblique
+    }
+
+    /// Style suffix added to `/BaseFont`, e.g. `,BoldItalic`.
+    pub fn suffix(&self) -> &'static str {
+        match (self.bold, self.oblique) {
+            (true, true) => ",BoldItalic",
+            (true, false) => ",Bold",
+            (false, true) => ",Italic",
+            (false, false) => "",
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct Font {
     pub name: String,
+    pub base_font: String,
     pub height: f32,
     pub font_ref: Ref,
+    pub format: FontFormat,
+    pub encoding: FontEncoding,
+    /// Advance width of every code (simple fonts) or glyph (composite and Type 3 fonts), in 1/1000 em.
+    pub widths: Rc<[u16]>,
+    /// Unicode to glyph id mapping of embedded fonts.
+    pub cmap: Rc<HashMap<char, u16>>,
+    /// Glyph id to CID mapping, only filled for CID-keyed CFF fonts.
+    pub cids: Rc<[u16]>,
+    /// `KPX` kerning pairs of Type 1 fonts by glyph id, in 1/1000 em.
+    pub afm_kerning: Rc<HashMap<(u16, u16), i32>>,
+    /// `GPOS` or `kern` pair kerning of embedded sfnt fonts, read once when the font is loaded.
+    pub pair_kerning: Rc<Kerning>,
+    pub descriptor: FontDescriptor,
+    /// Font file data, empty for the standard fonts.
+    pub data: Rc<[u8]>,
+    /// Styles emulated at rendering time, see `Font::synthesize`.
+    pub synthetic: SyntheticStyle,
+    /// Vertical metrics and alternates of the variants made by `Font::vertical`.
+    pub vertical: Option<Rc<VerticalLayout>>,
+    // Codes (simple fonts) or glyph ids (composite fonts) written so far, with the text they stand for.
+    // Shared with the synthetic variants of the font, which embed the same program.
+    used_glyphs: Rc<RefCell<BTreeMap<u16, String>>>,
+    // Glyph ids of Type 3 fonts in the order they were given a code.
+    type3_glyphs: Rc<RefCell<Vec<u16>>>,
+}
+
+impl Font {
+
+    pub fn new_standard(name: &str, font_ref: Ref, standard_font: StandardFont) -> Font {
+        let metrics = standard_font.metrics();
+
+        Font {
+            name: name.to_string(),
+            base_font: standard_font.base_font().to_string(),
+            height: (metrics.ascender - metrics.descender) as f32 / 1000.0,
+            font_ref,
+            format: FontFormat::Standard(standard_font),
+            encoding: FontEncoding::Simple(standard_font.encoding()),
+            widths: Rc::from(standard_font.widths()),
+            cmap: Rc::new(HashMap::new()),
+            cids: Rc::from(Vec::new()),
+            afm_kerning: Rc::new(HashMap::new()),
+            pair_kerning: Rc::new(Kerning::default()),
+            descriptor: FontDescriptor {
+                flags: standard_font.flags(),
+                bbox: metrics.font_bbox,
+                italic_angle: metrics.italic_angle,
+                ascent: metrics.ascender,
+                descent: metrics.descender,
+                cap_height: metrics.cap_height.unwrap_or(metrics.ascender),
+                stem_v: metrics.stem_v,
+            },
+            data: Rc::from(Vec::new()),
+            synthetic: SyntheticStyle::default(),
+            vertical: None,
+            used_glyphs: Rc::new(RefCell::new(BTreeMap::new())),
+            type3_glyphs: Rc::new(RefCell::new(Vec::new())),
+        }
+    }
+
+    pub fn new_embedded(name: &str, font_ref: Ref, data: &[u8], options: &FontOptions) -> Result<Font, String> {
+        // Web fonts are embedded as the TrueType or OpenType font they wrap.
+        let data = woff::to_sfnt(data)?;
+        // Only the selected face of a collection is kept, so it can be embedded on its own.
+        let mut data = sfnt::extract_face(&data, options.index)?;
+        if !options.variations.is_empty() {
+            data = variation::instantiate(&data, &options.variations)?;
+        }
+        let face = ttf_parser::Face::parse(&data, 0)
+            .map_err(|_| "Error parsing font data".to_string())?;
+        // PDF font programs hold CFF or TrueType outlines, there is no way to embed CFF2 ones.
+        if face.raw_face().table(Tag::from_bytes(b"CFF2")).is_some() {
+            return Err("Fonts with CFF2 outlines are not supported".to_string());
+        }
+
+        let units_per_em = face.units_per_em() as f32;
+        let to_thousand = |value: f32| (value * 1000.0 / units_per_em).round() as i32;
+
+        let cids: Vec<u16> = match face.tables().cff {
+            Some(cff) if cff.glyph_cid(ttf_parser::GlyphId(0)).is_some() => (0..face.number_of_glyphs())
+                .map(|glyph_id| cff.glyph_cid(ttf_parser::GlyphId(glyph_id)).unwrap_or(0))
+                .collect(),
+            _ => Vec::new(),
+        };
+        let format = if !cids.is_empty() {
+            FontFormat::CidKeyedCff
+        } else if face.raw_face().table(Tag::from_bytes(b"CFF ")).is_some() {
+            FontFormat::OpenType
+        } else {
+            FontFormat::TrueType
+        };
+        let encoding = match format {
+            // Color glyphs are drawn by Type 3 glyph descriptions, font programs can't hold them.
+            _ if color_glyph::has_color_glyphs(&face) => FontEncoding::Type3,
+            FontFormat::CidKeyedCff => FontEncoding::Identity,
+            _ if options.composite => FontEncoding::Identity,
+            _ => FontEncoding::Simple(Encoding::WinAnsi),
+        };
+
+        let mut cmap = HashMap::new();
+        if let Some(table) = face.tables().cmap {
+            for subtable in table.subtables.into_iter().filter(|subtable| subtable.is_unicode()) {
+                subtable.codepoints(|code_point| {
+                    let glyph_id = subtable.glyph_index(code_point);
+                    if let (Some(c), Some(glyph_id)) = (char::from_u32(code_point), glyph_id) {
+                        cmap.entry(c).or_insert(glyph_id.0);
+                    }
+                });
+            }
+        }
+
+        let advance = |glyph_id: u16| -> u16 {
+            face.glyph_hor_advance(ttf_parser::GlyphId(glyph_id))
+                .map(|advance| to_thousand(advance as f32) as u16)
+                .unwrap_or(0)
+        };
+        let widths: Rc<[u16]> = match encoding {
+            FontEncoding::Simple(encoding) => (0..=255u8)
+                .map(|code| encoding.decode_code(code)
+                    .and_then(|c| cmap.get(&c))
+                    .map(|glyph_id| advance(*glyph_id))
+                    .unwrap_or(0))
+                .collect(),
+            FontEncoding::Identity | FontEncoding::Type3 => (0..face.number_of_glyphs()).map(advance).collect(),
+        };
+
+        // /FontBBox [xMin yMin xMax yMax]
+        let bbox = face.global_bounding_box();
+
+        // /Ascent y /Descent
+        let ascent = face.ascender();
+        let descent = face.descender();
+        let height_in_font_units = (ascent - descent) as f32;
+
+        let flags = match encoding {
+            FontEncoding::Simple(_) => 32,
+            FontEncoding::Identity | FontEncoding::Type3 => 4,
+        };
+
+        let mut glyphs: Vec<u16> = cmap.values().copied().collect();
+        glyphs.sort_unstable();
+        glyphs.dedup();
+        let pair_kerning = Kerning::new(&face, &glyphs);
+
+        Ok(Font {
+            name: name.to_string(),
+            base_font: postscript_name(&face).unwrap_or_else(|| name.replace(" ", "")),
+            height: height_in_font_units / units_per_em,
+            font_ref,
+            format,
+            encoding,
+            widths,
+            cmap: Rc::new(cmap),
+            cids: Rc::from(cids),
+            afm_kerning: Rc::new(HashMap::new()),
+            pair_kerning: Rc::new(pair_kerning),
+            descriptor: FontDescriptor {
+                flags,
+                bbox: [
+                    to_thousand(bbox.x_min as f32),
+                    to_thousand(bbox.y_min as f32),
+                    to_thousand(bbox.x_max as f32),
+                    to_thousand(bbox.y_max as f32),
+                ],
+                italic_angle: face.italic_angle(),
+                ascent: to_thousand(ascent as f32),
+                descent: to_thousand(descent as f32),
+                cap_height: to_thousand(face.capital_height().unwrap_or(ascent) as f32),
+                stem_v: 80,
+            },
+            data: Rc::from(data.as_slice()),
+            synthetic: SyntheticStyle::default(),
+            vertical: None,
+            used_glyphs: Rc::new(RefCell::new(BTreeMap::new())),
+            type3_glyphs: Rc::new(RefCell::new(Vec::new())),
+        })
+    }
+
+    /// Type 1 font from its PFB or PFA program and its AFM metrics. Text is written through
+    /// `/WinAnsiEncoding`, and glyph ids are the indices of the glyphs in the AFM metrics.
+    pub fn new_type1(name: &str, font_ref: Ref, program: &[u8], afm: &str) -> Result<Font, String> {
+        const FIXED_PITCH: u32 = 1;
+        const NONSYMBOLIC: u32 = 1 << 5;
+        const ITALIC: u32 = 1 << 6;
+
+        let program = Type1Program::parse(program)?;
+        let metrics = AfmMetrics::parse(afm)?;
+        if let Some(font_name) = program.font_name().filter(|font_name| *font_name != metrics.font_name) {
+            return Err(format!("The AFM metrics are for {}, not for {}", metrics.font_name, font_name));
+        }
+        // Symbol fonts would need their built-in encoding, text fonts use the standard one.
+        if metrics.encoding_scheme == "FontSpecific" {
+            return Err(format!("Type 1 font {} has a font specific encoding", metrics.font_name));
+        }
+
+        let encoding = Encoding::WinAnsi;
+        let glyph_ids: HashMap<&str, u16> = metrics.char_metrics.iter()
+            .enumerate()
+            .map(|(glyph_id, metric)| (metric.name.as_str(), glyph_id as u16))
+            .collect();
+        let cmap: HashMap<char, u16> = (0..=255u8)
+            .filter_map(|code| Some((encoding.decode_code(code)?, *glyph_ids.get(encoding.glyph_name(code)?)?)))
+            .collect();
+        let widths: Rc<[u16]> = (0..=255u8)
+            .map(|code| encoding.glyph_name(code)
+                .and_then(|glyph_name| metrics.width_of_glyph(glyph_name))
+                .unwrap_or(0.0)
+                .round() as u16)
+            .collect();
+        let afm_kerning = metrics.kern_pairs.iter()
+            .filter_map(|(left, right, adjustment)| {
+                Some(((*glyph_ids.get(left.as_str())?, *glyph_ids.get(right.as_str())?), adjustment.round() as i32))
+            })
+            .collect();
+
+        let mut flags = NONSYMBOLIC;
+        if metrics.is_fixed_pitch {
+            flags |= FIXED_PITCH;
+        }
+        if metrics.italic_angle != 0.0 {
+            flags |= ITALIC;
+        }
+
+        Ok(Font {
+            name: name.to_string(),
+            base_font: sanitize_postscript_name(&metrics.font_name),
+            height: (metrics.ascender - metrics.descender) as f32 / 1000.0,
+            font_ref,
+            format: FontFormat::Type1,
+            encoding: FontEncoding::Simple(encoding),
+            widths,
+            cmap: Rc::new(cmap),
+            cids: Rc::from(Vec::new()),
+            afm_kerning: Rc::new(afm_kerning),
+            pair_kerning: Rc::new(Kerning::default()),
+            descriptor: FontDescriptor {
+                flags,
+                bbox: metrics.font_bbox,
+                italic_angle: metrics.italic_angle,
+                ascent: metrics.ascender,
+                descent: metrics.descender,
+                cap_height: metrics.cap_height.unwrap_or(metrics.ascender),
+                stem_v: metrics.stem_v,
+            },
+            data: Rc::from(program.data),
+            synthetic: SyntheticStyle::default(),
+            vertical: None,
+            used_glyphs: Rc::new(RefCell::new(BTreeMap::new())),
+            type3_glyphs: Rc::new(RefCell::new(Vec::new())),
+        })
+    }
+
+    /// Copy of this font as the resource `name` of a document. The parsed data and metrics
+    /// are shared, the glyphs used so far are not.
+    pub fn with_resource(&self, name: &str, font_ref: Ref) -> Font {
+        Font {
+            name: name.to_string(),
+            font_ref,
+            used_glyphs: Rc::new(RefCell::new(BTreeMap::new())),
+            type3_glyphs: Rc::new(RefCell::new(Vec::new())),
+            ..self.clone()
+        }
+    }
+
+    /// Variant of this font that emulates `synthetic` when rendering. It embeds the same font
+    /// program and is flagged in its resource: the `/BaseFont` of embedded fonts gets a style
+    /// suffix and the descriptor has the ForceBold and Italic flags set.
+    pub fn synthesize(&self, name: &str, font_ref: Ref, synthetic: SyntheticStyle) -> Font {
+        const ITALIC: u32 = 1 << 6;
+        const FORCE_BOLD: u32 = 1 << 18;
+
+        let mut font = self.clone();
+        font.name = name.to_string();
+        font.font_ref = font_ref;
+        font.synthetic = synthetic;
+
+        // Standard fonts keep their name, anything else is not one of the 14 base fonts.
+        if self.is_embedded() {
+            font.base_font = format!("{}{}", self.base_font, synthetic.suffix());
+        }
+        if synthetic.bold {
+            font.descriptor.flags |= FORCE_BOLD;
+        }
+        if synthetic.oblique {
+            font.descriptor.flags |= ITALIC;
+            if font.descriptor.italic_angle == 0.0 {
+                font.descriptor.italic_angle = -12.0;
+            }
+        }
+
+        font
+    }
+
+    /// Variant of this composite font for vertical writing, written with `/Identity-V`. It
+    /// embeds the same font program: text set with it runs top to bottom, upright characters
+    /// with their vertical metrics and `vert` alternates, the other ones sideways with this font.
+    pub fn vertical(&self, name: &str, font_ref: Ref) -> Result<Font, String> {
+        if self.encoding != FontEncoding::Identity {
+            return Err(format!("Vertical writing needs a composite font, {} is not one", self.base_font));
+        }
+        let face = ttf_parser::Face::parse(&self.data, 0)
+            .map_err(|_| "Error parsing font data".to_string())?;
+
+        let mut font = self.clone();
+        font.name = name.to_string();
+        font.font_ref = font_ref;
+        font.vertical = Some(Rc::new(VerticalLayout::new(&face, &self.name)));
+        Ok(font)
+    }
+
+    /// `/BaseFont` and `/FontName` of the font. Subset fonts get a tag of six uppercase letters
+    /// made from their glyphs, e.g. `KQWBXR+NotoSansMono-Regular`. Type 1 programs are never
+    /// subset and keep their name.
+    pub fn pdf_base_font(&self, subset: bool) -> String {
+        if !subset || !self.is_embedded() || self.format == FontFormat::Type1 {
+            return self.base_font.clone();
+        }
+
+        // FNV-1a over the font name and the glyphs of the subset.
+        let mut hash: u32 = 0x811C9DC5;
+        let glyph_bytes = self.subset_glyphs().into_iter().flat_map(u16::to_be_bytes);
+        for byte in self.base_font.bytes().chain(glyph_bytes) {
+            hash = (hash ^ byte as u32).wrapping_mul(0x01000193);
+        }
+        let tag: String = (0..6)
+            .map(|_| {
+                let letter = (b'A' + (hash % 26) as u8) as char;
+                hash /= 26;
+                letter
+            })
+            .collect();
+
+        format!("{}+{}", tag, self.base_font)
+    }
+
+    pub fn is_embedded(&self) -> bool {
+        !matches!(self.format, FontFormat::Standard(_))
+    }
+
+    /// Whether `c` can be drawn with this font: it has a glyph for it and, for simple fonts,
+    /// the encoding has a code for it.
+    pub fn covers(&self, c: char) -> bool {
+        match self.encoding {
+            FontEncoding::Simple(encoding) => encoding.encode_char(c).is_some()
+                && (!self.is_embedded() || self.glyph_id(c).is_some()),
+            FontEncoding::Identity | FontEncoding::Type3 => self.glyph_id(c).is_some(),
+        }
+    }
+
+    pub fn glyph_id(&self, c: char) -> Option<u16> {
+        self.cmap.get(&c).copied()
+    }
+
+    /// Code (simple fonts) or glyph id (composite and Type 3 fonts) that selects `c`.
+    pub fn encode_char(&self, c: char) -> Option<u16> {
+        match self.encoding {
+            FontEncoding::Simple(encoding) => encoding.encode_char(c).map(u16::from),
+            FontEncoding::Identity | FontEncoding::Type3 => self.glyph_id(c),
+        }
+    }
+
+    /// Character drawn for `c`. Simple fonts draw `REPLACEMENT_CHARACTER` (or a space, when
+    /// their encoding lacks it too) for the characters their encoding has no code for; see
+    /// `Text::check_glyphs` to reject such text instead.
+    pub fn drawn_char(&self, c: char) -> char {
+        match self.encoding {
+            FontEncoding::Simple(encoding) if encoding.encode_char(c).is_none() => [REPLACEMENT_CHARACTER, ' '].into_iter()
+                .find(|replacement| encoding.encode_char(*replacement).is_some())
+                .unwrap_or(c),
+            _ => c,
+        }
+    }
+
+    /// Code written in the content stream for glyph `glyph_id` of a composite font.
+    pub fn cid(&self, glyph_id: u16) -> u16 {
+        self.cids.get(glyph_id as usize).copied().unwrap_or(glyph_id)
+    }
+
+    /// Width of `text` set at `size` points, in points, kerning included.
+    pub fn text_width(&self, text: &str, size: f32) -> f32 {
+        self.measure(text, size, true)
+    }
+
+    /// Width of `text` set at `size` points, in points, with or without kerning. Vertical
+    /// fonts return the length of the column instead: the vertical advances of the upright
+    /// glyphs plus the widths of the runs set sideways.
+    pub fn measure(&self, text: &str, size: f32, kerning: bool) -> f32 {
+        let Some(layout) = &self.vertical else {
+            return self.measure_horizontal(text, size, kerning);
+        };
+        vertical::orientation_runs(text).into_iter()
+            .map(|(run, upright)| if upright {
+                let units: u32 = self.glyphs(run).iter()
+                    .map(|(glyph_id, _)| layout.advance(layout.alternate(*glyph_id)) as u32)
+                    .sum();
+                units as f32 * size / 1000.0
+            } else {
+                self.measure_horizontal(run, size, kerning)
+            })
+            .sum()
+    }
+
+    fn measure_horizontal(&self, text: &str, size: f32, kerning: bool) -> f32 {
+        let mut units: i32 = text.chars()
+            .filter_map(|c| self.encode_char(self.drawn_char(c)))
+            .map(|code| self.widths.get(code as usize).copied().unwrap_or(0) as i32)
+            .sum();
+        if kerning {
+            units += self.kerning(text).iter().sum::<i32>();
+        }
+
+        units as f32 * size / 1000.0
+    }
+
+    /// Kerning after each character of `text` (0 for the last one), in 1/1000 em.
+    /// Only embedded fonts have kerning, standard and Type 3 fonts are set without it.
+    pub fn kerning(&self, text: &str) -> Vec<i32> {
+        let chars: Vec<char> = text.chars().collect();
+        let mut adjustments = vec![0; chars.len()];
+        if self.encoding == FontEncoding::Type3 {
+            return adjustments;
+        }
+        if self.format == FontFormat::Type1 {
+            for (index, pair) in chars.windows(2).enumerate() {
+                if let (Some(left), Some(right)) = (self.glyph_id(pair[0]), self.glyph_id(pair[1])) {
+                    adjustments[index] = self.afm_kerning.get(&(left, right)).copied().unwrap_or(0);
+                }
+            }
+            return adjustments;
+        }
+
+        let units_per_em = self.pair_kerning.units_per_em() as f32;
+        for (index, pair) in chars.windows(2).enumerate() {
+            if let (Some(left), Some(right)) = (self.glyph_id(pair[0]), self.glyph_id(pair[1])) {
+                let adjustment = self.pair_kerning.pair(left, right);
+                adjustments[index] = (adjustment as f32 * 1000.0 / units_per_em).round() as i32;
+            }
+        }
+        adjustments
+    }
+
+    /// Text showing operator for `text`: a `Tj` string, or a `TJ` array that moves the kerned
+    /// pairs closer when `kerning` is set.
+    pub fn show_text(&self, text: &str, kerning: bool) -> String {
+        let adjustments = if kerning { self.kerning(text) } else { Vec::new() };
+        if adjustments.iter().all(|adjustment| *adjustment == 0) {
+            return format!("{} Tj", self.pdf_string(text));
+        }
+
+        let mut array = String::from("[");
+        let mut start = 0;
+        for ((index, c), adjustment) in text.char_indices().zip(adjustments) {
+            if adjustment != 0 {
+                let end = index + c.len_utf8();
+                // TJ numbers are subtracted from the advance.
+                array.push_str(&format!("{} {} ", self.pdf_string(&text[start..end]), -adjustment));
+                start = end;
+            }
+        }
+        array.push_str(&format!("{}] TJ", self.pdf_string(&text[start..])));
+        array
+    }
+
+    /// PDF string operand for `text`, remembering the glyphs it uses for subsetting. Simple
+    /// fonts write the codes of their encoding (cp1252 for `/WinAnsiEncoding`) in an escaped
+    /// literal string, see `drawn_char` for the characters it has no code for.
+    pub fn pdf_string(&self, text: &str) -> String {
+        let mut used_glyphs = self.used_glyphs.borrow_mut();
+        match self.encoding {
+            FontEncoding::Simple(encoding) => {
+                let mut codes = Vec::with_capacity(text.len());
+                for c in text.chars().map(|c| self.drawn_char(c)) {
+                    if let Some(code) = encoding.encode_char(c) {
+                        used_glyphs.entry(code as u16).or_insert_with(|| c.to_string());
+                        codes.push(code);
+                    }
+                }
+                pdf_literal_string(&codes)
+            }
+            FontEncoding::Identity | FontEncoding::Type3 => {
+                drop(used_glyphs);
+                self.pdf_glyph_string(&self.glyphs(text))
+            }
+        }
+    }
+
+    /// Glyph ids for the characters of `text`, each with the text it stands for. Characters
+    /// missing from the font fall back to `.notdef`, except joiners and variation selectors,
+    /// which are kept in the text of the glyph before them.
+    pub fn glyphs(&self, text: &str) -> Vec<(u16, String)> {
+        let mut glyphs: Vec<(u16, String)> = Vec::new();
+        for c in text.chars() {
+            match (self.glyph_id(c), glyphs.last_mut()) {
+                (None, Some((_, previous))) if matches!(c, '\u{200C}' | '\u{200D}' | '\u{FE00}'..='\u{FE0F}') => previous.push(c),
+                (glyph_id, _) => glyphs.push((glyph_id.unwrap_or(0), c.to_string())),
+            }
+        }
+        glyphs
+    }
+
+    /// PDF string operand showing glyph ids of a composite font, each with the text it stands
+    /// for in `/ToUnicode` (empty when the glyph is part of a cluster mapped by another one).
+    /// On Type 3 fonts the glyphs must have codes in the same font, see `show_type3_glyphs`.
+    pub fn pdf_glyph_string(&self, glyphs: &[(u16, String)]) -> String {
+        let mut hex = String::with_capacity(glyphs.len() * 4 + 2);
+        hex.push('<');
+        for (glyph_id, text) in glyphs {
+            match self.encoding {
+                FontEncoding::Type3 => hex.push_str(&format!("{:02X}", self.type3_code(*glyph_id).1)),
+                _ => hex.push_str(&format!("{:04X}", self.cid(*glyph_id))),
+            }
+            let mut used_glyphs = self.used_glyphs.borrow_mut();
+            let entry = used_glyphs.entry(*glyph_id).or_default();
+            if entry.is_empty() {
+                entry.clone_from(text);
+            }
+        }
+        hex.push('>');
+        hex
+    }
+
+    /// Type 3 font holding `glyph_id` (0 for the font itself, `n` for the `n`-th extra font
+    /// named by `type3_resource_name`) and its code there. Codes are given on first use.
+    pub fn type3_code(&self, glyph_id: u16) -> (usize, u8) {
+        let mut type3_glyphs = self.type3_glyphs.borrow_mut();
+        let index = match type3_glyphs.iter().position(|used| *used == glyph_id) {
+            Some(index) => index,
+            None => {
+                type3_glyphs.push(glyph_id);
+                type3_glyphs.len() - 1
+            }
+        };
+        (index / 256, (index % 256) as u8)
+    }
+
+    /// Resource name of the `chunk`-th Type 3 font: the font name, then `F1_1`, `F1_2`…
+    pub fn type3_resource_name(&self, chunk: usize) -> String {
+        match chunk {
+            0 => self.name.clone(),
+            _ => format!("{}_{}", self.name, chunk),
+        }
+    }
+
+    /// Glyph ids given a code so far on a Type 3 font, in code order across its fonts.
+    pub fn type3_glyphs(&self) -> Vec<u16> {
+        self.type3_glyphs.borrow().clone()
+    }
+
+    /// Text showing operators for `glyphs` of a Type 3 font set at `size` points. `Tf` switches
+    /// to the extra fonts holding glyphs past the first 256, and back to the font at the end.
+    pub fn show_type3_glyphs(&self, glyphs: &[(u16, String)], size: f32) -> String {
+        let mut operators: Vec<String> = Vec::new();
+        let mut current_chunk = 0;
+        let mut run: Vec<(u16, String)> = Vec::new();
+
+        for glyph in glyphs {
+            let (chunk, _) = self.type3_code(glyph.0);
+            if chunk != current_chunk {
+                if !run.is_empty() {
+                    operators.push(format!("{} Tj", self.pdf_glyph_string(&run)));
+                    run.clear();
+                }
+                operators.push(format!("/{} {} Tf", self.type3_resource_name(chunk), size));
+                current_chunk = chunk;
+            }
+            run.push(glyph.clone());
+        }
+        if !run.is_empty() {
+            operators.push(format!("{} Tj", self.pdf_glyph_string(&run)));
+        }
+        if current_chunk != 0 {
+            operators.push(format!("/{} {} Tf", self.name, size));
+        }
+        operators.join("\n")
+    }
+
+    pub fn used_glyphs(&self) -> BTreeMap<u16, String> {
+        self.used_glyphs.borrow().clone()
+    }
+
+    /// Glyph ids of the font program needed to render the text written so far.
+    pub fn subset_glyphs(&self) -> Vec<u16> {
+        let mut glyphs: Vec<u16> = match self.encoding {
+            FontEncoding::Simple(encoding) => self.used_glyphs.borrow().keys()
+                .filter_map(|code| encoding.decode_code(*code as u8))
+                .filter_map(|c| self.glyph_id(c))
+                .collect(),
+            FontEncoding::Identity | FontEncoding::Type3 => self.used_glyphs.borrow().keys().copied().collect(),
+        };
+        glyphs.push(0);
+        glyphs.sort_unstable();
+        glyphs.dedup();
+        glyphs
+    }
+
+    /// `/Widths` array for the `/FirstChar 32 /LastChar 255` range.
+    pub fn pdf_widths(&self) -> String {
+        let widths: Vec<String> = self.widths[32..].iter().map(|width| width.to_string()).collect();
+        format!("[ {} ]", widths.join(" "))
+    }
+
+    /// `/W` array of a CIDFont, covering the glyphs used so far.
+    pub fn pdf_cid_widths(&self) -> String {
+        let mut widths: Vec<(u16, u16)> = self.used_glyphs.borrow().keys()
+            .map(|glyph_id| (self.cid(*glyph_id), self.widths.get(*glyph_id as usize).copied().unwrap_or(0)))
+            .collect();
+        widths.sort_unstable();
+
+        let mut array = String::from("[");
+        let mut previous: Option<u16> = None;
+        for (cid, width) in widths {
+            if previous.is_some_and(|previous| previous + 1 == cid) {
+                array.push_str(&format!(" {}", width));
+            } else {
+                if previous.is_some() {
+                    array.push(']');
+                }
+                array.push_str(&format!(" {} [{}", cid, width));
+            }
+            previous = Some(cid);
+        }
+        if previous.is_some() {
+            array.push(']');
+        }
+        array.push_str(" ]");
+        array
+    }
+
+    /// `/W2` array of a vertical CIDFont, covering the glyphs used so far: the vertical
+    /// advance (negative, downwards) and the position of the vertical origin of each glyph.
+    pub fn pdf_cid_vertical_widths(&self) -> String {
+        let Some(layout) = &self.vertical else {
+            return String::from("[ ]");
+        };
+        let mut metrics: Vec<(u16, String)> = self.used_glyphs.borrow().keys()
+            .map(|glyph_id| {
+                let width = self.widths.get(*glyph_id as usize).copied().unwrap_or(0);
+                let metrics = format!("-{} {} {}", layout.advance(*glyph_id), width / 2, layout.origin(*glyph_id));
+                (self.cid(*glyph_id), metrics)
+            })
+            .collect();
+        metrics.sort_unstable();
+
+        let mut array = String::from("[");
+        let mut previous: Option<u16> = None;
+        for (cid, metrics) in metrics {
+            if previous.is_some_and(|previous| previous + 1 == cid) {
+                array.push_str(&format!(" {}", metrics));
+            } else {
+                if previous.is_some() {
+                    array.push(']');
+                }
+                array.push_str(&format!(" {} [{}", cid, metrics));
+            }
+            previous = Some(cid);
+        }
+        if previous.is_some() {
+            array.push(']');
+        }
+        array.push_str(" ]");
+        array
+    }
+
+    /// `/ToUnicode` CMap mapping the codes used so far back to their text.
+    pub fn to_unicode_cmap(&self) -> String {
+        let used_glyphs = self.used_glyphs.borrow();
+        match self.encoding {
+            FontEncoding::Simple(_) => unicode_cmap(used_glyphs.iter().map(|(code, text)| (*code, text)), 2),
+            FontEncoding::Identity => unicode_cmap(used_glyphs.iter().map(|(glyph_id, text)| (self.cid(*glyph_id), text)), 4),
+            FontEncoding::Type3 => self.type3_to_unicode_cmap(0),
+        }
+    }
+
+    /// `/ToUnicode` CMap of the `chunk`-th Type 3 font, see `type3_code`.
+    pub fn type3_to_unicode_cmap(&self, chunk: usize) -> String {
+        let used_glyphs = self.used_glyphs.borrow();
+        let type3_glyphs = self.type3_glyphs.borrow();
+        let entries = type3_glyphs.iter()
+            .enumerate()
+            .skip(chunk * 256)
+            .take(256)
+            .filter_map(|(index, glyph_id)| used_glyphs.get(glyph_id).map(|text| ((index % 256) as u16, text)));
+        unicode_cmap(entries, 2)
+    }
+}
+
+// `/ToUnicode` CMap for codes of `code_length` hex digits and the text they stand for.
+fn unicode_cmap<'a>(entries: impl Iterator<Item = (u16, &'a String)>, code_length: usize) -> String {
+    let range = if code_length == 2 { "<00> <FF>" } else { "<0000> <FFFF>" };
+    let mut entries: Vec<(u16, String)> = entries
+        .filter(|(_, text)| !text.is_empty())
+        .map(|(code, text)| (code, text.encode_utf16().map(|unit| format!("{:04X}", unit)).collect()))
+        .collect();
+    entries.sort();
+    entries.dedup_by_key(|(code, _)| *code);
+
+    let mut cmap = String::from("/CIDInit /ProcSet findresource begin\n12 dict begin\nbegincmap\n/CIDSystemInfo << /Registry (Adobe) /Ordering (UCS) /Supplement 0 >> def\n/CMapName /Adobe-Identity-UCS def\n/CMapType 2 def\n");
+    cmap.push_str(&format!("1 begincodespacerange\n{}\nendcodespacerange\n", range));
+    for chunk in entries.chunks(100) {
+        cmap.push_str(&format!("{} beginbfchar\n", chunk.len()));
+        for (code, unicode) in chunk {
+            cmap.push_str(&format!("<{:0width$X}> <{}>\n", code, unicode, width = code_length));
+        }
+        cmap.push_str("endbfchar\n");
+    }
+    cmap.push_str("endcmap\nCMapName currentdict /CMap defineresource pop\nend\nend");
+    cmap
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{list_faces, pdf_literal_string, sanitize_postscript_name, Font, FontEncoding, FontFormat, FontOptions, SyntheticStyle};
+    use crate::reference::Ref;
+    use crate::standard_font::StandardFont;
+    use crate::test_fixtures::{pfb, TYPE1_AFM};
+
+    #[test]
+    fn it_measures_type1_fonts_with_afm_metrics() {
+        let font = Font::new_type1("F1", Ref::new(1), &pfb(), TYPE1_AFM).unwrap();
+        assert_eq!(font.format, FontFormat::Type1);
+        assert!(font.covers('A') && !font.covers('B'));
+        assert_eq!(font.kerning("AV A"), vec![-80, 0, 0, 0]);
+        assert!((font.text_width("AV A", 10.0) - 20.7).abs() < 0.001);
+        assert!((font.measure("AV", 10.0, false) - 13.0).abs() < 0.001);
+        // The whole program is embedded, without a subset tag.
+        assert_eq!(font.pdf_base_font(true), "Test-Regular");
+
+        let other_afm = TYPE1_AFM.replace("Test-Regular", "Other-Regular");
+        assert!(Font::new_type1("F1", Ref::new(1), &pfb(), &other_afm).is_err());
+    }
+
+    #[test]
+    fn it_detects_truetype_outlines() {
+        let font = Font::new_embedded("F1", Ref::new(1), include_bytes!("../assets/NotoSansMono.ttf"), &FontOptions::default()).unwrap();
+        assert_eq!(font.format, FontFormat::TrueType);
+        assert!(matches!(font.encoding, FontEncoding::Simple(_)));
+        assert_eq!(font.widths[b'A' as usize], 600);
+    }
+
+    #[test]
+    fn it_rejects_cff2_outlines() {
+        let error = Font::new_embedded("F1", Ref::new(1), include_bytes!("../assets/AdobeVFPrototype-Subset.otf"), &FontOptions::default()).unwrap_err();
+        assert_eq!(error, "Fonts with CFF2 outlines are not supported");
+    }
+
+    #[test]
+    fn it_tracks_used_glyphs() {
+        let font = Font::new_embedded("F1", Ref::new(1), include_bytes!("../assets/NotoSansMono.ttf"), &FontOptions::default()).unwrap();
+        assert_eq!(font.pdf_string("AA"), "(AA)");
+        let glyph_a = font.glyph_id('A').unwrap();
+        assert_eq!(font.subset_glyphs(), vec![0, glyph_a]);
+    }
+
+    #[test]
+    fn it_encodes_and_escapes_simple_font_strings() {
+        let font = Font::new_standard("F1", Ref::new(1), StandardFont::Helvetica);
+        assert_eq!(font.pdf_string("Total (net) \\ 5€ é"), "(Total \\(net\\) \\\\ 5\\200 \\351)");
+        // Characters outside cp1252 are replaced, and measured as the replacement.
+        assert_eq!(font.pdf_string("漢a"), "(?a)");
+        assert_eq!(font.measure("漢", 10.0, false), font.measure("?", 10.0, false));
+        assert_eq!(font.used_glyphs()[&(b'?' as u16)], "?");
+
+        let symbol = Font::new_standard("F2", Ref::new(2), StandardFont::Symbol);
+        assert_eq!(symbol.pdf_string("α(β)"), "(a\\(b\\))");
+        let dingbats = Font::new_standard("F3", Ref::new(3), StandardFont::ZapfDingbats);
+        assert_eq!(dingbats.pdf_string("A"), "( )");
+        assert_eq!(pdf_literal_string(&[0x00, 0x0A, 0xFF]), "(\\000\\012\\377)");
+    }
+
+    #[test]
+    fn it_names_fonts_after_their_postscript_name() {
+        let font = Font::new_embedded("F1", Ref::new(1), include_bytes!("../assets/NotoSansMono.ttf"), &FontOptions::default()).unwrap();
+        assert_eq!(font.name, "F1");
+        assert_eq!(font.base_font, "NotoSansMono-Regular");
+        assert_eq!(font.pdf_base_font(false), "NotoSansMono-Regular");
+
+        font.pdf_string("A");
+        let subset_name = font.pdf_base_font(true);
+        assert_eq!(subset_name.len(), "ABCDEF+NotoSansMono-Regular".len());
+        assert!(subset_name[..6].chars().all(|c| c.is_ascii_uppercase()));
+        assert!(subset_name.ends_with("+NotoSansMono-Regular"));
+        font.pdf_string("B");
+        assert_ne!(font.pdf_base_font(true), subset_name);
+
+        assert_eq!(sanitize_postscript_name("My Font (Bold)/1"), "MyFontBold1");
+    }
+
+    #[test]
+    fn it_lists_the_face_of_a_single_font() {
+        let faces = list_faces(include_bytes!("../assets/NotoSansMono.ttf")).unwrap();
+        assert_eq!(faces.len(), 1);
+        assert_eq!(faces[0].family, "Noto Sans Mono");
+        assert_eq!(faces[0].postscript_name, "NotoSansMono-Regular");
+        assert!(faces[0].matches("noto sans mono regular"));
+        assert_eq!((faces[0].weight, faces[0].italic), (400, false));
+    }
+
+    #[test]
+    fn it_flags_synthetic_variants() {
+        let font = Font::new_embedded("F1", Ref::new(1), include_bytes!("../assets/NotoSansMono.ttf"), &FontOptions::default()).unwrap();
+        let bold = font.synthesize("F2", Ref::new(2), SyntheticStyle { bold: true, oblique: true });
+        assert_eq!(bold.base_font, "NotoSansMono-Regular,BoldItalic");
+        assert_eq!(bold.descriptor.italic_angle, -12.0);
+        assert_ne!(bold.descriptor.flags & (1 << 18), 0);
+
+        // The variant shares the used glyphs of the font it was made from.
+        bold.pdf_string("A");
+        assert_eq!(font.subset_glyphs(), vec![0, font.glyph_id('A').unwrap()]);
+    }
+
+    #[test]
+    fn it_embeds_instances_of_variable_fonts() {
+        let options = FontOptions { variations: vec![(*b"wdth", 62.5)], ..FontOptions::default() };
+        let condensed = Font::new_embedded("F1", Ref::new(1), include_bytes!("../assets/NotoSansMono.ttf"), &options).unwrap();
+        assert!(condensed.widths[b'A' as usize] < 600);
+        assert!(!ttf_parser::Face::parse(&condensed.data, 0).unwrap().is_variable());
+    }
+
+    #[test]
+    fn it_kerns_pairs_in_output_and_measurement() {
+        let font = Font::new_embedded("F1", Ref::new(1), include_bytes!("../assets/Helvetica.ttf"), &FontOptions::default()).unwrap();
+        let kerning = font.kerning("AVo");
+        assert!(kerning[0] < 0);
+        assert_eq!(kerning[2], 0);
+
+        assert_eq!(font.show_text("AV", true), format!("[(A) {} (V)] TJ", -kerning[0]));
+        assert_eq!(font.show_text("AV", false), "(AV) Tj");
+        assert_eq!(font.show_text("oo", true), "(oo) Tj");
+        assert!(font.text_width("AV", 10.0) < font.measure("AV", 10.0, false));
+    }
+
+    #[test]
+    fn it_gives_type3_codes_in_order_of_use() {
+        let mut font = Font::new_embedded("F1", Ref::new(1), include_bytes!("../assets/NotoSansMono.ttf"), &FontOptions::default()).unwrap();
+        font.encoding = FontEncoding::Type3;
+        let glyph_b = font.glyph_id('B').unwrap();
+        assert_eq!(font.show_type3_glyphs(&font.glyphs("BAB"), 12.0), "<000100> Tj");
+        assert_eq!(font.type3_glyphs()[0], glyph_b);
+        assert!(font.type3_to_unicode_cmap(0).contains("<00> <0042>"));
+
+        // Past 256 glyphs, codes go to a second font.
+        let glyphs: Vec<(u16, String)> = (1..300).map(|glyph_id| (glyph_id, String::new())).collect();
+        let operators = font.show_type3_glyphs(&glyphs, 12.0);
+        assert!(operators.contains("Tj\n/F1_1 12 Tf\n<"));
+        assert!(operators.ends_with("/F1 12 Tf"));
+        assert_eq!(font.type3_code(299), (1, 42));
+    }
 }
@@ -0,0 +1,242 @@
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use ttf_parser::Tag;
+use crate::font::{list_faces, FaceInfo};
+use crate::sfnt::write_sfnt;
+
+/// Font directories of Linux systems, the user ones relative to the home directory.
+pub const SYSTEM_FONT_DIRS: [&str; 3] = ["/usr/share/fonts", "/usr/local/share/fonts", "/usr/X11R6/lib/X11/fonts"];
+pub const USER_FONT_DIRS: [&str; 2] = [".local/share/fonts", ".fonts"];
+
+const FONT_EXTENSIONS: [&str; 6] = ["ttf", "otf", "ttc", "otc", "woff", "woff2"];
+
+// Tables the faces are described with: the names, weight and style, and the tables no face
+// can be parsed without.
+const NAMING_TABLES: [&[u8; 4]; 5] = [b"name", b"OS/2", b"head", b"hhea", b"maxp"];
+
+/// A face found by a `FontDatabase`, with the file it comes from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FontFile {
+    pub path: PathBuf,
+    pub face: FaceInfo,
+}
+
+/// Index of the font faces installed in a set of directories, searched by name without
+/// fontconfig or any other system library.
+#[derive(Debug, Clone, Default)]
+pub struct FontDatabase {
+    fonts: Vec<FontFile>,
+    scanned_dirs: HashSet<PathBuf>,
+}
+
+impl FontDatabase {
+
+    pub fn new() -> FontDatabase {
+        FontDatabase::default()
+    }
+
+    /// Database of the fonts installed in the system and user font directories.
+    pub fn system() -> FontDatabase {
+        let mut database = FontDatabase::new();
+        database.add_system_dirs();
+        database
+    }
+
+    /// Scans the system font directories, `$XDG_DATA_HOME/fonts` and the user font directories
+    /// inside the home directory. Missing directories are skipped.
+    pub fn add_system_dirs(&mut self) {
+        for dir in SYSTEM_FONT_DIRS {
+            self.add_dir(dir);
+        }
+        if let Some(data_home) = std::env::var_os("XDG_DATA_HOME") {
+            self.add_dir(Path::new(&data_home).join("fonts"));
+        }
+        if let Some(home) = std::env::var_os("HOME") {
+            for dir in USER_FONT_DIRS {
+                self.add_dir(Path::new(&home).join(dir));
+            }
+        }
+    }
+
+    /// Scans `dir` and its subdirectories for TrueType and OpenType fonts and collections, and
+    /// for WOFF/WOFF2 web fonts. Files that can't be read or parsed are skipped.
+    pub fn add_dir<P: AsRef<Path>>(&mut self, dir: P) {
+        // Directories reached twice through symbolic links are scanned once.
+        let Ok(dir) = dir.as_ref().canonicalize() else {
+            return;
+        };
+        if !self.scanned_dirs.insert(dir.clone()) {
+            return;
+        }
+        let Ok(entries) = fs::read_dir(&dir) else {
+            return;
+        };
+
+        let mut paths: Vec<PathBuf> = entries.filter_map(|entry| entry.ok().map(|entry| entry.path())).collect();
+        paths.sort();
+        for path in paths {
+            if path.is_dir() {
+                self.add_dir(&path);
+            } else {
+                // Not a font file: nothing to add.
+                let _ = self.add_file(&path);
+            }
+        }
+    }
+
+    /// Adds the faces of the font file at `path`. Only the table directories and the naming
+    /// tables of TrueType and OpenType files are read, web fonts are compressed and read whole.
+    /// The font data is read when a face is added to a document.
+    pub fn add_file<P: AsRef<Path>>(&mut self, path: P) -> Result<(), String> {
+        let path = path.as_ref();
+        let is_font = path.extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| FONT_EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str()));
+        if !is_font {
+            return Err(format!("{} is not a font file", path.display()));
+        }
+
+        for face in read_faces(path)? {
+            self.fonts.push(FontFile {
+                path: path.to_path_buf(),
+                face,
+            });
+        }
+        Ok(())
+    }
+
+    pub fn fonts(&self) -> &[FontFile] {
+        &self.fonts
+    }
+
+    /// Names of the families found, sorted and without duplicates.
+    pub fn families(&self) -> Vec<String> {
+        let mut families: Vec<String> = self.fonts.iter().map(|font| font.face.family.clone()).collect();
+        families.sort();
+        families.dedup();
+        families
+    }
+
+    /// Face whose PostScript name or "Family Style" name is `name`, e.g. `DejaVuSans-Bold` or
+    /// `DejaVu Sans Bold`, ignoring case. A family name alone, like `DejaVu Sans`, finds its
+    /// upright face with the weight closest to regular.
+    pub fn find(&self, name: &str) -> Option<&FontFile> {
+        self.fonts.iter()
+            .find(|font| font.face.matches(name))
+            .or_else(|| self.fonts.iter()
+                .filter(|font| font.face.family.eq_ignore_ascii_case(name))
+                .min_by_key(|font| (font.face.italic, font.face.weight.abs_diff(400))))
+    }
+}
+
+// Faces of the font file at `path`, the faces of sfnt files and collections read out of
+// small fonts holding their naming tables alone.
+fn read_faces(path: &Path) -> Result<Vec<FaceInfo>, String> {
+    let io_error = |error: std::io::Error| format!("Error reading font file {}: {}", path.display(), error);
+    let mut file = File::open(path).map_err(io_error)?;
+    let header = read_at(&mut file, 0, 12).map_err(io_error)?;
+    match &header[..4] {
+        b"wOFF" | b"wOF2" => {
+            let mut data = Vec::new();
+            file.seek(SeekFrom::Start(0)).and_then(|_| file.read_to_end(&mut data)).map_err(io_error)?;
+            list_faces(&data)
+        }
+        b"ttcf" => {
+            let count = u32::from_be_bytes([header[8], header[9], header[10], header[11]]);
+            let offsets = read_at(&mut file, 12, count as u64 * 4).map_err(io_error)?;
+            let mut faces = Vec::new();
+            for (index, offset) in offsets.chunks(4).enumerate() {
+                let offset = u32::from_be_bytes([offset[0], offset[1], offset[2], offset[3]]);
+                let face = read_naming_tables(&mut file, offset as u64).map_err(io_error)?;
+                faces.extend(list_faces(&face)?.into_iter().map(|face| FaceInfo { index: index as u32, ..face }));
+            }
+            Ok(faces)
+        }
+        _ => list_faces(&read_naming_tables(&mut file, 0).map_err(io_error)?),
+    }
+}
+
+// Font made of the naming tables of the face whose table directory starts at `offset`.
+fn read_naming_tables(file: &mut File, offset: u64) -> std::io::Result<Vec<u8>> {
+    let header = read_at(file, offset, 12)?;
+    let sfnt_version = u32::from_be_bytes([header[0], header[1], header[2], header[3]]);
+    let num_tables = u16::from_be_bytes([header[4], header[5]]);
+    let records = read_at(file, offset + 12, num_tables as u64 * 16)?;
+
+    let mut tables = Vec::new();
+    for record in records.chunks(16) {
+        let tag = Tag::from_bytes(&[record[0], record[1], record[2], record[3]]);
+        if NAMING_TABLES.iter().any(|name| Tag::from_bytes(name) == tag) {
+            let table_offset = u32::from_be_bytes([record[8], record[9], record[10], record[11]]);
+            let length = u32::from_be_bytes([record[12], record[13], record[14], record[15]]);
+            tables.push((tag, read_at(file, table_offset as u64, length as u64)?));
+        }
+    }
+    Ok(write_sfnt(sfnt_version, tables))
+}
+
+// Reads `length` bytes at `offset`, failing on reads past the end of the file.
+fn read_at(file: &mut File, offset: u64, length: u64) -> std::io::Result<Vec<u8>> {
+    if offset.saturating_add(length) > file.metadata()?.len() {
+        return Err(std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "table out of the file"));
+    }
+    let mut data = vec![0; length as usize];
+    file.seek(SeekFrom::Start(offset))?;
+    file.read_exact(&mut data)?;
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use ttf_parser::Tag;
+    use super::{read_faces, FontDatabase};
+    use crate::font::list_faces;
+    use crate::test_fixtures::{collection, font_tables};
+
+    #[test]
+    fn it_finds_faces_by_name() {
+        let mut database = FontDatabase::new();
+        database.add_dir(Path::new(env!("CARGO_MANIFEST_DIR")).join("assets"));
+        // Scanning the same directory again adds nothing.
+        database.add_dir(Path::new(env!("CARGO_MANIFEST_DIR")).join("assets/../assets"));
+        assert!(database.families().contains(&"Noto Sans Mono".to_string()));
+        assert_eq!(database.fonts().iter().filter(|font| font.face.family == "Noto Sans Mono").count(), 1);
+
+        let noto = database.find("NotoSansMono-Regular").unwrap();
+        assert!(noto.path.ends_with("NotoSansMono.ttf"));
+        assert_eq!(database.find("noto sans mono regular"), Some(noto));
+        assert_eq!(database.find("Noto Sans Mono"), Some(noto));
+        assert!(database.find("Comic Sans MS").is_none());
+        assert!(database.add_file("Cargo.toml").is_err());
+    }
+
+    #[test]
+    fn it_describes_faces_with_their_naming_tables() {
+        let assets = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets");
+        for name in ["NotoSansMono.ttf", "Helvetica.ttf"] {
+            let data = std::fs::read(assets.join(name)).unwrap();
+            assert_eq!(read_faces(&assets.join(name)).unwrap(), list_faces(&data).unwrap());
+        }
+
+        // Faces of a collection keep their index.
+        let noto = font_tables(include_bytes!("../assets/NotoSansMono.ttf"));
+        let helvetica = font_tables(include_bytes!("../assets/Helvetica.ttf"));
+        let tables: Vec<(Tag, &[u8])> = noto.iter().chain(&helvetica).map(|(tag, data)| (*tag, &data[..])).collect();
+        let noto_face: Vec<usize> = (0..noto.len()).collect();
+        let helvetica_face: Vec<usize> = (noto.len()..tables.len()).collect();
+        let data = collection(&tables, &[&noto_face, &helvetica_face]);
+        let path = std::env::temp_dir().join(format!("rspdf-{}-collection.ttc", std::process::id()));
+        std::fs::write(&path, &data).unwrap();
+        let faces = read_faces(&path);
+        let _ = std::fs::remove_file(&path);
+        let faces = faces.unwrap();
+        assert_eq!(faces, list_faces(&data).unwrap());
+        assert_eq!(faces.iter().map(|face| face.index).collect::<Vec<u32>>(), vec![0, 1]);
+        assert_eq!(faces[0].postscript_name, "NotoSansMono-Regular");
+
+        assert!(read_faces(&assets.join("missing.ttf")).is_err());
+    }
+}
@@ -0,0 +1,172 @@
+use std::ops::Range;
+use std::rc::Rc;
+use crate::font::Font;
+
+/// Chain of fonts used to draw a text: each character is drawn with the first font that
+/// has a glyph for it, starting with the primary font.
+#[derive(Debug, Clone)]
+pub struct FontFallback {
+    fonts: Vec<Rc<Font>>,
+}
+
+/// A run of text drawn with a single font of a `FontFallback`.
+#[derive(Debug, Clone)]
+pub struct FontRun<'a> {
+    pub font: Rc<Font>,
+    pub text: &'a str,
+    /// Byte range of the run in the split text.
+    pub range: Range<usize>,
+}
+
+impl FontFallback {
+
+    pub fn new(primary: Rc<Font>) -> FontFallback {
+        FontFallback {
+            fonts: vec![primary],
+        }
+    }
+
+    /// Adds `font` at the end of the chain.
+    pub fn then(mut self, font: Rc<Font>) -> Self {
+        self.fonts.push(font);
+        self
+    }
+
+    pub fn primary(&self) -> &Rc<Font> {
+        &self.fonts[0]
+    }
+
+    pub fn fonts(&self) -> &[Rc<Font>] {
+        &self.fonts
+    }
+
+    /// First font of the chain that covers `c`.
+    pub fn font_for(&self, c: char) -> Option<&Rc<Font>> {
+        self.fonts.iter().find(|font| font.covers(c))
+    }
+
+    /// Splits `text` into runs of consecutive characters drawn with the same font.
+    ///
+    /// Joiners, variation selectors and combining marks are kept with the character before
+    /// them: the cluster is drawn with the first font that covers all of it, or else with the
+    /// font of its first character. Characters no font covers are drawn with the primary font
+    /// (as `.notdef` boxes), see `missing_chars`.
+    pub fn runs<'a>(&self, text: &'a str) -> Vec<FontRun<'a>> {
+        let mut runs: Vec<FontRun<'a>> = Vec::new();
+
+        for cluster in clusters(text) {
+            let cluster_text = &text[cluster.clone()];
+            let font = self.fonts.iter()
+                .find(|font| cluster_text.chars().all(|c| font.covers(c)))
+                .or_else(|| cluster_text.chars().next().and_then(|c| self.font_for(c)))
+                .unwrap_or(self.primary());
+
+            match runs.last_mut() {
+                Some(run) if Rc::ptr_eq(font, &run.font) => {
+                    run.range.end = cluster.end;
+                    run.text = &text[run.range.clone()];
+                }
+                _ => runs.push(FontRun {
+                    font: Rc::clone(font),
+                    text: cluster_text,
+                    range: cluster,
+                }),
+            }
+        }
+
+        runs
+    }
+
+    /// Characters of `text` that no font of the chain covers, without duplicates.
+    pub fn missing_chars(&self, text: &str) -> Vec<char> {
+        let mut missing: Vec<char> = Vec::new();
+        for c in text.chars() {
+            if !c.is_control() && self.font_for(c).is_none() && !missing.contains(&c) {
+                missing.push(c);
+            }
+        }
+        missing
+    }
+
+    /// Fails when some character of `text` is not covered by any font of the chain.
+    pub fn check(&self, text: &str) -> Result<(), String> {
+        let missing = self.missing_chars(text);
+        if missing.is_empty() {
+            return Ok(());
+        }
+
+        let chars: Vec<String> = missing.iter()
+            .map(|c| format!("'{}' (U+{:04X})", c, *c as u32))
+            .collect();
+        Err(format!("No font covers {}", chars.join(", ")))
+    }
+}
+
+// Byte ranges of a character followed by the joining characters after it.
+fn clusters(text: &str) -> Vec<Range<usize>> {
+    let mut clusters: Vec<Range<usize>> = Vec::new();
+    for (index, c) in text.char_indices() {
+        let end = index + c.len_utf8();
+        match clusters.last_mut() {
+            Some(cluster) if is_joining(c) => cluster.end = end,
+            _ => clusters.push(index..end),
+        }
+    }
+    clusters
+}
+
+// Characters that only make sense together with the one before them.
+fn is_joining(c: char) -> bool {
+    matches!(c,
+        '\u{200C}' | '\u{200D}'
+        | '\u{0300}'..='\u{036F}'
+        | '\u{1AB0}'..='\u{1AFF}'
+        | '\u{20D0}'..='\u{20FF}'
+        | '\u{FE00}'..='\u{FE0F}'
+        | '\u{FE20}'..='\u{FE2F}'
+        | '\u{1F3FB}'..='\u{1F3FF}'
+        | '\u{E0020}'..='\u{E007F}'
+        | '\u{E0100}'..='\u{E01EF}')
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+    use super::FontFallback;
+    use crate::font::{Font, FontOptions};
+    use crate::reference::Ref;
+    use crate::standard_font::StandardFont;
+
+    fn chain() -> FontFallback {
+        let helvetica = Rc::new(Font::new_standard("F1", Ref::new(1), StandardFont::Helvetica));
+        let symbol = Rc::new(Font::new_standard("F2", Ref::new(2), StandardFont::Symbol));
+        FontFallback::new(helvetica).then(symbol)
+    }
+
+    #[test]
+    fn it_splits_runs_by_coverage() {
+        let runs = chain().runs("a = αβ + b");
+        let runs: Vec<(&str, &str)> = runs.iter().map(|run| (run.font.name.as_str(), run.text)).collect();
+        assert_eq!(runs, vec![("F1", "a = "), ("F2", "αβ"), ("F1", " + b")]);
+    }
+
+    #[test]
+    fn it_reports_missing_chars() {
+        let fallback = chain();
+        assert_eq!(fallback.missing_chars("a 漢字 漢"), vec!['漢', '字']);
+        assert_eq!(fallback.check("a 漢"), Err("No font covers '漢' (U+6F22)".to_string()));
+        assert!(fallback.check("αa").is_ok());
+        // Missing characters stay with the primary font.
+        assert_eq!(fallback.runs("漢").first().unwrap().font.name, "F1");
+    }
+
+    #[test]
+    fn it_keeps_combining_marks_with_their_base() {
+        let options = FontOptions { composite: true, ..FontOptions::default() };
+        let noto = Rc::new(Font::new_embedded("F3", Ref::new(3), include_bytes!("../assets/NotoSansMono.ttf"), &options).unwrap());
+        let fallback = chain().then(Rc::clone(&noto));
+        let runs = fallback.runs("e\u{301}");
+        assert_eq!(runs.len(), 1);
+        assert_eq!(runs[0].font.name, "F3");
+    }
+}
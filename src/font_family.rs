@@ -0,0 +1,201 @@
+use std::rc::Rc;
+use crate::font::Font;
+
+/// Weight (100 to 900) and slant of a face inside a `FontFamily`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FontStyle {
+    pub weight: u16,
+    pub italic: bool,
+}
+
+impl FontStyle {
+    pub const REGULAR: FontStyle = FontStyle::new(400, false);
+    pub const BOLD: FontStyle = FontStyle::new(700, false);
+    pub const ITALIC: FontStyle = FontStyle::new(400, true);
+    pub const BOLD_ITALIC: FontStyle = FontStyle::new(700, true);
+
+    pub const fn new(weight: u16, italic: bool) -> FontStyle {
+        FontStyle { weight, italic }
+    }
+}
+
+impl Default for FontStyle {
+    fn default() -> Self {
+        FontStyle::REGULAR
+    }
+}
+
+/// Faces of one typeface registered under a family name.
+#[derive(Debug, Clone)]
+pub struct FontFamily {
+    pub name: String,
+    faces: Vec<(FontStyle, Rc<Font>)>,
+}
+
+impl FontFamily {
+
+    pub fn new(name: &str) -> FontFamily {
+        FontFamily {
+            name: name.to_string(),
+            faces: Vec::new(),
+        }
+    }
+
+    /// Registers `font` for `style`, replacing a face previously registered for the same style.
+    pub fn add_face(&mut self, style: FontStyle, font: Rc<Font>) {
+        self.faces.retain(|(face_style, _)| *face_style != style);
+        self.faces.push((style, font));
+    }
+
+    pub fn faces(&self) -> impl Iterator<Item = (FontStyle, &Rc<Font>)> {
+        self.faces.iter().map(|(style, font)| (*style, font))
+    }
+
+    pub fn query(&self) -> FontQuery<'_> {
+        FontQuery {
+            family: Some(self),
+            style: FontStyle::REGULAR,
+        }
+    }
+
+    /// The registered face closest to `style`, following the CSS font matching rules:
+    /// the slant is matched first, then the nearest weight in the preferred direction.
+    pub fn resolve(&self, style: FontStyle) -> Option<(FontStyle, Rc<Font>)> {
+        let same_slant: Vec<&(FontStyle, Rc<Font>)> = self.faces.iter()
+            .filter(|(face_style, _)| face_style.italic == style.italic)
+            .collect();
+        let candidates = if same_slant.is_empty() {
+            self.faces.iter().collect()
+        } else {
+            same_slant
+        };
+
+        candidates.into_iter()
+            .min_by_key(|(face_style, _)| weight_distance(style.weight, face_style.weight))
+            .map(|(face_style, font)| (*face_style, Rc::clone(font)))
+    }
+}
+
+// Orders candidate weights as CSS Fonts Level 4 does: lighter requests look down first,
+// bolder ones look up first, and 400-500 tries up to 500 before looking down.
+fn weight_distance(desired: u16, available: u16) -> (u8, u16) {
+    let (desired, available) = (desired as i32, available as i32);
+    let below = || (1, (desired - available) as u16);
+    let above = || (1, (available - desired) as u16);
+
+    if available == desired {
+        return (0, 0);
+    }
+
+    if (400..=500).contains(&desired) {
+        if available > desired && available <= 500 {
+            (0, (available - desired) as u16)
+        } else if available < desired {
+            below()
+        } else {
+            (2, (available - desired) as u16)
+        }
+    } else if desired < 400 {
+        if available < desired { (0, (desired - available) as u16) } else { above() }
+    } else if available > desired {
+        (0, (available - desired) as u16)
+    } else {
+        below()
+    }
+}
+
+/// Style request against a family, e.g. `pdf.family("Inter").weight(700).italic().resolve()`.
+#[derive(Debug, Clone, Copy)]
+pub struct FontQuery<'a> {
+    family: Option<&'a FontFamily>,
+    style: FontStyle,
+}
+
+impl<'a> FontQuery<'a> {
+
+    pub fn new(family: Option<&'a FontFamily>) -> FontQuery<'a> {
+        FontQuery {
+            family,
+            style: FontStyle::REGULAR,
+        }
+    }
+
+    pub fn weight(mut self, weight: u16) -> Self {
+        self.style.weight = weight;
+        self
+    }
+
+    pub fn bold(self) -> Self {
+        self.weight(700)
+    }
+
+    pub fn italic(mut self) -> Self {
+        self.style.italic = true;
+        self
+    }
+
+    pub fn style(&self) -> FontStyle {
+        self.style
+    }
+
+    /// Closest matching face, `None` when the family is unknown or has no faces.
+    pub fn resolve(&self) -> Option<Rc<Font>> {
+        self.family?.resolve(self.style).map(|(_, font)| font)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+    use super::{FontFamily, FontStyle};
+    use crate::font::Font;
+    use crate::reference::Ref;
+    use crate::standard_font::StandardFont;
+
+    fn face(standard_font: StandardFont) -> Rc<Font> {
+        Rc::new(Font::new_standard(standard_font.base_font(), Ref::new(1), standard_font))
+    }
+
+    fn family_with(styles: &[(FontStyle, StandardFont)]) -> FontFamily {
+        let mut family = FontFamily::new("Helvetica");
+        for (style, standard_font) in styles {
+            family.add_face(*style, face(*standard_font));
+        }
+        family
+    }
+
+    #[test]
+    fn it_resolves_exact_styles() {
+        let family = family_with(&[
+            (FontStyle::REGULAR, StandardFont::Helvetica),
+            (FontStyle::BOLD, StandardFont::HelveticaBold),
+            (FontStyle::ITALIC, StandardFont::HelveticaOblique),
+            (FontStyle::BOLD_ITALIC, StandardFont::HelveticaBoldOblique),
+        ]);
+        assert_eq!(family.query().resolve().unwrap().base_font, "Helvetica");
+        assert_eq!(family.query().bold().italic().resolve().unwrap().base_font, "Helvetica-BoldOblique");
+        assert_eq!(family.query().weight(700).resolve().unwrap().base_font, "Helvetica-Bold");
+    }
+
+    #[test]
+    fn it_resolves_the_closest_weight() {
+        let family = family_with(&[
+            (FontStyle::new(300, false), StandardFont::Helvetica),
+            (FontStyle::new(600, false), StandardFont::HelveticaBold),
+        ]);
+        // 400 looks up to 500 first, then down.
+        assert_eq!(family.resolve(FontStyle::new(400, false)).unwrap().0.weight, 300);
+        // 700 looks up first, then down.
+        assert_eq!(family.resolve(FontStyle::new(700, false)).unwrap().0.weight, 600);
+        // 200 looks down first, then up.
+        assert_eq!(family.resolve(FontStyle::new(200, false)).unwrap().0.weight, 300);
+        assert_eq!(family.resolve(FontStyle::new(550, false)).unwrap().0.weight, 600);
+    }
+
+    #[test]
+    fn it_falls_back_to_the_other_slant() {
+        let family = family_with(&[(FontStyle::REGULAR, StandardFont::Helvetica)]);
+        assert_eq!(family.query().italic().resolve().unwrap().base_font, "Helvetica");
+        assert!(FontFamily::new("Empty").query().resolve().is_none());
+    }
+}
@@ -0,0 +1,124 @@
+use std::cell::RefCell;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::rc::Rc;
+use crate::font::{Font, FontOptions};
+use crate::reference::Ref;
+
+/// A font parsed by a `FontRegistry`. Handles are cheap to clone and can be added to any
+/// number of documents with `RsPdf::add_registered_font`.
+#[derive(Debug, Clone)]
+pub struct FontHandle {
+    id: usize,
+    key: FontKey,
+    font: Rc<Font>,
+}
+
+impl FontHandle {
+
+    /// Identifies the parsed font inside its registry.
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    /// Identifies the font data and options, the same in every registry.
+    pub(crate) fn key(&self) -> &FontKey {
+        &self.key
+    }
+
+    /// The parsed font, for its metrics. Documents use their own copy of it.
+    pub fn font(&self) -> &Font {
+        &self.font
+    }
+}
+
+// Font data and the options it was parsed with.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct FontKey {
+    data_hash: u64,
+    data_length: usize,
+    index: u32,
+    variations: Vec<([u8; 4], u32)>,
+    composite: bool,
+}
+
+impl FontKey {
+    fn new(data: &[u8], options: &FontOptions) -> FontKey {
+        let mut hasher = DefaultHasher::new();
+        data.hash(&mut hasher);
+        FontKey {
+            data_hash: hasher.finish(),
+            data_length: data.len(),
+            index: options.index,
+            variations: options.variations.iter().map(|(tag, value)| (*tag, value.to_bits())).collect(),
+            composite: options.composite,
+        }
+    }
+}
+
+/// Fonts parsed once and shared by many documents: loading the same data with the same
+/// options again returns the handle of the first load. Share it between documents with
+/// `RsPdf::set_font_registry`, every document has a registry of its own otherwise.
+#[derive(Debug, Default)]
+pub struct FontRegistry {
+    fonts: RefCell<HashMap<FontKey, FontHandle>>,
+}
+
+impl FontRegistry {
+
+    pub fn new() -> FontRegistry {
+        FontRegistry::default()
+    }
+
+    /// Parses `data` with `options`, or returns the handle of a previous load of the same font.
+    pub fn load(&self, data: &[u8], options: &FontOptions) -> Result<FontHandle, String> {
+        let key = FontKey::new(data, options);
+        if let Some(handle) = self.fonts.borrow().get(&key) {
+            return Ok(handle.clone());
+        }
+
+        let font = Font::new_embedded("", Ref::new(0), data, options)?;
+        let mut fonts = self.fonts.borrow_mut();
+        let handle = FontHandle {
+            id: fonts.len(),
+            key: key.clone(),
+            font: Rc::new(font),
+        };
+        fonts.insert(key, handle.clone());
+        Ok(handle)
+    }
+
+    /// Number of fonts parsed so far.
+    pub fn len(&self) -> usize {
+        self.fonts.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fonts.borrow().is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+    use super::FontRegistry;
+    use crate::font::FontOptions;
+
+    #[test]
+    fn it_parses_each_font_once() {
+        let registry = FontRegistry::new();
+        let data = include_bytes!("../assets/NotoSansMono.ttf");
+        let first = registry.load(data, &FontOptions::default()).unwrap();
+        // Same bytes in another buffer.
+        let copy = data.to_vec();
+        let again = registry.load(&copy, &FontOptions::default()).unwrap();
+        assert_eq!(first.id(), again.id());
+        assert!(Rc::ptr_eq(&first.font().widths, &again.font().widths));
+
+        let composite = FontOptions { composite: true, ..FontOptions::default() };
+        assert_ne!(registry.load(data, &composite).unwrap().id(), first.id());
+        assert_eq!(registry.len(), 2);
+        assert!(registry.load(b"not a font", &FontOptions::default()).is_err());
+    }
+}
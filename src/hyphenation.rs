@@ -0,0 +1,212 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// Soft hyphen: an explicit break opportunity, only drawn as a hyphen when a line ends there.
+pub const SOFT_HYPHEN: char = '\u{AD}';
+
+/// Finds where words can be hyphenated with Liang's patterns, as used by TeX.
+///
+/// Patterns are letters with digits between them: `hy3ph` says a break is allowed between
+/// `y` and `p` (odd digits allow a break, even ones forbid it, the highest one wins), and a
+/// dot marks the start or the end of the word. Hyphenation files of TeX (`hyph-en-us.tex`
+/// and the like) hold them in `\patterns{...}`, with exceptions like `as-so-ciate` in
+/// `\hyphenation{...}`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hyphenator {
+    /// Language of the patterns, e.g. `en-us`.
+    pub language: String,
+    /// Fewest characters left before a hyphen and carried after it.
+    pub left_min: usize,
+    pub right_min: usize,
+    patterns: HashMap<Vec<char>, Vec<u8>>,
+    exceptions: HashMap<String, Vec<usize>>,
+    max_pattern_length: usize,
+}
+
+impl Hyphenator {
+
+    /// Reads the patterns and exceptions of a TeX hyphenation file in UTF-8. Text outside
+    /// `\patterns` and `\hyphenation` is ignored, a file without them is read as patterns.
+    pub fn new(language: &str, source: &str) -> Result<Hyphenator, String> {
+        let mut hyphenator = Hyphenator {
+            language: language.to_string(),
+            left_min: 2,
+            right_min: 3,
+            patterns: HashMap::new(),
+            exceptions: HashMap::new(),
+            max_pattern_length: 0,
+        };
+
+        let source: String = source.lines()
+            .map(|line| line.split('%').next().unwrap_or(""))
+            .collect::<Vec<&str>>()
+            .join("\n");
+        let groups = [("\\patterns", false), ("\\hyphenation", true)].into_iter()
+            .flat_map(|(command, exceptions)| tex_groups(&source, command).into_iter().map(move |group| (group, exceptions)))
+            .collect::<Vec<(&str, bool)>>();
+        let groups = if groups.is_empty() { vec![(source.as_str(), false)] } else { groups };
+
+        for (group, exceptions) in groups {
+            for entry in group.split_whitespace() {
+                if exceptions {
+                    hyphenator.add_exception(entry);
+                } else {
+                    hyphenator.add_pattern(entry)?;
+                }
+            }
+        }
+        if hyphenator.patterns.is_empty() && hyphenator.exceptions.is_empty() {
+            return Err(format!("No hyphenation patterns found for {}", language));
+        }
+        Ok(hyphenator)
+    }
+
+    pub fn from_file(language: &str, path: &Path) -> Result<Hyphenator, String> {
+        let source = fs::read_to_string(path)
+            .map_err(|error| format!("Error reading hyphenation file {}: {}", path.display(), error))?;
+        Hyphenator::new(language, &source)
+    }
+
+    pub fn with_min_lengths(mut self, left_min: usize, right_min: usize) -> Hyphenator {
+        self.left_min = left_min;
+        self.right_min = right_min;
+        self
+    }
+
+    fn add_pattern(&mut self, pattern: &str) -> Result<(), String> {
+        let mut letters = Vec::new();
+        let mut values = vec![0];
+        for c in pattern.chars() {
+            match c.to_digit(10) {
+                Some(value) => if let Some(last) = values.last_mut() {
+                    *last = value as u8;
+                },
+                None => {
+                    letters.extend(c.to_lowercase());
+                    values.push(0);
+                }
+            }
+        }
+        if letters.is_empty() {
+            return Err(format!("Invalid hyphenation pattern {}", pattern));
+        }
+        self.max_pattern_length = self.max_pattern_length.max(letters.len());
+        self.patterns.insert(letters, values);
+        Ok(())
+    }
+
+    fn add_exception(&mut self, exception: &str) {
+        let mut word = String::new();
+        let mut breaks = Vec::new();
+        for c in exception.chars() {
+            if c == '-' {
+                breaks.push(word.chars().count());
+            } else {
+                word.extend(c.to_lowercase());
+            }
+        }
+        self.exceptions.insert(word, breaks);
+    }
+
+    /// Byte offsets in `word` where it can be hyphenated. Punctuation around the word is left
+    /// out, and words with soft hyphens only break at them.
+    pub fn hyphenate(&self, word: &str) -> Vec<usize> {
+        if word.contains(SOFT_HYPHEN) {
+            return soft_hyphens(word);
+        }
+        let (Some(start), Some((last, last_char))) = (word.find(char::is_alphabetic), word.char_indices().rfind(|(_, c)| c.is_alphabetic())) else {
+            return Vec::new();
+        };
+        let end = last + last_char.len_utf8();
+        let core = &word[start..end];
+        let offsets: Vec<usize> = core.char_indices().map(|(offset, _)| start + offset).collect();
+        let chars: Vec<char> = core.chars().map(|c| c.to_lowercase().next().unwrap_or(c)).collect();
+        let count = chars.len();
+        if count < self.left_min + self.right_min {
+            return Vec::new();
+        }
+
+        let positions = match self.exceptions.get(&chars.iter().collect::<String>()) {
+            Some(breaks) => breaks.clone(),
+            None => {
+                let padded: Vec<char> = [&['.'], chars.as_slice(), &['.']].concat();
+                // `values[i]` is the value between padded[i - 1] and padded[i].
+                let mut values = vec![0u8; padded.len() + 1];
+                for start in 0..padded.len() {
+                    for end in start + 1..=(start + self.max_pattern_length).min(padded.len()) {
+                        if let Some(pattern) = self.patterns.get(&padded[start..end]) {
+                            for (index, value) in pattern.iter().enumerate() {
+                                values[start + index] = values[start + index].max(*value);
+                            }
+                        }
+                    }
+                }
+                // A break before chars[m] is between padded[m] and padded[m + 1].
+                (1..count).filter(|m| values[m + 1] % 2 == 1).collect()
+            }
+        };
+        positions.into_iter()
+            .filter(|m| *m >= self.left_min && count - m >= self.right_min)
+            .map(|m| offsets[m])
+            .collect()
+    }
+}
+
+/// Byte offsets right after the soft hyphens of `word`, where it may break.
+pub fn soft_hyphens(word: &str) -> Vec<usize> {
+    word.match_indices(SOFT_HYPHEN)
+        .map(|(offset, _)| offset + SOFT_HYPHEN.len_utf8())
+        .filter(|offset| *offset < word.len())
+        .collect()
+}
+
+// Contents of the `{...}` groups following `command`.
+fn tex_groups<'a>(source: &'a str, command: &str) -> Vec<&'a str> {
+    let mut groups = Vec::new();
+    let mut rest = source;
+    while let Some(index) = rest.find(command) {
+        rest = &rest[index + command.len()..];
+        let Some(open) = rest.find('{') else {
+            break;
+        };
+        let Some(close) = rest[open..].find('}') else {
+            break;
+        };
+        groups.push(&rest[open + 1..open + close]);
+        rest = &rest[open + close..];
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Hyphenator;
+    use crate::test_fixtures::HYPHENATION_PATTERNS;
+
+    fn hyphenated(hyphenator: &Hyphenator, word: &str) -> String {
+        let mut text = word.to_string();
+        for offset in hyphenator.hyphenate(word).into_iter().rev() {
+            text.insert(offset, '-');
+        }
+        text
+    }
+
+    #[test]
+    fn it_hyphenates_with_liang_patterns() {
+        let hyphenator = Hyphenator::new("en-us", HYPHENATION_PATTERNS).unwrap();
+        assert_eq!(hyphenated(&hyphenator, "hyphenation"), "hy-phen-ation");
+        assert_eq!(hyphenated(&hyphenator, "(Hyphenation),"), "(Hy-phen-ation),");
+        assert_eq!(hyphenated(&hyphenator, "associate"), "as-so-ciate");
+        // Fragments shorter than the minimum lengths are not split off.
+        assert_eq!(hyphenated(&hyphenator.clone().with_min_lengths(3, 5), "hyphenation"), "hyphen-ation");
+        assert_eq!(hyphenated(&hyphenator, "hy"), "hy");
+    }
+
+    #[test]
+    fn it_breaks_at_soft_hyphens_only() {
+        let hyphenator = Hyphenator::new("en-us", "hy3ph").unwrap();
+        assert_eq!(hyphenator.hyphenate("hyph\u{AD}enation"), vec!["hyph\u{AD}".len()]);
+        assert!(Hyphenator::new("en-us", "\\patterns{}").is_err());
+    }
+}
@@ -0,0 +1,187 @@
+use std::collections::{HashMap, HashSet};
+use ttf_parser::gpos::{PairAdjustment, PositioningSubtable};
+use ttf_parser::kern::Format;
+use ttf_parser::opentype_layout::{ClassDefinition, Coverage};
+use ttf_parser::{GlyphId, Tag};
+
+/// Pair kerning of a font face, from the `kern` feature lookups of the `GPOS` table or, for
+/// fonts without them, from the legacy `kern` table.
+///
+/// The adjustments are copied out of the tables once, when the font is loaded: pairs listed
+/// one by one are kept for the glyphs text is drawn with, class based subtables are kept whole.
+#[derive(Debug, Clone)]
+pub struct Kerning {
+    // Subtables of each lookup. Adjustments of different lookups add up, within a lookup the
+    // first subtable that covers the pair applies. Each `kern` subtable is a lookup of its own.
+    lookups: Vec<Vec<PairTable>>,
+    units_per_em: u16,
+}
+
+#[derive(Debug, Clone)]
+enum PairTable {
+    Pairs(HashMap<(u16, u16), i32>),
+    Classes {
+        coverage: HashSet<u16>,
+        left: HashMap<u16, u16>,
+        right: HashMap<u16, u16>,
+        right_count: u16,
+        // Adjustment of each class pair, row by row.
+        values: Vec<i32>,
+    },
+}
+
+impl PairTable {
+
+    fn get(&self, left: u16, right: u16) -> Option<i32> {
+        match self {
+            PairTable::Pairs(pairs) => pairs.get(&(left, right)).copied(),
+            PairTable::Classes { coverage, left: left_classes, right: right_classes, right_count, values } => {
+                if !coverage.contains(&left) {
+                    return None;
+                }
+                let left_class = left_classes.get(&left).copied().unwrap_or(0) as usize;
+                let right_class = right_classes.get(&right).copied().unwrap_or(0) as usize;
+                values.get(left_class * *right_count as usize + right_class).copied()
+            }
+        }
+    }
+}
+
+impl Default for Kerning {
+    fn default() -> Kerning {
+        Kerning { lookups: Vec::new(), units_per_em: 1000 }
+    }
+}
+
+impl Kerning {
+
+    /// Reads the kerning of `face` for pairs of `glyphs`, the glyphs text can be drawn with.
+    pub fn new(face: &ttf_parser::Face, glyphs: &[u16]) -> Kerning {
+        let mut lookups = Vec::new();
+        if let Some(gpos) = face.tables().gpos {
+            // Every script and language system lists its own `kern` feature, usually pointing
+            // to the same lookups.
+            let mut indices: Vec<u16> = gpos.features.into_iter()
+                .filter(|feature| feature.tag == Tag::from_bytes(b"kern"))
+                .flat_map(|feature| feature.lookup_indices)
+                .collect();
+            indices.sort_unstable();
+            indices.dedup();
+            lookups = indices.into_iter()
+                .filter_map(|index| gpos.lookups.get(index))
+                .map(|lookup| lookup.subtables.into_iter::<PositioningSubtable>()
+                    .filter_map(|subtable| match subtable {
+                        PositioningSubtable::Pair(pair) => Some(pair_table(&pair, glyphs)),
+                        _ => None,
+                    })
+                    .collect::<Vec<PairTable>>())
+                .filter(|lookup| !lookup.is_empty())
+                .collect();
+        }
+
+        if lookups.is_empty() {
+            if let Some(kern) = face.tables().kern {
+                lookups = kern.subtables.into_iter()
+                    .filter(|subtable| subtable.horizontal && !subtable.variable && !subtable.has_cross_stream)
+                    .map(|subtable| {
+                        let pairs = match &subtable.format {
+                            Format::Format0(pairs) => pairs.pairs.into_iter()
+                                .map(|pair| ((pair.left().0, pair.right().0), pair.value as i32))
+                                .collect(),
+                            _ => glyph_pairs(glyphs, glyphs, |left, right| subtable.glyphs_kerning(GlyphId(left), GlyphId(right)).map(i32::from)),
+                        };
+                        vec![PairTable::Pairs(pairs)]
+                    })
+                    .collect();
+            }
+        }
+
+        Kerning { lookups, units_per_em: face.units_per_em() }
+    }
+
+    /// Advance adjustment between `left` and `right`, in font units. Negative values bring
+    /// the glyphs closer.
+    pub fn pair(&self, left: u16, right: u16) -> i32 {
+        self.lookups.iter()
+            .filter_map(|lookup| lookup.iter().find_map(|table| table.get(left, right)))
+            .sum()
+    }
+
+    pub fn units_per_em(&self) -> u16 {
+        self.units_per_em
+    }
+}
+
+fn pair_table(pair: &PairAdjustment, glyphs: &[u16]) -> PairTable {
+    match pair {
+        PairAdjustment::Format1 { coverage, sets } => {
+            let known: HashSet<u16> = glyphs.iter().copied().collect();
+            let lefts: Vec<u16> = covered_glyphs(coverage).into_iter().filter(|glyph| known.contains(glyph)).collect();
+            PairTable::Pairs(glyph_pairs(&lefts, glyphs, |left, right| {
+                let (first, _) = sets.get(coverage.get(GlyphId(left))?)?.get(GlyphId(right))?;
+                Some(first.x_advance as i32)
+            }))
+        }
+        PairAdjustment::Format2 { coverage, classes, matrix } => {
+            let left = class_map(&classes.0);
+            let right = class_map(&classes.1);
+            // The matrix has a row per left class and a column per right class.
+            let left_count = (0..).take_while(|class| matrix.get((*class, 0)).is_some()).count() as u16;
+            let right_count = (0..).take_while(|class| matrix.get((0, *class)).is_some()).count() as u16;
+            let values = (0..left_count)
+                .flat_map(|left_class| (0..right_count).map(move |right_class| (left_class, right_class)))
+                .map(|classes| matrix.get(classes).map_or(0, |(first, _)| first.x_advance as i32))
+                .collect();
+            PairTable::Classes { coverage: covered_glyphs(coverage).into_iter().collect(), left, right, right_count, values }
+        }
+    }
+}
+
+// Adjustments `adjustment` gives to pairs of a glyph of `lefts` and one of `rights`.
+fn glyph_pairs(lefts: &[u16], rights: &[u16], adjustment: impl Fn(u16, u16) -> Option<i32>) -> HashMap<(u16, u16), i32> {
+    let mut pairs = HashMap::new();
+    for left in lefts {
+        for right in rights {
+            if let Some(value) = adjustment(*left, *right) {
+                pairs.insert((*left, *right), value);
+            }
+        }
+    }
+    pairs
+}
+
+fn covered_glyphs(coverage: &Coverage) -> Vec<u16> {
+    match coverage {
+        Coverage::Format1 { glyphs } => glyphs.into_iter().map(|glyph| glyph.0).collect(),
+        Coverage::Format2 { records } => records.into_iter().flat_map(|record| record.start.0..=record.end.0).collect(),
+    }
+}
+
+// Class of the glyphs a class definition lists, the others are in class 0.
+fn class_map(classes: &ClassDefinition) -> HashMap<u16, u16> {
+    match classes {
+        ClassDefinition::Format1 { start, classes } => classes.into_iter().enumerate()
+            .map(|(index, class)| (start.0.wrapping_add(index as u16), class))
+            .collect(),
+        ClassDefinition::Format2 { records } => records.into_iter()
+            .flat_map(|record| (record.start.0..=record.end.0).map(move |glyph| (glyph, record.value)))
+            .collect(),
+        ClassDefinition::Empty => HashMap::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Kerning;
+
+    #[test]
+    fn it_reads_gpos_pair_adjustments() {
+        let face = ttf_parser::Face::parse(include_bytes!("../assets/Helvetica.ttf"), 0).unwrap();
+        let glyph = |c| face.glyph_index(c).unwrap().0;
+        let kerning = Kerning::new(&face, &[glyph('A'), glyph('V'), glyph('T'), glyph('o')]);
+
+        assert!(kerning.pair(glyph('A'), glyph('V')) < 0);
+        assert!(kerning.pair(glyph('T'), glyph('o')) < 0);
+        assert_eq!(kerning.pair(glyph('o'), glyph('o')), 0);
+    }
+}
@@ -1,31 +1,75 @@
 
 
-mod units;
-mod page_format;
-mod page;
-mod meta_data;
-mod text;
-mod reference;
-mod line;
-mod types;
-mod font;
+pub mod units;
+pub mod page_format;
+pub mod page;
+pub mod meta_data;
+pub mod text;
+pub mod paragraph;
+pub mod rich_text;
+pub mod line_breaking;
+pub mod hyphenation;
+pub mod word_dictionary;
+pub mod reference;
+pub mod line;
+pub mod types;
+pub mod font;
+pub mod afm;
+pub mod encoding;
+pub mod standard_font;
+pub mod cff;
+pub mod sfnt;
+pub mod font_family;
+pub mod font_fallback;
+pub mod font_database;
+pub mod font_registry;
+pub mod variation;
+pub mod kerning;
+pub mod bidi;
+pub mod outline;
+pub mod color_glyph;
+pub mod png;
+pub mod woff;
+pub mod type1;
+pub mod vertical;
+#[cfg(feature = "shaping")]
+pub mod shaping;
+#[cfg(test)]
+mod test_fixtures;
 
 use std::collections::HashMap;
-use std::io::{Read};
+use std::io::Write;
 use std::ops::Add;
+use std::path::Path;
 use std::rc::Rc;
 use page::{Page};
-use crate::font::Font;
+use crate::color_glyph::GlyphDrawing;
+use crate::font::{list_faces, Font, FontEncoding, FontFormat, FontOptions, SyntheticStyle};
+use crate::font_database::FontDatabase;
+use crate::font_family::{FontFamily, FontQuery, FontStyle};
+use crate::font_registry::{FontHandle, FontKey, FontRegistry};
 use crate::meta_data::MetaData;
+use crate::png::Bitmap;
 use crate::reference::Ref;
+use crate::standard_font::StandardFont;
+use crate::type1::Type1Program;
 
 #[derive(Debug)]
-struct RsPdf {
+pub struct RsPdf {
     meta_data: MetaData,
     pages: Option<Vec<Page>>,
     current_id: u32,
     fonts: HashMap<String, Rc<Font>>,
+    families: HashMap<String, FontFamily>,
+    synthetic_fonts: HashMap<(String, SyntheticStyle), Rc<Font>>,
+    // Vertical variants by resource name of their horizontal font.
+    vertical_fonts: HashMap<String, Rc<Font>>,
+    font_registry: Rc<FontRegistry>,
+    // Resources of the registry fonts added so far, by font data and options, so a font
+    // keeps its resource when the registry changes.
+    registered_fonts: HashMap<FontKey, Rc<Font>>,
     current_font: u32,
+    subset_fonts: bool,
     document: Vec<u8>,
     xref_offset: Vec<u32>,
 }
@@ -44,9 +88,15 @@ impl RsPdf {
             pages: Option::None,
             current_id: 1,
             current_font: 0,
+            subset_fonts: true,
             document: Vec::from(format!("%PDF-{}\n", version)),
             xref_offset: Vec::new(),
             fonts: HashMap::new(),
+            families: HashMap::new(),
+            synthetic_fonts: HashMap::new(),
+            vertical_fonts: HashMap::new(),
+            font_registry: Rc::new(FontRegistry::new()),
+            registered_fonts: HashMap::new(),
         }
     }
 
@@ -56,79 +106,502 @@ impl RsPdf {
             pages: Option::None,
             current_id: 1,
             current_font: 0,
+            subset_fonts: true,
             document: Vec::new(),
             xref_offset: Vec::new(),
             fonts: HashMap::new(),
+            families: HashMap::new(),
+            synthetic_fonts: HashMap::new(),
+            vertical_fonts: HashMap::new(),
+            font_registry: Rc::new(FontRegistry::new()),
+            registered_fonts: HashMap::new(),
         }
     }
 
     pub fn add_font(&mut self, data: &[u8]) -> Result<Rc<Font>, String> {
+        self.add_font_with_options(data, FontOptions::default())
+    }
+
+    /// Adds a font through the font registry of the document: data already added with the same
+    /// options returns the font added first, so each font is embedded once.
+    pub fn add_font_with_options(&mut self, data: &[u8], options: FontOptions) -> Result<Rc<Font>, String> {
+        let handle = self.font_registry.load(data, &options)?;
+        Ok(self.add_registered_font(&handle))
+    }
+
+    /// Adds a font parsed by a `FontRegistry`, once per document: adding the same handle, or a
+    /// handle of the same font from another registry, again returns the same resource.
+    pub fn add_registered_font(&mut self, handle: &FontHandle) -> Rc<Font> {
+        if let Some(font) = self.registered_fonts.get(handle.key()) {
+            return Rc::clone(font);
+        }
 
         self.current_font += 1;
         let font_name = format!("F{}", self.current_font);
 
         let font_obj_id: Ref = self.alloc_id();
-        let font_obj_id_str: String = font_obj_id.clone().into();
+        let font = Rc::new(handle.font().with_resource(&font_name, font_obj_id));
+        self.fonts.insert(font_name, Rc::clone(&font));
+        self.registered_fonts.insert(handle.key().clone(), Rc::clone(&font));
 
-        let face_obj_id: String = self.alloc_id().into();
-        let font_file_obj_id: String = self.alloc_id().into();
+        font
+    }
 
+    /// Uses `registry` to parse the fonts added from now on, so documents sharing it parse each
+    /// font only once. Fonts added before keep their resource: adding one of them again
+    /// returns it and the font is embedded once.
+    pub fn set_font_registry(&mut self, registry: Rc<FontRegistry>) {
+        self.font_registry = registry;
+    }
 
-        let face = ttf_parser::Face::parse(data, 0)
-            .map_err(|_| "Error parsing font data".to_string())?;
+    pub fn font_registry(&self) -> &Rc<FontRegistry> {
+        &self.font_registry
+    }
 
-        // /FontBBox [xMin yMin xMax yMax]
-        let bbox = face.global_bounding_box();
+    /// Adds the font file at `path`, the first face of collections.
+    pub fn add_font_file<P: AsRef<Path>>(&mut self, path: P) -> Result<Rc<Font>, String> {
+        let path = path.as_ref();
+        let data = std::fs::read(path).map_err(|error| format!("Error reading font file {}: {}", path.display(), error))?;
+        self.add_font(&data)
+    }
 
-        // /Ascent y /Descent
-        let units_per_em = face.units_per_em() as f32;
-        let ascent = face.ascender();
-        let descent = face.descender();
-        let height_in_font_units = (ascent - descent) as f32;
+    /// Adds the installed face named `name` (see `FontDatabase::find`), e.g.
+    /// `pdf.add_installed_font(&FontDatabase::system(), "DejaVu Sans Bold")`.
+    pub fn add_installed_font(&mut self, database: &FontDatabase, name: &str) -> Result<Rc<Font>, String> {
+        let font_file = database.find(name).ok_or_else(|| format!("No installed font named {}", name))?;
+        let data = std::fs::read(&font_file.path)
+            .map_err(|error| format!("Error reading font file {}: {}", font_file.path.display(), error))?;
 
-        let font = Rc::new(Font {
-            font_ref: font_obj_id.clone(),
-            name: font_name.clone(),
-            height: height_in_font_units / units_per_em,
-        });
-        self.fonts.insert(font_name.clone(), Rc::clone(&font));
+        self.add_font_with_options(&data, FontOptions {
+            index: font_file.face.index,
+            ..FontOptions::default()
+        })
+    }
 
-        let cap_height = face.capital_height().unwrap_or(ascent);
-        let italic_angle = face.italic_angle();
+    /// Adds the face of a font collection whose PostScript name or "Family Style" name is `name`.
+    pub fn add_font_by_name(&mut self, data: &[u8], name: &str) -> Result<Rc<Font>, String> {
+        let face = list_faces(data)?
+            .into_iter()
+            .find(|face| face.matches(name))
+            .ok_or_else(|| format!("No face named {} in the font data", name))?;
 
-        self.document.extend_from_slice(format!(
-            "{} obj\n<< /Type /Font /Subtype /TrueType /BaseFont /{} /Encoding /WinAnsiEncoding /FirstChar 32 /LastChar 255 /FontDescriptor {} R >>\nendobj\n",
-            font_obj_id_str,
-            font_name.replace(" ", ""),
-            face_obj_id
-        ).as_bytes());
+        self.add_font_with_options(data, FontOptions {
+            index: face.index,
+            ..FontOptions::default()
+        })
+    }
 
-        self.xref_offset.push(self.document.len() as u32);
+    /// Adds one of the standard 14 fonts. Viewers provide these fonts, so nothing is
+    /// embedded and the metrics come from the bundled AFM files.
+    pub fn add_standard_font(&mut self, standard_font: StandardFont) -> Rc<Font> {
+
+        self.current_font += 1;
+        let font_name = format!("F{}", self.current_font);
+
+        let font_obj_id: Ref = self.alloc_id();
+        let font = Rc::new(Font::new_standard(&font_name, font_obj_id, standard_font));
+        self.fonts.insert(font_name, Rc::clone(&font));
+
+        font
+    }
+
+    /// Adds a PostScript Type 1 font from its program, a `.pfb` or `.pfa` file, and the
+    /// `.afm` file with its metrics. The program is embedded whole as `/FontFile`.
+    pub fn add_type1_font(&mut self, program: &[u8], afm: &str) -> Result<Rc<Font>, String> {
+        let type1_font = Font::new_type1("", Ref::new(0), program, afm)?;
+
+        self.current_font += 1;
+        let font_name = format!("F{}", self.current_font);
+
+        let font_obj_id: Ref = self.alloc_id();
+        let font = Rc::new(type1_font.with_resource(&font_name, font_obj_id));
+        self.fonts.insert(font_name, Rc::clone(&font));
+
+        Ok(font)
+    }
+
+    /// Enables or disables font subsetting. When enabled (the default) only the glyphs
+    /// used by the document are kept in the embedded font programs.
+    pub fn set_font_subsetting(&mut self, subset_fonts: bool) {
+        self.subset_fonts = subset_fonts;
+    }
+
+    pub fn get_font(&self, font_name: &str) -> Option<&Rc<Font>> {
+        self.fonts.get(font_name)
+    }
+
+    /// Adds a resource for `font` that emulates the `synthetic` styles when rendering `Text`.
+    /// Variants are cached, asking twice for the same style returns the same resource.
+    pub fn add_synthetic_font(&mut self, font: &Rc<Font>, synthetic: SyntheticStyle) -> Rc<Font> {
+        if !synthetic.is_synthetic() {
+            return Rc::clone(font);
+        }
+
+        let key = (font.name.clone(), synthetic);
+        if let Some(synthetic_font) = self.synthetic_fonts.get(&key) {
+            return Rc::clone(synthetic_font);
+        }
+
+        self.current_font += 1;
+        let font_name = format!("F{}", self.current_font);
+
+        let font_obj_id: Ref = self.alloc_id();
+        let synthetic_font = Rc::new(font.synthesize(&font_name, font_obj_id, synthetic));
+        self.fonts.insert(font_name, Rc::clone(&synthetic_font));
+        self.synthetic_fonts.insert(key, Rc::clone(&synthetic_font));
+
+        synthetic_font
+    }
+
+    /// Adds the vertical writing variant of the composite font `font` (see `Font::vertical`):
+    /// `Text` set with it runs top to bottom. Asking twice returns the same resource.
+    pub fn add_vertical_font(&mut self, font: &Rc<Font>) -> Result<Rc<Font>, String> {
+        if let Some(vertical_font) = self.vertical_fonts.get(&font.name) {
+            return Ok(Rc::clone(vertical_font));
+        }
+        let mut vertical_font = font.vertical("", Ref::new(0))?;
 
+        self.current_font += 1;
+        vertical_font.name = format!("F{}", self.current_font);
+        vertical_font.font_ref = self.alloc_id();
+
+        let vertical_font = Rc::new(vertical_font);
+        self.fonts.insert(vertical_font.name.clone(), Rc::clone(&vertical_font));
+        self.vertical_fonts.insert(font.name.clone(), Rc::clone(&vertical_font));
+        Ok(vertical_font)
+    }
+
+    /// Resolves `style` against a registered family like `family()` does, but when the family
+    /// has no bold or italic face the closest face is emulated with a synthetic variant.
+    pub fn resolve_font(&mut self, family: &str, style: FontStyle) -> Option<Rc<Font>> {
+        let (face_style, font) = self.families.get(family)?.resolve(style)?;
+
+        let synthetic = SyntheticStyle {
+            bold: style.weight >= 600 && face_style.weight < 600,
+            oblique: style.italic && !face_style.italic,
+        };
+
+        Some(self.add_synthetic_font(&font, synthetic))
+    }
+
+    /// Registers `font` as the `style` face of the family `family`, creating the family if needed.
+    pub fn register_family_face(&mut self, family: &str, style: FontStyle, font: Rc<Font>) {
+        self.families.entry(family.to_string())
+            .or_insert_with(|| FontFamily::new(family))
+            .add_face(style, font);
+    }
+
+    pub fn get_family(&self, family: &str) -> Option<&FontFamily> {
+        self.families.get(family)
+    }
+
+    /// Starts a style request against a registered family, e.g.
+    /// `pdf.family("Inter").weight(700).italic().resolve()`.
+    pub fn family(&self, family: &str) -> FontQuery<'_> {
+        FontQuery::new(self.families.get(family))
+    }
+
+    pub fn meta_data(&self) -> &MetaData {
+        &self.meta_data
+    }
+
+    // `font_files` maps font program data to its stream object and whether the embedded
+    // program is a subset, so fonts sharing a program (synthetic variants) embed it only once.
+    // Returns the extra font resources written for Type 3 fonts with more than 256 glyphs.
+    fn write_font(&mut self, font: &Font, font_files: &mut HashMap<usize, (String, bool)>) -> Vec<(String, Ref)> {
+        if font.encoding == FontEncoding::Type3 {
+            return self.write_type3_font(font);
+        }
+
+        let font_file = match font.format {
+            FontFormat::Standard(_) => None,
+            FontFormat::TrueType => Some("FontFile2"),
+            FontFormat::OpenType | FontFormat::CidKeyedCff => Some("FontFile3"),
+            FontFormat::Type1 => Some("FontFile"),
+        };
+        let data_key = Rc::as_ptr(&font.data) as *const u8 as usize;
+        let already_written = font_files.contains_key(&data_key);
+        // The program is subset first: only a font whose embedded program is the subset gets
+        // the subset tag in its name.
+        let program = font_file.filter(|_| !already_written).map(|_| self.font_program(font));
+        let subset = match &program {
+            Some((_, subset)) => *subset,
+            None => font_files.get(&data_key).is_some_and(|(_, subset)| *subset),
+        };
+
+        let font_obj_id: String = font.font_ref.clone().into();
+        let face_obj_id: String = self.alloc_id().into();
+        let base_font = font.pdf_base_font(subset);
+
+        match font.encoding {
+            FontEncoding::Simple(encoding) => {
+                let subtype = match font.format {
+                    FontFormat::TrueType => "TrueType",
+                    _ => "Type1",
+                };
+                let encoding_entry = encoding.pdf_name()
+                    .map(|name| format!("/Encoding /{} ", name))
+                    .unwrap_or_default();
+
+                self.document.extend_from_slice(format!(
+                    "{} obj\n<< /Type /Font /Subtype /{} /BaseFont /{} {}/FirstChar 32 /LastChar 255 /Widths {} /FontDescriptor {} R >>\nendobj\n",
+                    font_obj_id,
+                    subtype,
+                    base_font,
+                    encoding_entry,
+                    font.pdf_widths(),
+                    face_obj_id
+                ).as_bytes());
+                self.xref_offset.push(self.document.len() as u32);
+            }
+            FontEncoding::Type3 => unreachable!("Type 3 fonts are written by write_type3_font"),
+            FontEncoding::Identity => {
+                let cid_font_obj_id: String = self.alloc_id().into();
+                let to_unicode_obj_id: String = self.alloc_id().into();
+
+                // Vertical variants write top to bottom, with the vertical metrics of the glyphs.
+                let (cmap, vertical_metrics) = match &font.vertical {
+                    Some(layout) => ("Identity-V", format!(
+                        " /DW2 [ {} -{} ] /W2 {}",
+                        layout.default_metrics.1,
+                        layout.default_metrics.0,
+                        font.pdf_cid_vertical_widths()
+                    )),
+                    None => ("Identity-H", String::new()),
+                };
+
+                self.document.extend_from_slice(format!(
+                    "{} obj\n<< /Type /Font /Subtype /Type0 /BaseFont /{} /Encoding /{} /DescendantFonts [ {} R ] /ToUnicode {} R >>\nendobj\n",
+                    font_obj_id,
+                    base_font,
+                    cmap,
+                    cid_font_obj_id,
+                    to_unicode_obj_id
+                ).as_bytes());
+                self.xref_offset.push(self.document.len() as u32);
+
+                let cid_system_info = ttf_parser::RawFace::parse(&font.data, 0).ok()
+                    .and_then(|face| face.table(ttf_parser::Tag::from_bytes(b"CFF ")))
+                    .and_then(cff::cid_system_info)
+                    .unwrap_or_default();
+                // Name-keyed CFF programs use glyph ids as CIDs, TrueType ones map CIDs to glyph ids.
+                let (subtype, cid_to_gid_map) = match font.format {
+                    FontFormat::CidKeyedCff | FontFormat::OpenType => ("CIDFontType0", ""),
+                    _ => ("CIDFontType2", " /CIDToGIDMap /Identity"),
+                };
+
+                self.document.extend_from_slice(format!(
+                    "{} obj\n<< /Type /Font /Subtype /{} /BaseFont /{} /CIDSystemInfo << /Registry ({}) /Ordering ({}) /Supplement {} >> /FontDescriptor {} R /W {}{}{} >>\nendobj\n",
+                    cid_font_obj_id,
+                    subtype,
+                    base_font,
+                    cid_system_info.registry,
+                    cid_system_info.ordering,
+                    cid_system_info.supplement,
+                    face_obj_id,
+                    font.pdf_cid_widths(),
+                    vertical_metrics,
+                    cid_to_gid_map
+                ).as_bytes());
+                self.xref_offset.push(self.document.len() as u32);
+
+                let to_unicode = font.to_unicode_cmap();
+                self.document.extend_from_slice(format!("{} obj\n<< /Length {} >>\nstream\n", to_unicode_obj_id, to_unicode.len()).as_bytes());
+                self.document.extend_from_slice(to_unicode.as_bytes());
+                self.document.extend_from_slice(b"\nendstream\nendobj\n");
+                self.xref_offset.push(self.document.len() as u32);
+            }
+        }
+
+        let descriptor = &font.descriptor;
+        let font_file_obj_id: Option<String> = font_file.map(|_| {
+            font_files.entry(data_key).or_insert_with(|| (self.alloc_id().into(), subset)).0.clone()
+        });
+        let font_file_entry = font_file.zip(font_file_obj_id.as_ref())
+            .map(|(key, id)| format!(" /{} {} R", key, id))
+            .unwrap_or_default();
 
         self.document.extend_from_slice(format!(
-            "{} obj\n<< /Type /FontDescriptor /FontName /{} /Flags 32 /FontBBox [ {} {} {} {} ] /ItalicAngle {} /Ascent {} /Descent {} /CapHeight {} /StemV 80 /FontFile2 {} R >>\nendobj\n",
+            "{} obj\n<< /Type /FontDescriptor /FontName /{} /Flags {} /FontBBox [ {} {} {} {} ] /ItalicAngle {} /Ascent {} /Descent {} /CapHeight {} /StemV {}{} >>\nendobj\n",
             face_obj_id,
-            font_name,
-            bbox.x_min, bbox.y_min, bbox.x_max, bbox.y_max,
-            italic_angle,
-            ascent,
-            descent,
-            cap_height,
-            font_file_obj_id
+            base_font,
+            descriptor.flags,
+            descriptor.bbox[0], descriptor.bbox[1], descriptor.bbox[2], descriptor.bbox[3],
+            descriptor.italic_angle,
+            descriptor.ascent,
+            descriptor.descent,
+            descriptor.cap_height,
+            descriptor.stem_v,
+            font_file_entry
         ).as_bytes());
         self.xref_offset.push(self.document.len() as u32);
 
-        self.document.extend_from_slice(format!("{} obj\n<< /Length {} >>\nstream\n", font_file_obj_id, data.len()).as_bytes());
-        self.document.extend_from_slice(data);
+        let (Some(font_file_obj_id), Some((mut data, _))) = (font_file_obj_id, program) else {
+            return Vec::new();
+        };
+
+        let stream_dict = match font.format {
+            FontFormat::CidKeyedCff => {
+                // CIDFontType0C streams hold the bare CFF table, not the OpenType wrapper.
+                if let Some(cff) = ttf_parser::RawFace::parse(&data, 0).ok()
+                    .and_then(|face| face.table(ttf_parser::Tag::from_bytes(b"CFF "))) {
+                    data = cff.to_vec();
+                }
+                format!("/Subtype /CIDFontType0C /Length {}", data.len())
+            }
+            FontFormat::OpenType => format!("/Subtype /OpenType /Length {}", data.len()),
+            FontFormat::Type1 => {
+                let lengths = Type1Program::parse(&data).map(|program| program.lengths).unwrap_or([data.len(), 0, 0]);
+                format!("/Length {} /Length1 {} /Length2 {} /Length3 {}", data.len(), lengths[0], lengths[1], lengths[2])
+            }
+            _ => format!("/Length {} /Length1 {}", data.len(), data.len()),
+        };
+
+        self.document.extend_from_slice(format!("{} obj\n<< {} >>\nstream\n", font_file_obj_id, stream_dict).as_bytes());
+        self.document.extend_from_slice(&data);
         self.document.extend_from_slice(b"\nendstream\nendobj\n");
         self.xref_offset.push(self.document.len() as u32);
+        Vec::new()
+    }
 
-        Ok(font)
+    // Program of an embedded font, subset to the used glyphs when subsetting is enabled and
+    // the subsetter handles the font, and whether it is that subset.
+    fn font_program(&self, font: &Font) -> (Vec<u8>, bool) {
+        // Type 1 programs are embedded whole.
+        if self.subset_fonts && font.format != FontFormat::Type1 {
+            if let Ok(subset) = subsetter::subset(&font.data, 0, subsetter::Profile::pdf(&font.subset_glyphs())) {
+                return (subset, true);
+            }
+        }
+        (font.data.to_vec(), false)
     }
 
-    pub fn get_font(&self, font_name: &str) -> Option<&Rc<Font>> {
-        self.fonts.get(font_name)
+    // Writes the glyphs given a code on a Type 3 font, 256 per `Type3` font: color layers,
+    // images and outlines drawn by `/CharProcs` streams. The first font uses the resource of
+    // `font`, the extra ones are returned so pages can list them.
+    fn write_type3_font(&mut self, font: &Font) -> Vec<(String, Ref)> {
+        let Ok(face) = ttf_parser::Face::parse(&font.data, 0) else {
+            return Vec::new();
+        };
+        let glyphs = font.type3_glyphs();
+        let mut extra_fonts = Vec::new();
+
+        // An unused font still gets its (empty) font dictionary.
+        for (chunk, chunk_glyphs) in glyphs.chunks(256).chain(glyphs.is_empty().then_some(&[][..])).enumerate() {
+            let font_ref = if chunk == 0 {
+                font.font_ref.clone()
+            } else {
+                let font_ref = self.alloc_id();
+                extra_fonts.push((font.type3_resource_name(chunk), font_ref.clone()));
+                font_ref
+            };
+
+            let mut char_procs = String::new();
+            let mut differences = String::new();
+            let mut widths: Vec<String> = Vec::new();
+            let mut images = String::new();
+            let mut opacities: Vec<u8> = Vec::new();
+
+            for glyph_id in chunk_glyphs {
+                let width = font.widths.get(*glyph_id as usize).copied().unwrap_or(0);
+                // d0 leaves the color to the glyph description, or to the text when it sets none.
+                let mut char_proc = format!("{} 0 d0\n", width);
+                match color_glyph::draw_glyph(&face, *glyph_id) {
+                    Some(GlyphDrawing::Layers { operators, opacities: layer_opacities }) => {
+                        char_proc.push_str(&operators);
+                        for opacity in layer_opacities {
+                            if !opacities.contains(&opacity) {
+                                opacities.push(opacity);
+                            }
+                        }
+                    }
+                    Some(GlyphDrawing::Bitmap { image, transform }) => {
+                        let image_obj_id: String = self.write_image(&image).into();
+                        images.push_str(&format!("/Im{} {} R ", glyph_id, image_obj_id));
+                        char_proc.push_str(&format!(
+                            "q\n{:.2} {:.2} {:.2} {:.2} {:.2} {:.2} cm\n/Im{} Do\nQ\n",
+                            transform[0], transform[1], transform[2], transform[3], transform[4], transform[5],
+                            glyph_id
+                        ));
+                    }
+                    Some(GlyphDrawing::Outline(path)) => char_proc.push_str(&format!("{}f\n", path)),
+                    None => {}
+                }
+
+                let char_proc_obj_id: String = self.alloc_id().into();
+                self.document.extend_from_slice(format!("{} obj\n<< /Length {} >>\nstream\n", char_proc_obj_id, char_proc.len()).as_bytes());
+                self.document.extend_from_slice(char_proc.as_bytes());
+                self.document.extend_from_slice(b"\nendstream\nendobj\n");
+                self.xref_offset.push(self.document.len() as u32);
+
+                char_procs.push_str(&format!("/g{} {} R ", glyph_id, char_proc_obj_id));
+                differences.push_str(&format!(" /g{}", glyph_id));
+                widths.push(width.to_string());
+            }
+            if widths.is_empty() {
+                widths.push("0".to_string());
+            }
+
+            let to_unicode = font.type3_to_unicode_cmap(chunk);
+            let to_unicode_obj_id: String = self.alloc_id().into();
+            self.document.extend_from_slice(format!("{} obj\n<< /Length {} >>\nstream\n", to_unicode_obj_id, to_unicode.len()).as_bytes());
+            self.document.extend_from_slice(to_unicode.as_bytes());
+            self.document.extend_from_slice(b"\nendstream\nendobj\n");
+            self.xref_offset.push(self.document.len() as u32);
+
+            let ext_g_states: String = opacities.iter()
+                .map(|opacity| format!("/A{} << /ca {:.3} >> ", opacity, *opacity as f32 / 255.0))
+                .collect();
+            let descriptor = &font.descriptor;
+            let font_obj_id: String = font_ref.into();
+            self.document.extend_from_slice(format!(
+                "{} obj\n<< /Type /Font /Subtype /Type3 /FontBBox [ {} {} {} {} ] /FontMatrix [0.001 0 0 0.001 0 0] /CharProcs << {}>> /Encoding << /Type /Encoding /Differences [0{}] >> /FirstChar 0 /LastChar {} /Widths [ {} ] /Resources << /XObject << {}>> /ExtGState << {}>> >> /ToUnicode {} R >>\nendobj\n",
+                font_obj_id,
+                descriptor.bbox[0], descriptor.bbox[1], descriptor.bbox[2], descriptor.bbox[3],
+                char_procs,
+                differences,
+                widths.len() - 1,
+                widths.join(" "),
+                images,
+                ext_g_states,
+                to_unicode_obj_id
+            ).as_bytes());
+            self.xref_offset.push(self.document.len() as u32);
+        }
+
+        extra_fonts
+    }
+
+    // Image XObject of 8-bit RGB pixels, Flate compressed, with its alpha channel as a soft mask.
+    fn write_image(&mut self, image: &Bitmap) -> Ref {
+        let compress = |data: &[u8]| -> Vec<u8> {
+            let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(data).and_then(|_| encoder.finish()).unwrap_or_default()
+        };
+
+        let soft_mask = image.alpha.as_ref().map(|alpha| {
+            let mask_ref = self.alloc_id();
+            let data = compress(alpha);
+            self.document.extend_from_slice(format!(
+                "{} obj\n<< /Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /DeviceGray /BitsPerComponent 8 /Filter /FlateDecode /Length {} >>\nstream\n",
+                String::from(mask_ref.clone()), image.width, image.height, data.len()
+            ).as_bytes());
+            self.document.extend_from_slice(&data);
+            self.document.extend_from_slice(b"\nendstream\nendobj\n");
+            self.xref_offset.push(self.document.len() as u32);
+            format!(" /SMask {} R", String::from(mask_ref))
+        });
+
+        let image_ref = self.alloc_id();
+        let data = compress(&image.rgb);
+        self.document.extend_from_slice(format!(
+            "{} obj\n<< /Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /DeviceRGB /BitsPerComponent 8 /Filter /FlateDecode{} /Length {} >>\nstream\n",
+            String::from(image_ref.clone()), image.width, image.height, soft_mask.unwrap_or_default(), data.len()
+        ).as_bytes());
+        self.document.extend_from_slice(&data);
+        self.document.extend_from_slice(b"\nendstream\nendobj\n");
+        self.xref_offset.push(self.document.len() as u32);
+        image_ref
     }
 
     fn alloc_id(&mut self) -> Ref {
@@ -136,7 +609,7 @@ impl RsPdf {
         self.current_id += 1;
         Ref::new(id)
     }
-    pub fn add_page(self: &mut Self, page: Page) {
+    pub fn add_page(&mut self, page: Page) {
         if self.pages.is_none() {
             self.pages = Some(vec![]);
         }
@@ -145,7 +618,15 @@ impl RsPdf {
         }
     }
 
-    pub fn build(self: &mut Self) -> Vec<u8> {
+    pub fn build(&mut self) -> Vec<u8> {
+
+        let mut fonts: Vec<Rc<Font>> = self.fonts.values().cloned().collect();
+        fonts.sort_by_key(|font| font.font_ref.id);
+        let mut font_files = HashMap::new();
+        let mut extra_fonts = Vec::new();
+        for font in fonts {
+            extra_fonts.extend(self.write_font(&font, &mut font_files));
+        }
 
         let catalog_obj_id: String = self.alloc_id().into();
         let pages_obj_id: String = self.alloc_id().into();
@@ -160,19 +641,29 @@ impl RsPdf {
         for (font_name, font_ref) in self.fonts.iter() {
             fonts_dict.push_str(&format!("/{0} {1} 0 R ", font_name.replace(" ", ""), font_ref.font_ref.id));
         }
+        for (font_name, font_ref) in extra_fonts {
+            fonts_dict.push_str(&format!("/{} {} 0 R ", font_name, font_ref.id));
+        }
 
         for page in page {
             let page_obj_id: String = self.alloc_id().into();
             kids.push(page_obj_id.clone().add(" R"));
             let page_size = page.size();
             let content_obj_id: String = self.alloc_id().into();
+            let annotation_ids: Vec<String> = page.links().iter().map(|_| self.alloc_id().into()).collect();
+            let annots = if annotation_ids.is_empty() {
+                String::new()
+            } else {
+                format!("/Annots [{}] ", annotation_ids.iter().map(|id| format!("{} R", id)).collect::<Vec<String>>().join(" "))
+            };
             self.document.extend_from_slice(format!(
-                "{} obj\n<< /Type /Page /Parent {} R /MediaBox [0 0 {} {}] /Contents {} R /Rotate 0 /Resources << /Font << {} >> >> >>\nendobj\n",
+                "{} obj\n<< /Type /Page /Parent {} R /MediaBox [0 0 {} {}] /Contents {} R {}/Rotate 0 /Resources << /Font << {} >> >> >>\nendobj\n",
                 page_obj_id,
                 pages_obj_id,
                 page_size.width_value(),
                 page_size.height_value(),
                 content_obj_id,
+                annots,
                 fonts_dict
             ).as_bytes());
             self.xref_offset.push(self.document.len() as u32);
@@ -181,6 +672,11 @@ impl RsPdf {
             self.document.extend_from_slice(page.content());
             self.document.extend_from_slice(b"\nendstream\nendobj\n");
             self.xref_offset.push(self.document.len() as u32);
+
+            for (id, link) in annotation_ids.iter().zip(page.links()) {
+                self.document.extend_from_slice(format!("{} obj\n{}\nendobj\n", id, rich_text::link_annotation(link)).as_bytes());
+                self.xref_offset.push(self.document.len() as u32);
+            }
         }
 
         self.document.extend_from_slice(format!("{} obj\n<< /Type /Pages /Kids [{}] /Count {} >>\nendobj\n", pages_obj_id, kids.join(" "), page_length).as_bytes());
@@ -206,6 +702,12 @@ mod tests {
     use crate::page::{OrientationType, Page};
     use crate::page_format::{PageFormat};
     use crate::RsPdf;
+    use crate::font::{FontFormat, FontOptions};
+    use crate::font_database::FontDatabase;
+    use crate::font_family::FontStyle;
+    use crate::font_registry::FontRegistry;
+    use crate::standard_font::StandardFont;
+    use crate::rich_text::{RichText, Span};
     use crate::text::{Text};
     use crate::types::{Point, RGB};
     use std::fs::File;
@@ -235,4 +737,295 @@ mod tests {
         file.write_all(pdf.build().as_slice()).unwrap();
 
     }
+
+    #[test]
+    fn it_adds_standard_fonts_without_embedding() {
+        let mut pdf = RsPdf::new("Standard fonts");
+
+        let helvetica = pdf.add_standard_font(StandardFont::Helvetica);
+        let symbol = pdf.add_standard_font(StandardFont::Symbol);
+        let mut page = Page::new(OrientationType::Portrait, PageFormat::A4.get_format());
+        let text = Text::new("Hello World!", Rc::clone(&helvetica), 12, Point(20.0, 800.0), RGB(0, 0, 0));
+        assert!((text.width() - 65.34).abs() < 0.001);
+        page.add_content(text.into());
+        page.add_content(Text::new("abg", Rc::clone(&symbol), 12, Point(20.0, 700.0), RGB(0, 0, 0)).into());
+        pdf.add_page(page);
+
+        let document = String::from_utf8_lossy(&pdf.build()).to_string();
+        assert!(document.contains("/Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding"));
+        assert!(document.contains("/BaseFont /Symbol /FirstChar 32"));
+        assert!(!document.contains("/FontFile"));
+        assert!(document.len() < 8 * 1024);
+    }
+
+    #[test]
+    fn it_writes_vertical_fonts_with_identity_v() {
+        let mut pdf = RsPdf::new("Vertical");
+        let options = FontOptions { composite: true, ..FontOptions::default() };
+        let font = pdf.add_font_with_options(include_bytes!("../assets/NotoSansMono.ttf"), options).unwrap();
+        let vertical = pdf.add_vertical_font(&font).unwrap();
+        assert_eq!(pdf.add_vertical_font(&font).unwrap().name, vertical.name);
+        let standard = pdf.add_standard_font(StandardFont::Helvetica);
+        assert!(pdf.add_vertical_font(&standard).is_err());
+
+        let mut page = Page::new(OrientationType::Portrait, PageFormat::A4.get_format());
+        page.add_content(Text::new("©A", Rc::clone(&vertical), 12, Point(100.0, 800.0), RGB(0, 0, 0)).into());
+        pdf.add_page(page);
+
+        let document = String::from_utf8_lossy(&pdf.build()).to_string();
+        assert!(document.contains("/Encoding /Identity-H"));
+        assert!(document.contains("/Encoding /Identity-V"));
+        assert!(document.contains(&format!("/DW2 [ {} -1000 ] /W2 [", vertical.vertical.as_ref().unwrap().default_metrics.1)));
+        // Both resources share one font program.
+        assert_eq!(document.matches("/FontFile2").count(), 2);
+        assert_eq!(document.matches("/Length1").count(), 1);
+    }
+
+    #[test]
+    fn it_embeds_type1_fonts() {
+        let mut pdf = RsPdf::new("Type 1");
+        let font = pdf.add_type1_font(&crate::test_fixtures::pfb(), crate::test_fixtures::TYPE1_AFM).unwrap();
+        assert!(pdf.add_type1_font(b"not a font", crate::test_fixtures::TYPE1_AFM).is_err());
+        let mut page = Page::new(OrientationType::Portrait, PageFormat::A4.get_format());
+        page.add_content(Text::new("AV", Rc::clone(&font), 12, Point(20.0, 800.0), RGB(0, 0, 0)).into());
+        pdf.add_page(page);
+
+        let document = String::from_utf8_lossy(&pdf.build()).to_string();
+        assert!(document.contains("/Subtype /Type1 /BaseFont /Test-Regular /Encoding /WinAnsiEncoding"));
+        assert!(document.contains("/FontName /Test-Regular "));
+        assert!(document.contains("/FontFile "));
+        assert!(document.contains("/Length 626 /Length1 87 /Length2 7 /Length3 532"));
+    }
+
+    #[test]
+    fn it_subsets_embedded_fonts() {
+        let data = include_bytes!("../assets/NotoSansMono.ttf");
+        let mut pdf = RsPdf::new("Subset");
+        let font = pdf.add_font(data).unwrap();
+        let mut page = Page::new(OrientationType::Portrait, PageFormat::A4.get_format());
+        page.add_content(Text::new("Subset", Rc::clone(&font), 12, Point(20.0, 800.0), RGB(0, 0, 0)).into());
+        pdf.add_page(page);
+
+        let document = pdf.build();
+        assert!(document.len() < data.len() / 2);
+        let document = String::from_utf8_lossy(&document).to_string();
+        assert!(document.contains("/FontFile2"));
+
+        // The resource stays F1, the font is named after its PostScript name with a subset tag.
+        let base_font = font.pdf_base_font(true);
+        assert!(base_font.ends_with("+NotoSansMono-Regular"));
+        assert!(document.contains(&format!("/F1 {} 0 R", font.font_ref.id)));
+        assert!(document.contains(&format!("/BaseFont /{} ", base_font)));
+        assert!(document.contains(&format!("/FontName /{} ", base_font)));
+
+        // A font the subsetter can't handle is embedded whole, named without a subset tag.
+        let data = crate::test_fixtures::outlineless_font();
+        let mut pdf = RsPdf::new("Whole");
+        let font = pdf.add_font(&data).unwrap();
+        let mut page = Page::new(OrientationType::Portrait, PageFormat::A4.get_format());
+        page.add_content(Text::new("Whole", Rc::clone(&font), 12, Point(20.0, 800.0), RGB(0, 0, 0)).into());
+        pdf.add_page(page);
+
+        let document = pdf.build();
+        assert!(document.len() > data.len());
+        let document = String::from_utf8_lossy(&document).to_string();
+        assert!(document.contains(&format!("/Length1 {}", data.len())));
+        assert!(document.contains("/BaseFont /NotoSansMono-Regular "));
+        assert!(document.contains("/FontName /NotoSansMono-Regular "));
+        assert!(!document.contains("+NotoSansMono-Regular"));
+    }
+
+    #[test]
+    fn it_embeds_cff_fonts() {
+        // Name-keyed CFF fonts embed the subset OpenType font.
+        let mut pdf = RsPdf::new("CFF");
+        let font = pdf.add_font(include_bytes!("../assets/TestGSUBOne.otf")).unwrap();
+        assert_eq!(font.format, FontFormat::OpenType);
+        let mut page = Page::new(OrientationType::Portrait, PageFormat::A4.get_format());
+        page.add_content(Text::new("a a", Rc::clone(&font), 12, Point(20.0, 800.0), RGB(0, 0, 0)).into());
+        pdf.add_page(page);
+
+        let document = String::from_utf8_lossy(&pdf.build()).to_string();
+        let base_font = font.pdf_base_font(true);
+        assert!(base_font.ends_with("+TestGSUBOne-Regular"));
+        assert!(document.contains(&format!("/FontName /{} ", base_font)));
+        assert!(document.contains("/FontFile3 "));
+        assert!(document.contains("<< /Subtype /OpenType /Length "));
+        assert!(!document.contains("/FontFile2"));
+
+        // CID-keyed CFF fonts embed the bare CFF table of the subset and are shown by CID:
+        // the fullwidth X is glyph 2 and CID 59047.
+        let data = include_bytes!("../assets/NotoSansCJK-Subset.otf");
+        let mut pdf = RsPdf::new("CID-keyed CFF");
+        let font = pdf.add_font(data).unwrap();
+        assert_eq!(font.format, FontFormat::CidKeyedCff);
+        let mut page = Page::new(OrientationType::Portrait, PageFormat::A4.get_format());
+        page.add_content(Text::new("\u{FF38}", Rc::clone(&font), 12, Point(20.0, 800.0), RGB(0, 0, 0)).into());
+        pdf.add_page(page);
+
+        let document = pdf.build();
+        let text = String::from_utf8_lossy(&document).to_string();
+        let base_font = font.pdf_base_font(true);
+        assert!(base_font.ends_with("+NotoSansCJK-Regular"));
+        assert!(text.contains(&format!("/Subtype /CIDFontType0 /BaseFont /{} ", base_font)));
+        assert!(!text.contains("/CIDToGIDMap"));
+        assert!(text.contains("/W [ 59047 [1000] ]"));
+        assert!(text.contains("<E6A7> <FF38>"));
+        // Shaped text is shown with TJ.
+        assert!(text.contains("<E6A7> Tj") || text.contains("[<E6A7>] TJ"));
+
+        let dict = b"<< /Subtype /CIDFontType0C /Length ";
+        let start = document.windows(dict.len()).position(|window| window == dict).unwrap() + dict.len();
+        let end = start + document[start..].iter().position(|byte| *byte == b' ').unwrap();
+        let length: usize = std::str::from_utf8(&document[start..end]).unwrap().parse().unwrap();
+        let program = &document[end + b" >>\nstream\n".len()..][..length];
+        let cff = ttf_parser::cff::Table::parse(program).unwrap();
+        assert_eq!(cff.glyph_cid(ttf_parser::GlyphId(2)), Some(59047));
+        let face = ttf_parser::RawFace::parse(data, 0).unwrap();
+        assert!(length < face.table(ttf_parser::Tag::from_bytes(b"CFF ")).unwrap().len());
+    }
+
+    #[test]
+    fn it_embeds_each_font_once() {
+        let data = include_bytes!("../assets/NotoSansMono.ttf");
+        let registry = Rc::new(FontRegistry::new());
+
+        let mut first = RsPdf::new("First");
+        first.set_font_registry(Rc::clone(&registry));
+        let font = first.add_font(data).unwrap();
+        assert_eq!(first.add_font(data).unwrap().name, font.name);
+        let mut page = Page::new(OrientationType::Portrait, PageFormat::A4.get_format());
+        page.add_content(Text::new("Once", Rc::clone(&font), 12, Point(20.0, 800.0), RGB(0, 0, 0)).into());
+        first.add_page(page);
+        let document = String::from_utf8_lossy(&first.build()).to_string();
+        assert_eq!(document.matches("/FontFile2").count(), 1);
+
+        // Another document reuses the parsed font, without the glyphs used by the first one.
+        let mut second = RsPdf::new("Second");
+        second.set_font_registry(Rc::clone(&registry));
+        let handle = registry.load(data, &FontOptions::default()).unwrap();
+        let reused = second.add_registered_font(&handle);
+        assert!(Rc::ptr_eq(&reused.cmap, &font.cmap));
+        assert!(reused.used_glyphs().is_empty());
+        assert_eq!(registry.len(), 1);
+
+        // Changing the registry keeps the fonts added before.
+        let mut third = RsPdf::new("Third");
+        let before = third.add_font(data).unwrap();
+        third.set_font_registry(Rc::new(FontRegistry::new()));
+        let after = third.add_font(data).unwrap();
+        assert!(Rc::ptr_eq(&before, &after));
+        let mut page = Page::new(OrientationType::Portrait, PageFormat::A4.get_format());
+        page.add_content(Text::new("Before", Rc::clone(&before), 12, Point(20.0, 800.0), RGB(0, 0, 0)).into());
+        page.add_content(Text::new("After", Rc::clone(&after), 12, Point(20.0, 780.0), RGB(0, 0, 0)).into());
+        third.add_page(page);
+        let document = String::from_utf8_lossy(&third.build()).to_string();
+        assert_eq!(document.matches("/FontFile2").count(), 1);
+        assert_eq!(document.matches("/Length1").count(), 1);
+    }
+
+    #[test]
+    fn it_adds_fonts_from_files() {
+        let assets = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("assets");
+        let mut pdf = RsPdf::new("Files");
+        let font = pdf.add_font_file(assets.join("NotoSansMono.ttf")).unwrap();
+        assert_eq!(font.base_font, "NotoSansMono-Regular");
+        assert!(pdf.add_font_file(assets.join("missing.ttf")).unwrap_err().starts_with("Error reading font file"));
+
+        let mut database = FontDatabase::new();
+        database.add_dir(&assets);
+        // The file was added already, the same resource is returned.
+        assert_eq!(pdf.add_installed_font(&database, "Noto Sans Mono Regular").unwrap().name, font.name);
+        assert!(pdf.add_installed_font(&database, "Comic Sans MS").is_err());
+    }
+
+    #[test]
+    fn it_resolves_faces_of_registered_families() {
+        let mut pdf = RsPdf::new("Families");
+        let regular = pdf.add_standard_font(StandardFont::TimesRoman);
+        let bold = pdf.add_standard_font(StandardFont::TimesBold);
+        pdf.register_family_face("Times", FontStyle::REGULAR, Rc::clone(&regular));
+        pdf.register_family_face("Times", FontStyle::BOLD, Rc::clone(&bold));
+
+        assert_eq!(pdf.family("Times").weight(800).resolve().unwrap().name, bold.name);
+        assert_eq!(pdf.family("Times").italic().resolve().unwrap().name, regular.name);
+        assert!(pdf.family("Inter").resolve().is_none());
+    }
+
+    #[test]
+    fn it_emulates_missing_bold_and_italic_faces() {
+        let mut pdf = RsPdf::new("Synthetic");
+        let regular = pdf.add_font(include_bytes!("../assets/NotoSansMono.ttf")).unwrap();
+        pdf.register_family_face("Noto", FontStyle::REGULAR, Rc::clone(&regular));
+
+        let bold_italic = pdf.resolve_font("Noto", FontStyle::BOLD_ITALIC).unwrap();
+        assert!(bold_italic.synthetic.bold && bold_italic.synthetic.oblique);
+        assert_eq!(pdf.resolve_font("Noto", FontStyle::REGULAR).unwrap().name, regular.name);
+        assert_eq!(pdf.resolve_font("Noto", FontStyle::BOLD_ITALIC).unwrap().name, bold_italic.name);
+
+        let mut page = Page::new(OrientationType::Portrait, PageFormat::A4.get_format());
+        page.add_content(Text::new("Bold", Rc::clone(&bold_italic), 20, Point(20.0, 800.0), RGB(0, 0, 0)).into());
+        page.add_content(Text::new("Regular", Rc::clone(&regular), 20, Point(20.0, 700.0), RGB(0, 0, 0)).into());
+        let content = String::from_utf8_lossy(page.content()).to_string();
+        assert!(content.contains("2 Tr"));
+        assert!(content.contains("1 0 0.2126 1 20.00"));
+        pdf.add_page(page);
+
+        let document = String::from_utf8_lossy(&pdf.build()).to_string();
+        assert!(document.contains(&format!("/BaseFont /{} ", bold_italic.pdf_base_font(true))));
+        assert!(bold_italic.pdf_base_font(true).ends_with(&format!("+{},BoldItalic", regular.base_font)));
+        assert_eq!(document.matches("/FontFile2").count(), 2);
+        assert_eq!(document.matches("/Length1").count(), 1);
+    }
+
+    #[test]
+    fn it_writes_link_annotations() {
+        let mut pdf = RsPdf::new("Links");
+        let font = pdf.add_font(include_bytes!("../assets/NotoSansMono.ttf")).unwrap();
+        let mut page = Page::new(OrientationType::Portrait, PageFormat::A4.get_format());
+        let spans = vec![
+            Span::new("Read the ", Rc::clone(&font), 12),
+            Span::new("manual", Rc::clone(&font), 12).with_underline(true).with_link("https://example.com/manual"),
+        ];
+        page.add_rich_text(RichText::new(spans, Point(20.0, 800.0), 300.0));
+        assert_eq!(page.links().len(), 1);
+        pdf.add_page(page);
+
+        let document = String::from_utf8_lossy(&pdf.build()).to_string();
+        assert!(document.contains("/Annots ["));
+        assert!(document.contains("/Subtype /Link /Rect ["));
+        assert!(document.contains("/A << /S /URI /URI (https://example.com/manual) >>"));
+    }
+
+    #[test]
+    fn it_draws_color_glyphs_on_type3_fonts() {
+        let mut pdf = RsPdf::new("Color glyphs");
+        let layers = pdf.add_font(&crate::test_fixtures::colr_font()).unwrap();
+        let bitmaps = pdf.add_font(&crate::test_fixtures::sbix_font()).unwrap();
+        let glyph_a = layers.glyph_id('A').unwrap();
+        let mut page = Page::new(OrientationType::Portrait, PageFormat::A4.get_format());
+        page.add_content(Text::new("A", Rc::clone(&layers), 12, Point(20.0, 800.0), RGB(0, 0, 0)).into());
+        page.add_content(Text::new("A", Rc::clone(&bitmaps), 12, Point(20.0, 780.0), RGB(0, 0, 0)).into());
+        pdf.add_page(page);
+
+        let document = String::from_utf8_lossy(&pdf.build()).to_string();
+        assert_eq!(document.matches("/Subtype /Type3").count(), 2);
+        let char_procs: Vec<&str> = document.split("600 0 d0\n").skip(1)
+            .map(|char_proc| &char_proc[..char_proc.find("\nendstream").unwrap()])
+            .collect();
+        assert_eq!(char_procs.len(), 2);
+
+        // A palette color per layer, the foreground layer takes the fill color of the text.
+        let layer_proc = char_procs[0];
+        assert!(layer_proc.starts_with("q\n1.000 0.000 0.000 rg\n"));
+        assert!(layer_proc.contains("Q\nq\n0.000 0.000 1.000 rg\n/A128 gs\n"));
+        assert_eq!((layer_proc.matches(" rg\n").count(), layer_proc.matches("f\n").count()), (2, 3));
+        assert!(!layer_proc.ends_with("Q\n"));
+        assert!(document.contains("/ExtGState << /A128 << /ca 0.502 >> >>"));
+
+        // The 2x1 image is scaled from 20 pixels per em, its corner 1 pixel left and 2 below.
+        assert_eq!(char_procs[1], format!("q\n100.00 0.00 0.00 50.00 -50.00 -100.00 cm\n/Im{} Do\nQ\n", glyph_a));
+        assert!(document.contains(&format!("/XObject << /Im{} ", glyph_a)));
+        assert!(document.contains("/Subtype /Image /Width 2 /Height 1 /ColorSpace /DeviceRGB "));
+    }
 }
@@ -0,0 +1,362 @@
+use unicode_linebreak::BreakClass;
+use crate::word_dictionary::WordDictionary;
+
+/// Characters that can't start a line in Japanese (kinsoku shori): closing brackets, small
+/// kana, the prolonged sound mark, iteration marks and punctuation.
+const NO_LINE_START: &str = ")]}〕〉》」』】〙〗〟’”｠»）］｝ゝゞーヽヾ々〻ぁぃぅぇぉっゃゅょゎゕゖァィゥェォッャュョヮヵヶㇰㇱㇲㇳㇴㇵㇶㇷㇸㇹㇺㇻㇼㇽㇾㇿ‐゠–〜～?!‼⁇⁈⁉？！・：；、。，．:;,.";
+/// Characters that can't end a line in Japanese: opening brackets.
+const NO_LINE_END: &str = "([{〔〈《「『【〘〖〝‘“｟«（［｛";
+
+/// Penalty of breaks that are never taken, or forced when negative.
+pub const INFINITE_PENALTY: f32 = 10000.0;
+
+/// Demerits added when two consecutive lines end at flagged penalties, e.g. two hyphens.
+const FLAGGED_DEMERITS: f32 = 3000.0;
+/// Demerits added when a line is much looser or tighter than the line before it.
+const FITNESS_DEMERITS: f32 = 3000.0;
+
+/// Material of a paragraph in the box, glue and penalty model of Knuth and Plass.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Item {
+    /// Something that is never broken nor resized, like a word.
+    Box { width: f32 },
+    /// Space between boxes that can stretch or shrink. A line can break at glue that
+    /// follows a box, the glue is then dropped.
+    Glue { width: f32, stretch: f32, shrink: f32 },
+    /// A possible break costing `penalty`, with `width` added to the line when taken (the
+    /// width of a hyphen). Flagged penalties should not end two lines in a row.
+    Penalty { width: f32, penalty: f32, flagged: bool },
+}
+
+impl Item {
+
+    /// Glue that fills the last line of a paragraph, followed by a forced break.
+    pub fn paragraph_end() -> [Item; 2] {
+        [
+            Item::Glue { width: 0.0, stretch: f32::INFINITY, shrink: 0.0 },
+            Item::Penalty { width: 0.0, penalty: -INFINITE_PENALTY, flagged: false },
+        ]
+    }
+
+    fn is_box(&self) -> bool {
+        matches!(self, Item::Box { .. })
+    }
+}
+
+// A feasible break and the best way of reaching it.
+#[derive(Debug, Clone, Copy)]
+struct Node {
+    position: usize,
+    line: usize,
+    fitness: usize,
+    // Sums of the items before the first box after the break.
+    width: f32,
+    stretch: f32,
+    shrink: f32,
+    demerits: f32,
+    flagged: bool,
+    previous: Option<usize>,
+}
+
+/// Positions of the items where `items` are best broken into lines, minimizing the demerits
+/// of the whole paragraph (the total-fit algorithm of Knuth and Plass). Each line is
+/// `line_width(index)` wide and may stretch its glue up to `tolerance` times (1 is a good
+/// start, larger values accept looser lines).
+///
+/// The items must end with a forced break, see `Item::paragraph_end`, which is the last
+/// position returned. `None` when no set of breaks keeps every line within the tolerance,
+/// for instance because a box is wider than its line.
+pub fn total_fit(items: &[Item], line_width: impl Fn(usize) -> f32, tolerance: f32) -> Option<Vec<usize>> {
+    let mut nodes = vec![Node {
+        position: 0,
+        line: 0,
+        fitness: 1,
+        width: 0.0,
+        stretch: 0.0,
+        shrink: 0.0,
+        demerits: 0.0,
+        flagged: false,
+        previous: None,
+    }];
+    let mut active: Vec<usize> = vec![0];
+    let (mut width, mut stretch, mut shrink) = (0.0, 0.0, 0.0);
+
+    for (position, item) in items.iter().enumerate() {
+        let (penalty, penalty_width, flagged) = match *item {
+            Item::Box { width: box_width } => {
+                width += box_width;
+                continue;
+            }
+            Item::Glue { .. } if position == 0 || !items[position - 1].is_box() => (None, 0.0, false),
+            Item::Glue { .. } => (Some(0.0), 0.0, false),
+            Item::Penalty { penalty, .. } if penalty >= INFINITE_PENALTY => (None, 0.0, false),
+            Item::Penalty { width, penalty, flagged } => (Some(penalty), width, flagged),
+        };
+
+        if let Some(penalty) = penalty {
+            // Best break here for each fitness class, out of the active nodes.
+            let mut candidates: [Option<(f32, usize)>; 4] = [None; 4];
+            active.retain(|&index| {
+                let node = &nodes[index];
+                let natural = width - node.width + penalty_width;
+                let available = line_width(node.line);
+                let ratio = if natural < available {
+                    if stretch - node.stretch > 0.0 { (available - natural) / (stretch - node.stretch) } else { f32::INFINITY }
+                } else if natural > available {
+                    if shrink - node.shrink > 0.0 { (available - natural) / (shrink - node.shrink) } else { f32::NEG_INFINITY }
+                } else {
+                    0.0
+                };
+
+                if (-1.0..=tolerance).contains(&ratio) {
+                    let badness = 100.0 * ratio.abs().powi(3);
+                    let mut demerits = if penalty >= 0.0 {
+                        (1.0 + badness + penalty).powi(2)
+                    } else if penalty > -INFINITE_PENALTY {
+                        (1.0 + badness).powi(2) - penalty.powi(2)
+                    } else {
+                        (1.0 + badness).powi(2)
+                    };
+                    if flagged && node.flagged {
+                        demerits += FLAGGED_DEMERITS;
+                    }
+                    let fitness = match ratio {
+                        ratio if ratio < -0.5 => 0,
+                        ratio if ratio <= 0.5 => 1,
+                        ratio if ratio <= 1.0 => 2,
+                        _ => 3,
+                    };
+                    if node.fitness.abs_diff(fitness) > 1 {
+                        demerits += FITNESS_DEMERITS;
+                    }
+                    let total = node.demerits + demerits;
+                    if candidates[fitness].is_none_or(|(best, _)| total < best) {
+                        candidates[fitness] = Some((total, index));
+                    }
+                }
+                // Lines from this node can only get longer: drop it once they are overfull.
+                ratio >= -1.0 && penalty > -INFINITE_PENALTY
+            });
+
+            let (after_width, after_stretch, after_shrink) = sums_after_break(items, position, (width, stretch, shrink));
+            for (fitness, candidate) in candidates.iter().enumerate() {
+                if let Some((demerits, previous)) = *candidate {
+                    nodes.push(Node {
+                        position,
+                        line: nodes[previous].line + 1,
+                        fitness,
+                        width: after_width,
+                        stretch: after_stretch,
+                        shrink: after_shrink,
+                        demerits,
+                        flagged,
+                        previous: Some(previous),
+                    });
+                    active.push(nodes.len() - 1);
+                }
+            }
+            if active.is_empty() {
+                return None;
+            }
+        }
+
+        if let Item::Glue { width: glue_width, stretch: glue_stretch, shrink: glue_shrink } = *item {
+            width += glue_width;
+            stretch += glue_stretch;
+            shrink += glue_shrink;
+        }
+    }
+
+    let last = items.len().checked_sub(1)?;
+    let best = active.into_iter()
+        .filter(|index| nodes[*index].position == last)
+        .min_by(|a, b| nodes[*a].demerits.total_cmp(&nodes[*b].demerits))?;
+
+    let mut breaks = Vec::new();
+    let mut node = Some(best);
+    while let Some(index) = node.filter(|index| *index != 0) {
+        breaks.push(nodes[index].position);
+        node = nodes[index].previous;
+    }
+    breaks.reverse();
+    Some(breaks)
+}
+
+/// Positions of the items where `items` are broken filling one line at a time: each line
+/// ends at the last break that still fits in `line_width(index)`. Lines without such a break,
+/// like one holding a single long word, end at their first break and overflow.
+///
+/// The items must end with a forced break, see `Item::paragraph_end`.
+pub fn first_fit(items: &[Item], line_width: impl Fn(usize) -> f32) -> Vec<usize> {
+    let mut breaks = Vec::new();
+    let mut line_start = 0.0;
+    let mut width = 0.0;
+    let mut candidate: Option<(usize, f32)> = None;
+    // Breaking at the candidate only helps when some box comes after it.
+    let mut box_since_candidate = false;
+
+    for (position, item) in items.iter().enumerate() {
+        let break_width = match *item {
+            Item::Box { width: box_width } => {
+                width += box_width;
+                box_since_candidate = true;
+                continue;
+            }
+            Item::Glue { .. } if position == 0 || !items[position - 1].is_box() => None,
+            Item::Glue { .. } => Some(0.0),
+            Item::Penalty { penalty, .. } if penalty >= INFINITE_PENALTY => None,
+            Item::Penalty { width, .. } => Some(width),
+        };
+
+        if let Some(break_width) = break_width {
+            // Some slack so lines that fit exactly are not broken by rounding errors.
+            let fits = |line_start: f32, line: usize| width - line_start + break_width <= line_width(line) + 0.001;
+            if let Some((previous, after)) = candidate.filter(|_| box_since_candidate && !fits(line_start, breaks.len())) {
+                breaks.push(previous);
+                line_start = after;
+            }
+            let after = sums_after_break(items, position, (width, 0.0, 0.0)).0;
+            candidate = Some((position, after));
+            box_since_candidate = false;
+            if matches!(item, Item::Penalty { penalty, .. } if *penalty <= -INFINITE_PENALTY) {
+                breaks.push(position);
+                line_start = after;
+                candidate = None;
+            }
+        }
+
+        if let Item::Glue { width: glue_width, .. } = *item {
+            width += glue_width;
+        }
+    }
+    breaks
+}
+
+/// Byte offsets of `text` where a line may break, each the offset of the character after the
+/// break: the opportunities of the Unicode line breaking algorithm (UAX #14), such as spaces,
+/// between ideographs or after hyphens and slashes, less those the kinsoku rules of Japanese
+/// forbid. The start and the end of the text are not included.
+///
+/// Thai and the other scripts written without spaces between words (class SA) are only
+/// broken at spaces and zero width spaces, as finding their words needs a dictionary, see
+/// `break_opportunities_with_dictionary`.
+pub fn break_opportunities(text: &str) -> Vec<usize> {
+    unicode_linebreak::linebreaks(text)
+        .map(|(offset, _)| offset)
+        .filter(|offset| *offset > 0 && *offset < text.len())
+        .filter(|offset| {
+            let before = text[..*offset].chars().next_back().is_some_and(|c| NO_LINE_END.contains(c));
+            let after = text[*offset..].chars().next().is_some_and(|c| NO_LINE_START.contains(c));
+            !before && !after
+        })
+        .collect()
+}
+
+/// Break opportunities of `text` (see `break_opportunities`) with the text written without
+/// spaces between words (class SA, like Thai) also broken between the words of `dictionary`.
+pub fn break_opportunities_with_dictionary(text: &str, dictionary: &WordDictionary) -> Vec<usize> {
+    let mut opportunities = break_opportunities(text);
+    let mut run_start = None;
+    for (offset, c) in text.char_indices().chain([(text.len(), ' ')]) {
+        let complex_context = offset < text.len() && unicode_linebreak::break_property(c as u32) == BreakClass::ComplexContext;
+        match run_start {
+            None if complex_context => run_start = Some(offset),
+            Some(start) if !complex_context => {
+                opportunities.extend(dictionary.word_breaks(&text[start..offset]).into_iter().map(|word_start| start + word_start));
+                run_start = None;
+            }
+            _ => {}
+        }
+    }
+    opportunities.sort_unstable();
+    opportunities.dedup();
+    opportunities
+}
+
+// Running sums once the glue and penalties that follow a break at `position` are dropped.
+fn sums_after_break(items: &[Item], position: usize, (mut width, mut stretch, mut shrink): (f32, f32, f32)) -> (f32, f32, f32) {
+    for (index, item) in items.iter().enumerate().skip(position) {
+        match *item {
+            Item::Box { .. } => break,
+            Item::Glue { width: glue_width, stretch: glue_stretch, shrink: glue_shrink } => {
+                width += glue_width;
+                stretch += glue_stretch;
+                shrink += glue_shrink;
+            }
+            Item::Penalty { penalty, .. } if penalty <= -INFINITE_PENALTY && index > position => break,
+            Item::Penalty { .. } => {}
+        }
+    }
+    (width, stretch, shrink)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{break_opportunities, break_opportunities_with_dictionary, first_fit, total_fit, Item};
+    use crate::test_fixtures::THAI_WORDS;
+    use crate::word_dictionary::WordDictionary;
+
+    // Words of the given widths separated by glue 1 wide, stretching by 1 and shrinking by 0.5.
+    fn items(words: &[f32]) -> Vec<Item> {
+        let mut items = Vec::new();
+        for (index, width) in words.iter().enumerate() {
+            if index > 0 {
+                items.push(Item::Glue { width: 1.0, stretch: 1.0, shrink: 0.5 });
+            }
+            items.push(Item::Box { width: *width });
+        }
+        items.extend(Item::paragraph_end());
+        items
+    }
+
+    #[test]
+    fn it_balances_lines_over_the_whole_paragraph() {
+        // Filling lines one at a time gives "4 4" and a very loose "1 5". Over the whole
+        // paragraph, a tight "4 4 1" and a last line "5 4" cost less.
+        let items = items(&[4.0, 4.0, 1.0, 5.0, 4.0]);
+        assert_eq!(total_fit(&items, |_| 10.0, 4.0), Some(vec![5, 10]));
+        // Without loose lines, the greedy breaks are not even considered.
+        assert_eq!(total_fit(&items, |_| 10.0, 1.0), Some(vec![5, 10]));
+        // The width can change from line to line.
+        assert_eq!(total_fit(&items, |line| if line == 0 { 9.0 } else { 12.0 }, 4.0), Some(vec![3, 10]));
+    }
+
+    #[test]
+    fn it_fills_lines_one_at_a_time() {
+        let items = items(&[4.0, 4.0, 1.0, 5.0, 4.0]);
+        assert_eq!(first_fit(&items, |_| 10.0), vec![3, 7, 10]);
+        // A hyphen fits the first part of a word on the line.
+        let mut hyphenated = super::tests::items(&[4.0, 6.0]);
+        hyphenated.splice(2..3, [Item::Box { width: 3.0 }, Item::Penalty { width: 1.0, penalty: 50.0, flagged: true }, Item::Box { width: 3.0 }]);
+        assert_eq!(first_fit(&hyphenated, |_| 9.0), vec![3, 6]);
+        assert_eq!(first_fit(&hyphenated, |_| 8.0), vec![1, 6]);
+        // Boxes wider than the line overflow on their own line.
+        assert_eq!(first_fit(&super::tests::items(&[2.0, 12.0, 2.0]), |_| 10.0), vec![1, 3, 6]);
+    }
+
+    #[test]
+    fn it_finds_unicode_break_opportunities() {
+        assert_eq!(break_opportunities("Hello world!"), vec![6]);
+        assert_eq!(break_opportunities("well-known and/or"), vec![5, 11, 15]);
+        assert_eq!(break_opportunities("日本語"), vec![3, 6]);
+        // No break inside the brackets, before 、 or before the small kana.
+        assert_eq!(break_opportunities("「日本」、ちょっと"), vec![6, 15, 24]);
+        assert_eq!(break_opportunities("ภาษา\u{200B}ไทย"), vec!["ภาษา\u{200B}".len()]);
+    }
+
+    #[test]
+    fn it_breaks_thai_between_dictionary_words() {
+        let dictionary = WordDictionary::new("th", THAI_WORDS).unwrap();
+        assert_eq!(break_opportunities("ภาษาไทยง่ายมาก"), Vec::<usize>::new());
+        assert_eq!(
+            break_opportunities_with_dictionary("ภาษาไทยง่ายมาก (Thai)", &dictionary),
+            vec!["ภาษา".len(), "ภาษาไทย".len(), "ภาษาไทยง่าย".len(), "ภาษาไทยง่ายมาก ".len()]
+        );
+    }
+
+    #[test]
+    fn it_fails_when_a_box_does_not_fit() {
+        assert!(total_fit(&items(&[2.0, 9.0, 2.0]), |_| 6.0, 1.0).is_none());
+        assert_eq!(total_fit(&items(&[2.0, 2.0]), |_| 6.0, 1.0), Some(vec![4]));
+    }
+}
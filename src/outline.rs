@@ -0,0 +1,115 @@
+use ttf_parser::{GlyphId, OutlineBuilder};
+
+/// Affine transform `[a b c d e f]`, as used by the `cm` operator.
+pub type Transform = [f32; 6];
+
+pub const IDENTITY: Transform = [1.0, 0.0, 0.0, 1.0, 0.0, 0.0];
+
+/// `first` followed by `second`.
+pub fn concat(first: &Transform, second: &Transform) -> Transform {
+    let [a, b, c, d, e, f] = *first;
+    let [a2, b2, c2, d2, e2, f2] = *second;
+    [
+        a * a2 + b * c2,
+        a * b2 + b * d2,
+        c * a2 + d * c2,
+        c * b2 + d * d2,
+        e * a2 + f * c2 + e2,
+        e * b2 + f * d2 + f2,
+    ]
+}
+
+/// Builds PDF path construction operators (`m`, `l`, `c`, `h`) out of glyph outlines.
+///
+/// Points are mapped through `transform`. Quadratic curves of TrueType outlines are
+/// converted to the cubic ones PDF supports.
+#[derive(Debug, Clone)]
+pub struct PathBuilder {
+    path: String,
+    transform: Transform,
+    current: (f32, f32),
+}
+
+impl PathBuilder {
+
+    pub fn new(transform: Transform) -> PathBuilder {
+        PathBuilder {
+            path: String::new(),
+            transform,
+            current: (0.0, 0.0),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.path.is_empty()
+    }
+
+    pub fn finish(self) -> String {
+        self.path
+    }
+
+    fn point(&self, x: f32, y: f32) -> String {
+        let [a, b, c, d, e, f] = self.transform;
+        format!("{:.2} {:.2}", a * x + c * y + e, b * x + d * y + f)
+    }
+}
+
+impl OutlineBuilder for PathBuilder {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.path.push_str(&format!("{} m\n", self.point(x, y)));
+        self.current = (x, y);
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.path.push_str(&format!("{} l\n", self.point(x, y)));
+        self.current = (x, y);
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        // The control points of the equivalent cubic are 2/3 of the way to the quadratic one.
+        let (x0, y0) = self.current;
+        let control1 = (x0 + 2.0 / 3.0 * (x1 - x0), y0 + 2.0 / 3.0 * (y1 - y0));
+        let control2 = (x + 2.0 / 3.0 * (x1 - x), y + 2.0 / 3.0 * (y1 - y));
+        self.curve_to(control1.0, control1.1, control2.0, control2.1, x, y);
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        self.path.push_str(&format!("{} {} {} c\n", self.point(x1, y1), self.point(x2, y2), self.point(x, y)));
+        self.current = (x, y);
+    }
+
+    fn close(&mut self) {
+        self.path.push_str("h\n");
+    }
+}
+
+/// Path of glyph `glyph_id` in 1/1000 em, `None` for glyphs without an outline.
+pub fn glyph_path(face: &ttf_parser::Face, glyph_id: u16) -> Option<String> {
+    let scale = 1000.0 / face.units_per_em() as f32;
+    let mut builder = PathBuilder::new([scale, 0.0, 0.0, scale, 0.0, 0.0]);
+    face.outline_glyph(GlyphId(glyph_id), &mut builder)?;
+    Some(builder.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{concat, PathBuilder, IDENTITY};
+    use ttf_parser::OutlineBuilder;
+
+    #[test]
+    fn it_converts_quadratic_curves_to_cubic_ones() {
+        let mut builder = PathBuilder::new(IDENTITY);
+        builder.move_to(0.0, 0.0);
+        builder.quad_to(30.0, 60.0, 60.0, 0.0);
+        builder.close();
+        assert_eq!(builder.finish(), "0.00 0.00 m\n20.00 40.00 40.00 40.00 60.00 0.00 c\nh\n");
+    }
+
+    #[test]
+    fn it_applies_transforms() {
+        let scale_then_move = concat(&[2.0, 0.0, 0.0, 2.0, 0.0, 0.0], &[1.0, 0.0, 0.0, 1.0, 10.0, 5.0]);
+        let mut builder = PathBuilder::new(scale_then_move);
+        builder.move_to(1.0, 1.0);
+        assert_eq!(builder.finish(), "12.00 7.00 m\n");
+    }
+}
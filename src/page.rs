@@ -1,4 +1,5 @@
 use crate::page_format::PageSize;
+use crate::rich_text::RichText;
 
 #[derive(Debug)]
 pub enum OrientationType {
@@ -15,10 +16,18 @@ impl OrientationType {
     }
 }
 
+/// A link annotation: clicking `rect` (`[x1 y1 x2 y2]` in points) opens `uri`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Link {
+    pub rect: [f32; 4],
+    pub uri: String,
+}
+
 #[derive(Debug, Clone)]
 pub struct Page {
     size: PageSize,
     content: Vec<u8>,
+    links: Vec<Link>,
 }
 
 impl Page {
@@ -32,6 +41,7 @@ impl Page {
         Page {
             size: base_size,
             content: Vec::new(),
+            links: Vec::new(),
         }
     }
 
@@ -39,6 +49,17 @@ impl Page {
         self.content.extend_from_slice(content.as_slice());
     }
 
+    pub fn add_link(&mut self, link: Link) {
+        self.links.push(link);
+    }
+
+    /// Adds the content of `text` and the links of its spans.
+    pub fn add_rich_text(&mut self, text: RichText) {
+        let (content, links) = text.layout();
+        self.add_content(content);
+        self.links.extend(links);
+    }
+
     pub fn size(&self) -> &PageSize {
         &self.size
     }
@@ -50,5 +71,9 @@ impl Page {
         &self.content
     }
 
+    pub fn links(&self) -> &[Link] {
+        &self.links
+    }
+
 }
 
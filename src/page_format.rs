@@ -3,7 +3,7 @@ use crate::units::Pt;
 
 
 #[derive(Debug)]
-enum PageSizeError {
+pub enum PageSizeError {
     InvalidWidth(f32),
     InvalidHeight(f32),
 }
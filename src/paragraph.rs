@@ -0,0 +1,304 @@
+use std::rc::Rc;
+use crate::bidi::Direction;
+use crate::font::Font;
+use crate::font_fallback::FontFallback;
+use crate::hyphenation::Hyphenator;
+use crate::rich_text::{RichText, Span};
+use crate::text::Alignment;
+use crate::types::{Point, RGB};
+use crate::word_dictionary::WordDictionary;
+
+/// Default distance between the baselines of a paragraph, relative to the font size.
+pub const DEFAULT_LEADING: f32 = 1.2;
+
+/// How a `Paragraph` chooses where its lines break.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineBreaking {
+    /// Each line takes as many words as fit, one line after the other.
+    #[default]
+    Greedy,
+    /// Breaks chosen over the whole paragraph so the spacing is as even as possible, with the
+    /// Knuth–Plass algorithm. Best with `Alignment::Justify`. Paragraphs that can't be set
+    /// within the tolerance, e.g. because of a word wider than the paragraph, are set greedily.
+    TotalFit,
+}
+
+/// A block of text wrapped into lines that fit in `width`.
+///
+/// Lines break where the Unicode line breaking algorithm allows, see
+/// `line_breaking::break_opportunities`: at spaces, between ideographs, after hyphens and
+/// slashes, and with a `WordDictionary` between the words of Thai and the other scripts
+/// written without spaces. Words are also split at soft hyphens (U+00AD) or, with a
+/// `Hyphenator`, where its patterns allow, and a hyphen is drawn at the end of the line. Text
+/// wider than the paragraph without a break opportunity gets a line of its own. Newlines start
+/// a new paragraph, with its own first line indent and spacing.
+pub struct Paragraph {
+    pub text: String,
+    pub font: Rc<Font>,
+    pub size: i32,
+    /// Top left corner of the paragraph, spacing before included.
+    pub point: Point,
+    pub width: f32,
+    pub color: RGB,
+    pub alignment: Alignment,
+    /// Distance between the baselines of consecutive lines, in points.
+    pub leading: f32,
+    /// Indent of the first line of each paragraph, from its start edge.
+    pub first_line_indent: f32,
+    /// Space above and below each paragraph, in points: paragraphs split by newlines are set
+    /// apart by the space after the one and before the next.
+    pub space_before: f32,
+    pub space_after: f32,
+    pub kerning: bool,
+    pub direction: Direction,
+    pub fallback: Option<FontFallback>,
+    pub line_breaking: LineBreaking,
+    /// Patterns used to hyphenate the words that don't fit, see `with_hyphenation`.
+    pub hyphenation: Option<Rc<Hyphenator>>,
+    /// Words used to break text written without spaces, see `with_word_dictionary`.
+    pub word_dictionary: Option<Rc<WordDictionary>>,
+}
+
+impl Paragraph {
+
+    pub fn new(text: &str, font: Rc<Font>, size: i32, point: Point, width: f32, color: RGB) -> Paragraph {
+        Paragraph {
+            text: text.to_string(),
+            font,
+            size,
+            point,
+            width,
+            color,
+            alignment: Alignment::Start,
+            leading: size as f32 * DEFAULT_LEADING,
+            first_line_indent: 0.0,
+            space_before: 0.0,
+            space_after: 0.0,
+            kerning: true,
+            direction: Direction::Auto,
+            fallback: None,
+            line_breaking: LineBreaking::Greedy,
+            hyphenation: None,
+            word_dictionary: None,
+        }
+    }
+
+    pub fn with_alignment(mut self, alignment: Alignment) -> Paragraph {
+        self.alignment = alignment;
+        self
+    }
+
+    pub fn with_leading(mut self, leading: f32) -> Paragraph {
+        self.leading = leading;
+        self
+    }
+
+    pub fn with_first_line_indent(mut self, indent: f32) -> Paragraph {
+        self.first_line_indent = indent;
+        self
+    }
+
+    pub fn with_spacing(mut self, before: f32, after: f32) -> Paragraph {
+        self.space_before = before;
+        self.space_after = after;
+        self
+    }
+
+    pub fn with_kerning(mut self, kerning: bool) -> Paragraph {
+        self.kerning = kerning;
+        self
+    }
+
+    pub fn with_direction(mut self, direction: Direction) -> Paragraph {
+        self.direction = direction;
+        self
+    }
+
+    /// Draws the text with a chain of fonts, see `Text::with_fallback`.
+    pub fn with_fallback(mut self, fallback: FontFallback) -> Paragraph {
+        self.font = Rc::clone(fallback.primary());
+        self.fallback = Some(fallback);
+        self
+    }
+
+    pub fn with_line_breaking(mut self, line_breaking: LineBreaking) -> Paragraph {
+        self.line_breaking = line_breaking;
+        self
+    }
+
+    /// Hyphenates words with the patterns of `hyphenator`, in the language of the text.
+    pub fn with_hyphenation(mut self, hyphenator: Rc<Hyphenator>) -> Paragraph {
+        self.hyphenation = Some(hyphenator);
+        self
+    }
+
+    /// Breaks Thai and the other scripts written without spaces between the words of
+    /// `dictionary`. Without one, their lines only break at spaces and zero width spaces.
+    pub fn with_word_dictionary(mut self, dictionary: Rc<WordDictionary>) -> Paragraph {
+        self.word_dictionary = Some(dictionary);
+        self
+    }
+
+    /// Text of each line once wrapped, words separated by a single space.
+    pub fn lines(&self) -> Vec<String> {
+        self.rich_text().lines()
+    }
+
+    /// Height taken by the paragraph in points: the spacing of every paragraph and one leading
+    /// per line.
+    /// Content stacked below starts at `point.1 - height()`.
+    pub fn height(&self) -> f32 {
+        self.rich_text().height()
+    }
+
+    /// The paragraph as a `RichText` with a single span.
+    pub fn rich_text(&self) -> RichText {
+        let mut span = Span::new(&self.text, Rc::clone(&self.font), self.size).with_color(self.color);
+        span.fallback = self.fallback.clone();
+        RichText {
+            spans: vec![span],
+            point: self.point,
+            width: self.width,
+            alignment: self.alignment,
+            leading: Some(self.leading),
+            first_line_indent: self.first_line_indent,
+            space_before: self.space_before,
+            space_after: self.space_after,
+            kerning: self.kerning,
+            direction: self.direction,
+            line_breaking: self.line_breaking,
+            hyphenation: self.hyphenation.clone(),
+            word_dictionary: self.word_dictionary.clone(),
+        }
+    }
+}
+
+impl From<Paragraph> for Vec<u8> {
+    fn from(paragraph: Paragraph) -> Vec<u8> {
+        // The single span of a paragraph has no link.
+        paragraph.rich_text().layout().0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+    use super::{LineBreaking, Paragraph};
+    use crate::hyphenation::Hyphenator;
+    use crate::font::Font;
+    use crate::reference::Ref;
+    use crate::standard_font::StandardFont;
+    use crate::text::Alignment;
+    use crate::types::{Point, RGB};
+    use crate::word_dictionary::WordDictionary;
+
+    // Courier glyphs are 6 points wide at 10 points, so 10 of them fit in 60 points.
+    fn paragraph(text: &str) -> Paragraph {
+        let courier = Rc::new(Font::new_standard("F1", Ref::new(1), StandardFont::Courier));
+        Paragraph::new(text, courier, 10, Point(100.0, 700.0), 60.0, RGB(0, 0, 0))
+    }
+
+    #[test]
+    fn it_wraps_words_into_lines() {
+        assert_eq!(paragraph("aaa bbb ccc ddd").lines(), vec!["aaa bbb", "ccc ddd"]);
+        assert_eq!(paragraph("aaaaa bbbb").lines(), vec!["aaaaa bbbb"]);
+        // Overlong words get a line of their own, newlines start a paragraph.
+        assert_eq!(paragraph("a bbbbbbbbbbbb c\n\nd").lines(), vec!["a", "bbbbbbbbbbbb", "c", "", "d"]);
+        // The first line is shorter when indented.
+        assert_eq!(paragraph("aaa bbb ccc").with_first_line_indent(20.0).lines(), vec!["aaa", "bbb ccc"]);
+    }
+
+    #[test]
+    fn it_breaks_lines_over_the_whole_paragraph() {
+        // Greedily, "fffffff" doesn't fit on the first line, which is left loose. Shrinking the
+        // spaces a little makes room for it.
+        let text = "aaa bbbbbbb cc ddd eeee fffffff gg h iiiiiii jj k llll";
+        let justified = |line_breaking| Paragraph { width: 180.0, ..paragraph(text) }
+            .with_alignment(Alignment::Justify)
+            .with_line_breaking(line_breaking);
+        assert_eq!(justified(LineBreaking::Greedy).lines(), vec!["aaa bbbbbbb cc ddd eeee", "fffffff gg h iiiiiii jj k llll"]);
+        assert_eq!(justified(LineBreaking::TotalFit).lines(), vec!["aaa bbbbbbb cc ddd eeee fffffff", "gg h iiiiiii jj k llll"]);
+        let output = String::from_utf8(justified(LineBreaking::TotalFit).into()).unwrap();
+        assert!(output.contains("-1.200 Tw\n(aaa bbbbbbb cc ddd eeee fffffff) Tj\n"));
+
+        // Words wider than the paragraph can't be set within the tolerance.
+        let overfull = paragraph("a bbbbbbbbbbbb c").with_line_breaking(LineBreaking::TotalFit);
+        assert_eq!(overfull.lines(), vec!["a", "bbbbbbbbbbbb", "c"]);
+    }
+
+    #[test]
+    fn it_hyphenates_words_that_do_not_fit() {
+        let hyphenator = Rc::new(Hyphenator::new("en-us", crate::test_fixtures::HYPHENATION_PATTERNS).unwrap());
+        assert_eq!(paragraph("a hyphenation").lines(), vec!["a", "hyphenation"]);
+        let hyphenated = paragraph("a hyphenation").with_hyphenation(Rc::clone(&hyphenator));
+        assert_eq!(hyphenated.lines(), vec!["a hyphen-", "ation"]);
+        let total_fit = paragraph("a hyphenation").with_hyphenation(hyphenator).with_line_breaking(LineBreaking::TotalFit);
+        assert_eq!(total_fit.lines(), vec!["a hyphen-", "ation"]);
+
+        // Soft hyphens are break points, and are not drawn when the word is not broken there.
+        assert_eq!(paragraph("aaaa bbb\u{AD}ccc").lines(), vec!["aaaa bbb-", "ccc"]);
+        let output = String::from_utf8(paragraph("x bbb\u{AD}ccc").into()).unwrap();
+        assert!(output.contains("(x bbbccc) Tj"));
+    }
+
+    #[test]
+    fn it_breaks_between_ideographs_with_kinsoku() {
+        // Courier draws the ideographs as "?", 6 points wide: 10 of them fit on a line. The
+        // prolonged sound mark can't start a line, so "こ" goes to the next line with it.
+        assert_eq!(paragraph("あいうえおかきくけこーさ").lines(), vec!["あいうえおかきくけ", "こーさ"]);
+        // Lines without spaces are not stretched when justified.
+        let justified = String::from_utf8(paragraph("あいうえおかきくけこーさ").with_alignment(Alignment::Justify).into()).unwrap();
+        assert!(!justified.contains(" Tw\n") && !justified.contains(" Tc\n"));
+        assert_eq!(paragraph("see example.com/docs/intro").lines(), vec!["see", "example.com/", "docs/intro"]);
+    }
+
+    #[test]
+    fn it_breaks_thai_between_dictionary_words() {
+        // Courier draws the 14 Thai characters as "?" too. Without spaces, Thai only breaks
+        // between the words of a dictionary.
+        assert_eq!(paragraph("ภาษาไทยง่ายมาก").lines(), vec!["ภาษาไทยง่ายมาก"]);
+        let dictionary = Rc::new(WordDictionary::new("th", crate::test_fixtures::THAI_WORDS).unwrap());
+        assert_eq!(paragraph("ภาษาไทยง่ายมาก").with_word_dictionary(dictionary).lines(), vec!["ภาษาไทย", "ง่ายมาก"]);
+    }
+
+    #[test]
+    fn it_reports_its_height() {
+        let paragraph = paragraph("aaa bbb ccc ddd").with_leading(14.0).with_spacing(3.0, 5.0);
+        assert_eq!(paragraph.height(), 3.0 + 2.0 * 14.0 + 5.0);
+        // Each paragraph has its spacing.
+        let paragraphs = self::paragraph("aaa bbb ccc ddd\neee").with_leading(14.0).with_spacing(3.0, 5.0);
+        assert_eq!(paragraphs.height(), 2.0 * (3.0 + 5.0) + 3.0 * 14.0);
+        assert_eq!(super::DEFAULT_LEADING * 10.0, self::paragraph("").leading);
+    }
+
+    #[test]
+    fn it_aligns_lines() {
+        let output = |alignment: Alignment| {
+            String::from_utf8(paragraph("aaa bbbb cc").with_alignment(alignment).into()).unwrap()
+        };
+        let right = output(Alignment::Right);
+        assert!(right.contains("1 0 0 1 112.00 ") && right.contains("1 0 0 1 148.00 "));
+        let center = output(Alignment::Center);
+        assert!(center.contains("1 0 0 1 106.00 ") && center.contains("1 0 0 1 124.00 "));
+
+        // Justified lines reach the right edge with wider spaces, the last line is set as Start.
+        let justified = output(Alignment::Justify);
+        assert!(justified.contains("12.000 Tw\n(aaa bbbb) Tj"));
+        assert_eq!(justified.matches(" Tw\n").count(), 1);
+        assert_eq!(justified.matches("1 0 0 1 100.00 ").count(), 2);
+    }
+
+    #[test]
+    fn it_stacks_lines_by_leading() {
+        let output = String::from_utf8(paragraph("aaa bbb ccc").with_leading(20.0).with_spacing(10.0, 0.0).into()).unwrap();
+        let height = Font::new_standard("F1", Ref::new(1), StandardFont::Courier).height;
+        let baseline = |top: f32| format!("1 0 0 1 100.00 {:.2} Tm", top - height * 10.0);
+        assert!(output.contains(&baseline(690.0)));
+        assert!(output.contains(&baseline(670.0)));
+
+        // The second paragraph starts below the spacing after the first and before itself.
+        let output = String::from_utf8(paragraph("aaa\nbbb").with_leading(20.0).with_spacing(10.0, 4.0).into()).unwrap();
+        assert!(output.contains(&baseline(690.0)));
+        assert!(output.contains(&baseline(656.0)));
+    }
+}
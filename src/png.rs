@@ -0,0 +1,186 @@
+use std::io::Read;
+
+/// 8-bit RGB pixels with an optional alpha channel, as embedded in image XObjects.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bitmap {
+    pub width: u32,
+    pub height: u32,
+    pub rgb: Vec<u8>,
+    pub alpha: Option<Vec<u8>>,
+}
+
+pub(crate) const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+
+/// Decodes a non-interlaced PNG image of any color type with 8 or 16 bits per channel,
+/// or 1 to 8 bit palette and grayscale images.
+pub fn decode_png(data: &[u8]) -> Result<Bitmap, String> {
+    if !data.starts_with(&SIGNATURE) {
+        return Err("Not a PNG image".to_string());
+    }
+
+    let mut header = None;
+    let mut palette: &[u8] = &[];
+    let mut transparency: &[u8] = &[];
+    let mut compressed = Vec::new();
+
+    let mut offset = SIGNATURE.len();
+    while offset + 8 <= data.len() {
+        let length = u32::from_be_bytes(data[offset..offset + 4].try_into().unwrap()) as usize;
+        let kind = &data[offset + 4..offset + 8];
+        let chunk = data.get(offset + 8..offset + 8 + length).ok_or("Truncated PNG chunk")?;
+        match kind {
+            b"IHDR" if chunk.len() >= 13 => header = Some(chunk),
+            b"PLTE" => palette = chunk,
+            b"tRNS" => transparency = chunk,
+            b"IDAT" => compressed.extend_from_slice(chunk),
+            b"IEND" => break,
+            _ => {}
+        }
+        // Length, type, data and CRC.
+        offset += 12 + length;
+    }
+
+    let header = header.ok_or("PNG image without header")?;
+    let width = u32::from_be_bytes(header[0..4].try_into().unwrap());
+    let height = u32::from_be_bytes(header[4..8].try_into().unwrap());
+    let (bit_depth, color_type, interlace) = (header[8] as usize, header[9], header[12]);
+    if interlace != 0 {
+        return Err("Interlaced PNG images are not supported".to_string());
+    }
+
+    let channels = match color_type {
+        0 | 3 => 1,
+        2 => 3,
+        4 => 2,
+        6 => 4,
+        _ => return Err(format!("Unknown PNG color type {}", color_type)),
+    };
+    let bits_per_pixel = channels * bit_depth;
+    let stride = (width as usize * bits_per_pixel).div_ceil(8);
+    let pixel_bytes = bits_per_pixel.div_ceil(8);
+
+    let mut filtered = Vec::new();
+    flate2::read::ZlibDecoder::new(compressed.as_slice())
+        .read_to_end(&mut filtered)
+        .map_err(|error| format!("Error inflating PNG data: {}", error))?;
+    let pixels = unfilter(&filtered, stride, height as usize, pixel_bytes)?;
+
+    // Samples scaled to 8 bits.
+    let sample = |row: &[u8], index: usize| -> u8 {
+        match bit_depth {
+            16 => row[index * 2],
+            8 => row[index],
+            _ => {
+                let bit = index * bit_depth;
+                let value = (row[bit / 8] >> (8 - bit_depth - bit % 8)) & ((1 << bit_depth) - 1);
+                if color_type == 3 { value } else { (value as u32 * 255 / ((1 << bit_depth) - 1)) as u8 }
+            }
+        }
+    };
+
+    let pixel_count = (width * height) as usize;
+    let mut rgb = Vec::with_capacity(pixel_count * 3);
+    let mut alpha = Vec::with_capacity(pixel_count);
+    for row in pixels.chunks(stride) {
+        for x in 0..width as usize {
+            let (color, opacity) = match color_type {
+                0 => {
+                    let gray = sample(row, x);
+                    let transparent = transparency.len() >= 2 && bit_depth <= 8 && transparency[1] == gray;
+                    ([gray; 3], if transparent { 0 } else { 255 })
+                }
+                2 => ([sample(row, x * 3), sample(row, x * 3 + 1), sample(row, x * 3 + 2)], 255),
+                3 => {
+                    let index = sample(row, x) as usize;
+                    let entry = palette.get(index * 3..index * 3 + 3).ok_or("PNG palette index out of range")?;
+                    ([entry[0], entry[1], entry[2]], transparency.get(index).copied().unwrap_or(255))
+                }
+                4 => ([sample(row, x * 2); 3], sample(row, x * 2 + 1)),
+                _ => ([sample(row, x * 4), sample(row, x * 4 + 1), sample(row, x * 4 + 2)], sample(row, x * 4 + 3)),
+            };
+            rgb.extend_from_slice(&color);
+            alpha.push(opacity);
+        }
+    }
+
+    Ok(Bitmap {
+        width,
+        height,
+        rgb,
+        alpha: if alpha.iter().all(|value| *value == 255) { None } else { Some(alpha) },
+    })
+}
+
+// Reverses the per-scanline filters, the filter type byte of each row is dropped.
+fn unfilter(data: &[u8], stride: usize, height: usize, pixel_bytes: usize) -> Result<Vec<u8>, String> {
+    if data.len() < (stride + 1) * height {
+        return Err("Truncated PNG image data".to_string());
+    }
+
+    let mut pixels = vec![0u8; stride * height];
+    for y in 0..height {
+        let filter = data[y * (stride + 1)];
+        let source = &data[y * (stride + 1) + 1..(y + 1) * (stride + 1)];
+        let (previous_rows, current_rows) = pixels.split_at_mut(y * stride);
+        let previous = if y == 0 { None } else { Some(&previous_rows[(y - 1) * stride..]) };
+        let row = &mut current_rows[..stride];
+
+        for x in 0..stride {
+            let left = if x >= pixel_bytes { row[x - pixel_bytes] } else { 0 };
+            let up = previous.map(|previous| previous[x]).unwrap_or(0);
+            let up_left = if x >= pixel_bytes { previous.map(|previous| previous[x - pixel_bytes]).unwrap_or(0) } else { 0 };
+            let predictor = match filter {
+                0 => 0,
+                1 => left,
+                2 => up,
+                3 => ((left as u16 + up as u16) / 2) as u8,
+                4 => paeth(left, up, up_left),
+                _ => return Err(format!("Unknown PNG filter {}", filter)),
+            };
+            row[x] = source[x].wrapping_add(predictor);
+        }
+    }
+    Ok(pixels)
+}
+
+fn paeth(left: u8, up: u8, up_left: u8) -> u8 {
+    let estimate = left as i16 + up as i16 - up_left as i16;
+    let (to_left, to_up, to_up_left) = (
+        (estimate - left as i16).abs(),
+        (estimate - up as i16).abs(),
+        (estimate - up_left as i16).abs(),
+    );
+    if to_left <= to_up && to_left <= to_up_left {
+        left
+    } else if to_up <= to_up_left {
+        up
+    } else {
+        up_left
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::decode_png;
+    use crate::test_fixtures::png;
+
+    #[test]
+    fn it_decodes_rgba_images() {
+        // 2x2, the second row uses the Up filter.
+        let data = png(6, &[&[0, 255, 0, 0, 255, 0, 0, 255, 128], &[2, 0, 0, 0, 0, 0, 255, 0, 0]], 2);
+        let image = decode_png(&data).unwrap();
+        assert_eq!((image.width, image.height), (2, 2));
+        assert_eq!(image.rgb, vec![255, 0, 0, 0, 0, 255, 255, 0, 0, 0, 255, 255]);
+        assert_eq!(image.alpha, Some(vec![255, 128, 255, 128]));
+    }
+
+    #[test]
+    fn it_drops_opaque_alpha_channels() {
+        let data = png(2, &[&[1, 10, 20, 30, 5, 5, 5]], 2);
+        let image = decode_png(&data).unwrap();
+        // Sub filter: the second pixel adds the first one.
+        assert_eq!(image.rgb, vec![10, 20, 30, 15, 25, 35]);
+        assert_eq!(image.alpha, None);
+        assert!(decode_png(b"GIF89a").is_err());
+    }
+}
@@ -0,0 +1,587 @@
+use std::rc::Rc;
+use crate::bidi::Direction;
+use crate::font::{pdf_literal_string, Font};
+use crate::font_fallback::FontFallback;
+use crate::hyphenation::{Hyphenator, SOFT_HYPHEN};
+use crate::line_breaking::{self, Item};
+use crate::page::Link;
+use crate::paragraph::{LineBreaking, DEFAULT_LEADING};
+use crate::text::{Alignment, Text};
+use crate::types::{Point, RGB};
+use crate::word_dictionary::WordDictionary;
+
+/// Stretchability of the spaces of text set with `LineBreaking::TotalFit`, relative to their
+/// width. Justified lines may also shrink them by `SPACE_SHRINK`.
+const SPACE_STRETCH: f32 = 0.5;
+const SPACE_SHRINK: f32 = 1.0 / 3.0;
+/// Cost of breaking a line after a hyphen, as TeX's `\hyphenpenalty`.
+const HYPHEN_PENALTY: f32 = 50.0;
+/// Tolerances tried in turn by `LineBreaking::TotalFit`, see `line_breaking::total_fit`.
+const TOLERANCES: [f32; 2] = [2.0, 10.0];
+/// Offset of the middle of underlines from the baseline and their thickness, relative to the
+/// size of the span.
+const UNDERLINE_POSITION: f32 = -0.1;
+const UNDERLINE_THICKNESS: f32 = 0.05;
+
+/// A run of text of a `RichText` drawn with a single style.
+#[derive(Debug, Clone)]
+pub struct Span {
+    pub text: String,
+    pub font: Rc<Font>,
+    pub size: i32,
+    pub color: RGB,
+    /// Distance the span is raised above the baseline in points, negative to lower it.
+    pub baseline_shift: f32,
+    pub underline: bool,
+    /// URI opened when clicking the span, see `Page::add_rich_text`.
+    pub link: Option<String>,
+    pub fallback: Option<FontFallback>,
+}
+
+impl Span {
+
+    pub fn new(text: &str, font: Rc<Font>, size: i32) -> Span {
+        Span {
+            text: text.to_string(),
+            font,
+            size,
+            color: RGB(0, 0, 0),
+            baseline_shift: 0.0,
+            underline: false,
+            link: None,
+            fallback: None,
+        }
+    }
+
+    pub fn with_color(mut self, color: RGB) -> Span {
+        self.color = color;
+        self
+    }
+
+    pub fn with_baseline_shift(mut self, shift: f32) -> Span {
+        self.baseline_shift = shift;
+        self
+    }
+
+    pub fn with_underline(mut self, underline: bool) -> Span {
+        self.underline = underline;
+        self
+    }
+
+    pub fn with_link(mut self, uri: &str) -> Span {
+        self.link = Some(uri.to_string());
+        self
+    }
+
+    /// Draws the span with a chain of fonts, see `Text::with_fallback`.
+    pub fn with_fallback(mut self, fallback: FontFallback) -> Span {
+        self.font = Rc::clone(fallback.primary());
+        self.fallback = Some(fallback);
+        self
+    }
+}
+
+/// A block of text made of spans with their own font, size and color, wrapped and aligned as
+/// a whole like a `Paragraph`.
+///
+/// Lines break at the same places as in a `Paragraph`, wherever the spans start and end. Spans
+/// of a line share its baseline, placed below the tallest span of the line, and are raised or
+/// lowered from it by their `baseline_shift`.
+///
+/// Add it to a page with `Page::add_rich_text`, which writes the link annotations of its spans
+/// along with its content.
+#[derive(Debug, Clone)]
+pub struct RichText {
+    pub spans: Vec<Span>,
+    /// Top left corner of the text, spacing before included.
+    pub point: Point,
+    pub width: f32,
+    pub alignment: Alignment,
+    /// Distance between the baselines of consecutive lines, in points. Without it each line is
+    /// `DEFAULT_LEADING` times the size of its largest span.
+    pub leading: Option<f32>,
+    /// Indent of the first line of each paragraph, from its start edge.
+    pub first_line_indent: f32,
+    /// Space above and below each paragraph, in points: paragraphs split by newlines are set
+    /// apart by the space after the one and before the next.
+    pub space_before: f32,
+    pub space_after: f32,
+    pub kerning: bool,
+    pub direction: Direction,
+    pub line_breaking: LineBreaking,
+    /// Patterns used to hyphenate the words that don't fit, see `Paragraph::with_hyphenation`.
+    pub hyphenation: Option<Rc<Hyphenator>>,
+    /// Words used to break text written without spaces, see `Paragraph::with_word_dictionary`.
+    pub word_dictionary: Option<Rc<WordDictionary>>,
+}
+
+// Text of a line drawn with the span `span`.
+#[derive(Debug, Clone, PartialEq)]
+struct Fragment {
+    span: usize,
+    text: String,
+}
+
+// A line of laid out text, the hyphen it ends with included.
+#[derive(Debug, Clone, PartialEq)]
+struct LineBox {
+    fragments: Vec<Fragment>,
+    // Spaces between the words, widened to justify the line.
+    spaces: usize,
+    first: bool,
+    last: bool,
+    // Span the line starts in, which sizes empty lines.
+    span: usize,
+}
+
+impl LineBox {
+
+    // Adds text at the end of the line, in the last fragment when it has the same span.
+    fn push(&mut self, fragment: &Fragment) {
+        if fragment.text.is_empty() {
+            return;
+        }
+        match self.fragments.last_mut() {
+            Some(last) if last.span == fragment.span => last.text.push_str(&fragment.text),
+            _ => self.fragments.push(fragment.clone()),
+        }
+    }
+
+    fn text(&self) -> String {
+        self.fragments.iter().map(|fragment| fragment.text.as_str()).collect()
+    }
+}
+
+impl RichText {
+
+    pub fn new(spans: Vec<Span>, point: Point, width: f32) -> RichText {
+        RichText {
+            spans,
+            point,
+            width,
+            alignment: Alignment::Start,
+            leading: None,
+            first_line_indent: 0.0,
+            space_before: 0.0,
+            space_after: 0.0,
+            kerning: true,
+            direction: Direction::Auto,
+            line_breaking: LineBreaking::Greedy,
+            hyphenation: None,
+            word_dictionary: None,
+        }
+    }
+
+    pub fn with_alignment(mut self, alignment: Alignment) -> RichText {
+        self.alignment = alignment;
+        self
+    }
+
+    pub fn with_leading(mut self, leading: f32) -> RichText {
+        self.leading = Some(leading);
+        self
+    }
+
+    pub fn with_first_line_indent(mut self, indent: f32) -> RichText {
+        self.first_line_indent = indent;
+        self
+    }
+
+    pub fn with_spacing(mut self, before: f32, after: f32) -> RichText {
+        self.space_before = before;
+        self.space_after = after;
+        self
+    }
+
+    pub fn with_kerning(mut self, kerning: bool) -> RichText {
+        self.kerning = kerning;
+        self
+    }
+
+    pub fn with_direction(mut self, direction: Direction) -> RichText {
+        self.direction = direction;
+        self
+    }
+
+    pub fn with_line_breaking(mut self, line_breaking: LineBreaking) -> RichText {
+        self.line_breaking = line_breaking;
+        self
+    }
+
+    pub fn with_hyphenation(mut self, hyphenator: Rc<Hyphenator>) -> RichText {
+        self.hyphenation = Some(hyphenator);
+        self
+    }
+
+    pub fn with_word_dictionary(mut self, dictionary: Rc<WordDictionary>) -> RichText {
+        self.word_dictionary = Some(dictionary);
+        self
+    }
+
+    /// Text of the spans, one after the other.
+    pub fn text(&self) -> String {
+        self.spans.iter().map(|span| span.text.as_str()).collect()
+    }
+
+    /// Text of each line once wrapped, words separated by a single space.
+    pub fn lines(&self) -> Vec<String> {
+        self.line_boxes().iter().map(LineBox::text).collect()
+    }
+
+    /// Height taken by the text in points: the spacing of every paragraph and the height of
+    /// every line. Content stacked below starts at `point.1 - height()`.
+    pub fn height(&self) -> f32 {
+        let lines = self.line_boxes();
+        let paragraphs = lines.iter().filter(|line| line.first).count().max(1);
+        let heights: f32 = lines.iter().map(|line| self.line_height(line)).sum();
+        paragraphs as f32 * (self.space_before + self.space_after) + heights
+    }
+
+    /// Link annotations of the spans with a link, one per line they are on.
+    pub fn links(&self) -> Vec<Link> {
+        self.layout().1
+    }
+
+    fn line_height(&self, line: &LineBox) -> f32 {
+        self.leading.unwrap_or_else(|| {
+            let size = line.fragments.iter().map(|fragment| self.spans[fragment.span].size).max();
+            size.unwrap_or(self.spans[line.span].size) as f32 * DEFAULT_LEADING
+        })
+    }
+
+    // Span of the character at byte `offset` of `text()`.
+    fn span_at(&self, offset: usize) -> usize {
+        let mut start = 0;
+        let mut found = 0;
+        for (index, span) in self.spans.iter().enumerate() {
+            if start > offset {
+                break;
+            }
+            if !span.text.is_empty() {
+                found = index;
+            }
+            start += span.text.len();
+        }
+        found
+    }
+
+    // Offsets in `text()` where the spans end.
+    fn span_ends(&self) -> Vec<usize> {
+        self.spans.iter()
+            .scan(0, |end, span| {
+                *end += span.text.len();
+                Some(*end)
+            })
+            .collect()
+    }
+
+    fn line_boxes(&self) -> Vec<LineBox> {
+        let mut lines = Vec::new();
+        if self.spans.is_empty() {
+            return lines;
+        }
+        let text = self.text();
+        let mut offset = 0;
+        for paragraph in text.split('\n') {
+            let items = self.items(&text, offset, offset + paragraph.len());
+            let boxes: Vec<Item> = items.iter().map(|(item, _)| *item).collect();
+            let line_width = |line: usize| self.available_width(line == 0);
+            let breaks = match self.line_breaking {
+                LineBreaking::Greedy => None,
+                LineBreaking::TotalFit => TOLERANCES.iter()
+                    .find_map(|tolerance| line_breaking::total_fit(&boxes, line_width, *tolerance)),
+            };
+
+            let mut start = 0;
+            for (index, end) in breaks.unwrap_or_else(|| line_breaking::first_fit(&boxes, line_width)).into_iter().enumerate() {
+                let span = items[start..end].first().map_or(self.span_at(offset), |(_, fragment)| fragment.span);
+                let mut line = LineBox { fragments: Vec::new(), spaces: 0, first: index == 0, last: end == items.len() - 1, span };
+                // Spaces at the start of a line are dropped, the hyphen of a break is added.
+                for (item, fragment) in &items[start..end] {
+                    match item {
+                        Item::Box { .. } => line.push(fragment),
+                        Item::Glue { .. } if !line.fragments.is_empty() && !fragment.text.is_empty() => {
+                            line.push(fragment);
+                            line.spaces += 1;
+                        }
+                        Item::Glue { .. } | Item::Penalty { .. } => {}
+                    }
+                }
+                if let (Item::Penalty { .. }, hyphen) = &items[end] {
+                    line.push(hyphen);
+                }
+                lines.push(line);
+                start = end + 1;
+            }
+            offset += paragraph.len() + 1;
+        }
+        lines
+    }
+
+    // Boxes, glue and penalties of the paragraph between `start` and `end` of `text` with the
+    // text each one draws: the text between two break opportunities (see
+    // `line_breaking::break_opportunities`) or a part of it, split where spans change, the
+    // spaces after it, and a hyphen at hyphenation points.
+    fn items(&self, text: &str, start: usize, end: usize) -> Vec<(Item, Fragment)> {
+        let paragraph = &text[start..end];
+        let span_ends = self.span_ends();
+        let mut items = Vec::new();
+
+        // Words of the paragraph, between its spaces, as ranges. Segments come in order, so the
+        // word of each one is found going forward.
+        let words: Vec<(usize, usize)> = paragraph.split(' ')
+            .scan(0, |word_start, word| {
+                let bounds = (*word_start, *word_start + word.len());
+                *word_start = bounds.1 + 1;
+                Some(bounds)
+            })
+            .collect();
+        let mut word = 0;
+
+        let opportunities = match &self.word_dictionary {
+            Some(dictionary) => line_breaking::break_opportunities_with_dictionary(paragraph, dictionary),
+            None => line_breaking::break_opportunities(paragraph),
+        };
+        let mut segment_start = 0;
+        for segment_end in opportunities.into_iter().chain([paragraph.len()]) {
+            let segment = &paragraph[segment_start..segment_end];
+            let content = segment.trim_end_matches(' ');
+            let (content, soft_hyphen) = match content.strip_suffix(SOFT_HYPHEN) {
+                Some(content) => (content, true),
+                None => (content, false),
+            };
+
+            // Words with soft hyphens only break at them.
+            while words[word].1 < segment_start {
+                word += 1;
+            }
+            let (word_start, word_end) = words[word];
+            let breaks = match &self.hyphenation {
+                Some(hyphenator) if !paragraph[word_start..word_end].contains(SOFT_HYPHEN) => hyphenator.hyphenate(content),
+                _ => Vec::new(),
+            };
+            let mut part_start = 0;
+            for part_end in breaks.into_iter().chain([content.len()]) {
+                let from = start + segment_start + part_start;
+                if part_start > 0 {
+                    items.push(self.hyphen_item(self.span_at(from - 1)));
+                }
+                // A part of a word may be drawn with several spans.
+                let mut box_start = from;
+                let to = start + segment_start + part_end;
+                while box_start < to {
+                    let span = self.span_at(box_start);
+                    let box_end = span_ends[span].min(to);
+                    let part: String = text[box_start..box_end].chars().filter(|c| *c != SOFT_HYPHEN).collect();
+                    if !part.is_empty() {
+                        items.push((Item::Box { width: self.measure(span, &part) }, Fragment { span, text: part }));
+                    }
+                    box_start = box_end;
+                }
+                part_start = part_end;
+            }
+
+            if segment_end < paragraph.len() {
+                let last = start + segment_end - 1;
+                if segment.ends_with(' ') {
+                    let span = self.span_at(last);
+                    let space = self.measure(span, " ");
+                    let shrink = if self.alignment == Alignment::Justify { space * SPACE_SHRINK } else { 0.0 };
+                    let glue = Item::Glue { width: space, stretch: space * SPACE_STRETCH, shrink };
+                    items.push((glue, Fragment { span, text: String::from(" ") }));
+                } else if soft_hyphen {
+                    items.push(self.hyphen_item(self.span_at(last)));
+                } else {
+                    let penalty = Item::Penalty { width: 0.0, penalty: 0.0, flagged: false };
+                    items.push((penalty, Fragment { span: self.span_at(last), text: String::new() }));
+                }
+            }
+            segment_start = segment_end;
+        }
+        let span = self.span_at(end.saturating_sub(1));
+        items.extend(Item::paragraph_end().map(|item| (item, Fragment { span, text: String::new() })));
+        items
+    }
+
+    // Penalty of a hyphenation point in `span`, drawing a hyphen if the line breaks there.
+    fn hyphen_item(&self, span: usize) -> (Item, Fragment) {
+        let hyphen = if self.spans[span].font.covers('\u{2010}') { "\u{2010}" } else { "-" };
+        let item = Item::Penalty { width: self.measure(span, hyphen), penalty: HYPHEN_PENALTY, flagged: true };
+        (item, Fragment { span, text: hyphen.to_string() })
+    }
+
+    fn available_width(&self, first: bool) -> f32 {
+        if first { self.width - self.first_line_indent } else { self.width }
+    }
+
+    fn measure(&self, span: usize, text: &str) -> f32 {
+        self.text_at(span, text, Point(0.0, 0.0)).width()
+    }
+
+    // Text of `span` with its left edge at `point`.
+    fn text_at(&self, span: usize, text: &str, point: Point) -> Text {
+        let span = &self.spans[span];
+        let mut line = Text::new(text, Rc::clone(&span.font), span.size, point, span.color)
+            .with_kerning(self.kerning)
+            .with_direction(self.direction)
+            .with_alignment(Alignment::Left);
+        if let Some(fallback) = &span.fallback {
+            line = line.with_fallback(fallback.clone());
+        }
+        line
+    }
+
+    // Content stream of the text and the links of its spans.
+    pub(crate) fn layout(&self) -> (Vec<u8>, Vec<Link>) {
+        let rtl = self.direction.is_rtl(&self.text());
+        let mut output = Vec::new();
+        let mut links = Vec::new();
+
+        let mut top = self.point.1 - self.space_before;
+        for (index, line) in self.line_boxes().into_iter().enumerate() {
+            if line.first && index > 0 {
+                top -= self.space_after + self.space_before;
+            }
+            let line_height = self.line_height(&line);
+            let line_top = top;
+            top -= line_height;
+            if line.fragments.is_empty() {
+                continue;
+            }
+            let available = self.available_width(line.first);
+            // The indent is on the start edge: on the right of right to left text.
+            let left = if line.first && !rtl { self.point.0 + self.first_line_indent } else { self.point.0 };
+            let natural_width: f32 = line.fragments.iter().map(|fragment| self.measure(fragment.span, &fragment.text)).sum();
+
+            let (x, word_spacing) = match (self.alignment, rtl) {
+                // The extra space is shared between the spaces of the line.
+                (Alignment::Justify, _) if !line.last && line.spaces > 0 => {
+                    (left, (available - natural_width) / line.spaces as f32)
+                }
+                (Alignment::Left, _) | (Alignment::Start | Alignment::Justify, false) | (Alignment::End, true) => (left, 0.0),
+                (Alignment::Right, _) | (Alignment::Start | Alignment::Justify, true) | (Alignment::End, false) => (left + available - natural_width, 0.0),
+                (Alignment::Center, _) => (left + (available - natural_width) / 2.0, 0.0),
+            };
+
+            // The spans share the baseline of the tallest one.
+            let ascent = |span: &Span| span.font.height * span.size as f32;
+            let baseline = line_top - line.fragments.iter()
+                .map(|fragment| ascent(&self.spans[fragment.span]))
+                .fold(0.0, f32::max);
+            let mut x = x;
+            let fragments: Box<dyn Iterator<Item = &Fragment>> = if rtl { Box::new(line.fragments.iter().rev()) } else { Box::new(line.fragments.iter()) };
+            for fragment in fragments {
+                let span = &self.spans[fragment.span];
+                let size = span.size as f32;
+                let shifted = baseline + span.baseline_shift;
+                let text = self.text_at(fragment.span, &fragment.text, Point(x, shifted + ascent(span))).with_word_spacing(word_spacing);
+                let width = text.width();
+                output.extend(Vec::<u8>::from(text));
+
+                if span.underline {
+                    let RGB(r, g, b) = span.color;
+                    let thickness = UNDERLINE_THICKNESS * size;
+                    output.extend(format!(
+                        "q\n{:.3} {:.3} {:.3} rg\n{:.2} {:.2} {:.2} {:.2} re\nf\nQ\n",
+                        r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0,
+                        x, shifted + UNDERLINE_POSITION * size - thickness / 2.0, width, thickness
+                    ).as_bytes());
+                }
+                if let Some(uri) = &span.link {
+                    let descriptor = &span.font.descriptor;
+                    links.push(Link {
+                        rect: [x, shifted + descriptor.descent as f32 * size / 1000.0, x + width, shifted + descriptor.ascent as f32 * size / 1000.0],
+                        uri: uri.clone(),
+                    });
+                }
+                x += width;
+            }
+        }
+        (output, links)
+    }
+}
+
+/// Writes the dictionary of a link annotation opening `link.uri`.
+pub fn link_annotation(link: &Link) -> String {
+    let [x1, y1, x2, y2] = link.rect;
+    format!(
+        "<< /Type /Annot /Subtype /Link /Rect [{:.2} {:.2} {:.2} {:.2}] /Border [0 0 0] /A << /S /URI /URI {} >> >>",
+        x1, y1, x2, y2, pdf_literal_string(link.uri.as_bytes())
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+    use super::{RichText, Span};
+    use crate::font::Font;
+    use crate::reference::Ref;
+    use crate::standard_font::StandardFont;
+    use crate::text::Alignment;
+    use crate::types::{Point, RGB};
+
+    fn courier() -> Rc<Font> {
+        Rc::new(Font::new_standard("F1", Ref::new(1), StandardFont::Courier))
+    }
+
+    fn bold() -> Rc<Font> {
+        Rc::new(Font::new_standard("F2", Ref::new(2), StandardFont::CourierBold))
+    }
+
+    #[test]
+    fn it_wraps_spans_as_one_text() {
+        // Courier glyphs are 6 points wide at 10 points and 12 at 20 points.
+        let spans = vec![
+            Span::new("aaa b", courier(), 10),
+            Span::new("bb", bold(), 10),
+            Span::new(" ccc ddd", courier(), 10),
+        ];
+        let text = RichText::new(spans, Point(100.0, 700.0), 60.0);
+        assert_eq!(text.lines(), vec!["aaa bbb", "ccc ddd"]);
+        let output = String::from_utf8(text.layout().0).unwrap();
+        assert!(output.contains("/F1 10 Tf") && output.contains("(aaa b) Tj") && output.contains("/F2 10 Tf"));
+        // "bb" follows "aaa b" on the same line.
+        assert!(output.contains("1 0 0 1 130.00 "));
+
+        let mixed = RichText::new(vec![Span::new("aa ", courier(), 10), Span::new("bb cc", courier(), 20)], Point(0.0, 0.0), 60.0)
+            .with_alignment(Alignment::Right);
+        assert_eq!(mixed.lines(), vec!["aa bb", "cc"]);
+        // Lines without a leading are as high as their largest span.
+        assert_eq!(mixed.height(), 2.0 * 24.0);
+        let output = String::from_utf8(mixed.layout().0).unwrap();
+        assert!(output.contains("1 0 0 1 18.00 ") && output.contains("1 0 0 1 36.00 "));
+    }
+
+    #[test]
+    fn it_shares_the_baseline_of_the_line() {
+        let spans = vec![
+            Span::new("a", courier(), 10),
+            Span::new("b", courier(), 20),
+            Span::new("2", courier(), 10).with_baseline_shift(4.0).with_color(RGB(255, 0, 0)),
+        ];
+        let output = String::from_utf8(RichText::new(spans, Point(0.0, 700.0), 200.0).layout().0).unwrap();
+        let baseline = 700.0 - courier().height * 20.0;
+        assert!(output.contains(&format!("1 0 0 1 0.00 {:.2} Tm", baseline)));
+        assert!(output.contains(&format!("1 0 0 1 6.00 {:.2} Tm", baseline)));
+        assert!(output.contains(&format!("1 0 0 1 18.00 {:.2} Tm", baseline + 4.0)));
+        assert!(output.contains("1.000 0.000 0.000 rg"));
+    }
+
+    #[test]
+    fn it_underlines_and_links_spans() {
+        let spans = vec![
+            Span::new("see ", courier(), 10),
+            Span::new("docs (v2)", courier(), 10).with_underline(true).with_link("https://example.com/(v2)"),
+        ];
+        let text = RichText::new(spans, Point(0.0, 700.0), 60.0);
+        assert_eq!(text.lines(), vec!["see docs", "(v2)"]);
+        let links = text.links();
+        assert_eq!(links.len(), 2);
+        assert_eq!((links[0].rect[0], links[0].rect[2]), (24.0, 48.0));
+        assert_eq!((links[1].rect[0], links[1].rect[2]), (0.0, 24.0));
+        assert!(super::link_annotation(&links[0]).contains("/URI (https://example.com/\\(v2\\))"));
+
+        let output = String::from_utf8(text.layout().0).unwrap();
+        assert_eq!(output.matches(" re\nf\n").count(), 2);
+    }
+}
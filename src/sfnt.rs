@@ -0,0 +1,147 @@
+use ttf_parser::Tag;
+
+/// `sfntVersion` of fonts with TrueType outlines.
+pub const TRUETYPE_VERSION: u32 = 0x0001_0000;
+/// `sfntVersion` of fonts with CFF outlines, `OTTO`.
+pub const CFF_VERSION: u32 = 0x4F54_544F;
+
+/// Assembles a standalone OpenType font file out of its tables.
+///
+/// Tables are sorted by tag and padded to four bytes, and the table checksums and the
+/// `checkSumAdjustment` of the `head` table are recomputed.
+pub fn write_sfnt(sfnt_version: u32, mut tables: Vec<(Tag, Vec<u8>)>) -> Vec<u8> {
+    tables.sort_by_key(|(tag, _)| tag.as_u32());
+
+    let num_tables = tables.len() as u16;
+    let entry_selector = if num_tables == 0 { 0 } else { 15 - num_tables.leading_zeros() as u16 };
+    let search_range = (1u16 << entry_selector) * 16;
+    let range_shift = num_tables * 16 - search_range.min(num_tables * 16);
+
+    let mut font = Vec::new();
+    font.extend_from_slice(&sfnt_version.to_be_bytes());
+    font.extend_from_slice(&num_tables.to_be_bytes());
+    font.extend_from_slice(&search_range.to_be_bytes());
+    font.extend_from_slice(&entry_selector.to_be_bytes());
+    font.extend_from_slice(&range_shift.to_be_bytes());
+
+    let mut head_offset = None;
+    let mut offset = 12 + tables.len() * 16;
+    for (tag, data) in tables.iter_mut() {
+        if *tag == Tag::from_bytes(b"head") && data.len() >= 12 {
+            data[8..12].fill(0);
+            head_offset = Some(offset);
+        }
+
+        font.extend_from_slice(&tag.as_u32().to_be_bytes());
+        font.extend_from_slice(&checksum(data).to_be_bytes());
+        font.extend_from_slice(&(offset as u32).to_be_bytes());
+        font.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        offset += data.len().next_multiple_of(4);
+    }
+
+    for (_, data) in tables.iter() {
+        font.extend_from_slice(data);
+        font.resize(font.len().next_multiple_of(4), 0);
+    }
+
+    if let Some(head_offset) = head_offset {
+        let adjustment = 0xB1B0_AFBAu32.wrapping_sub(checksum(&font));
+        font[head_offset + 8..head_offset + 12].copy_from_slice(&adjustment.to_be_bytes());
+    }
+
+    font
+}
+
+/// Copies face `index` of a font collection (`.ttc`/`.otc`) into a standalone font.
+/// Data that is not a collection is returned unchanged, it only has face 0.
+pub fn extract_face(data: &[u8], index: u32) -> Result<Vec<u8>, String> {
+    if ttf_parser::fonts_in_collection(data).is_none() {
+        if index > 0 {
+            return Err(format!("Face {} requested but the font is not a collection", index));
+        }
+        return Ok(data.to_vec());
+    }
+
+    let face = ttf_parser::RawFace::parse(data, index)
+        .map_err(|_| format!("Error parsing face {} of the font collection", index))?;
+
+    let mut tables = Vec::new();
+    for record in face.table_records {
+        let start = record.offset as usize;
+        let table = data.get(start..start + record.length as usize)
+            .ok_or_else(|| format!("Table {} is out of bounds", record.tag))?;
+        tables.push((record.tag, table.to_vec()));
+    }
+
+    let sfnt_version = if face.table(Tag::from_bytes(b"CFF ")).is_some() || face.table(Tag::from_bytes(b"CFF2")).is_some() {
+        CFF_VERSION
+    } else {
+        TRUETYPE_VERSION
+    };
+
+    Ok(write_sfnt(sfnt_version, tables))
+}
+
+fn checksum(data: &[u8]) -> u32 {
+    data.chunks(4).fold(0u32, |sum, chunk| {
+        let mut bytes = [0u8; 4];
+        bytes[..chunk.len()].copy_from_slice(chunk);
+        sum.wrapping_add(u32::from_be_bytes(bytes))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{extract_face, write_sfnt, TRUETYPE_VERSION};
+    use crate::test_fixtures::collection;
+    use ttf_parser::Tag;
+
+    #[test]
+    fn it_writes_sorted_and_padded_tables() {
+        let font = write_sfnt(TRUETYPE_VERSION, vec![
+            (Tag::from_bytes(b"name"), vec![1, 2, 3]),
+            (Tag::from_bytes(b"cmap"), vec![4, 5, 6, 7, 8]),
+        ]);
+        let face = ttf_parser::RawFace::parse(&font, 0).unwrap();
+        assert_eq!(face.table_records.get(0).unwrap().tag, Tag::from_bytes(b"cmap"));
+        assert_eq!(face.table(Tag::from_bytes(b"cmap")), Some(&[4u8, 5, 6, 7, 8][..]));
+        assert_eq!(face.table(Tag::from_bytes(b"name")), Some(&[1u8, 2, 3][..]));
+        assert_eq!(font.len() % 4, 0);
+    }
+
+    #[test]
+    fn it_keeps_single_fonts_unchanged() {
+        let data = include_bytes!("../assets/NotoSansMono.ttf");
+        assert_eq!(extract_face(data, 0).unwrap(), data.to_vec());
+        assert!(extract_face(data, 1).is_err());
+    }
+
+    #[test]
+    fn it_extracts_a_face_of_a_collection() {
+        let data = include_bytes!("../assets/NotoSansMono.ttf");
+        let face = ttf_parser::RawFace::parse(data, 0).unwrap();
+        let tables: Vec<(Tag, &[u8])> = face.table_records.into_iter()
+            .map(|record| (record.tag, face.table(record.tag).unwrap()))
+            .collect();
+        let post = tables.iter().position(|(tag, _)| *tag == Tag::from_bytes(b"post")).unwrap();
+        let all: Vec<usize> = (0..tables.len()).collect();
+        let without_post: Vec<usize> = all.iter().copied().filter(|index| *index != post).collect();
+        let collection = collection(&tables, &[&without_post, &all]);
+        assert_eq!(ttf_parser::fonts_in_collection(&collection), Some(2));
+
+        // Face 1 lists every table and comes back as a valid font with the same tables.
+        let extracted = extract_face(&collection, 1).unwrap();
+        let second = ttf_parser::RawFace::parse(&extracted, 0).unwrap();
+        assert_eq!(second.table_records.len() as usize, tables.len());
+        // `head` only differs by its recomputed checksum adjustment.
+        for (tag, data) in tables.iter().filter(|(tag, _)| *tag != Tag::from_bytes(b"head")) {
+            assert_eq!(second.table(*tag), Some(*data));
+        }
+        let original = ttf_parser::Face::parse(data, 0).unwrap();
+        assert_eq!(ttf_parser::Face::parse(&extracted, 0).unwrap().number_of_glyphs(), original.number_of_glyphs());
+
+        let first = extract_face(&collection, 0).unwrap();
+        assert!(ttf_parser::RawFace::parse(&first, 0).unwrap().table(Tag::from_bytes(b"post")).is_none());
+        assert!(extract_face(&collection, 2).is_err());
+    }
+}
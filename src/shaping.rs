@@ -0,0 +1,200 @@
+use rustybuzz::ttf_parser::Tag;
+use rustybuzz::{Feature, UnicodeBuffer};
+use crate::font::{with_actual_text, Font, FontEncoding};
+
+/// A glyph positioned by the shaper. Advances and offsets are in 1/1000 em.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShapedGlyph {
+    pub glyph_id: u16,
+    /// Byte offset in the shaped text of the first character the glyph comes from.
+    pub cluster: usize,
+    pub x_advance: i32,
+    pub x_offset: i32,
+    pub y_offset: i32,
+}
+
+/// Shapes `text` with the OpenType layout tables of `font`: ligatures, contextual forms,
+/// reordering and mark positioning. `rtl` runs are shaped right to left, glyphs are always
+/// returned in visual order.
+///
+/// Returns `None` for fonts that are not embedded.
+pub fn shape(font: &Font, text: &str, rtl: bool, kerning: bool) -> Option<Vec<ShapedGlyph>> {
+    let face = rustybuzz::Face::from_slice(&font.data, 0)?;
+    let to_thousand = |value: i32| (value as f32 * 1000.0 / face.units_per_em() as f32).round() as i32;
+
+    let mut buffer = UnicodeBuffer::new();
+    buffer.push_str(text);
+    buffer.set_direction(if rtl { rustybuzz::Direction::RightToLeft } else { rustybuzz::Direction::LeftToRight });
+    let features = if kerning { Vec::new() } else { vec![Feature::new(Tag::from_bytes(b"kern"), 0, ..)] };
+    let output = rustybuzz::shape(&face, &features, buffer);
+
+    Some(output.glyph_infos().iter()
+        .zip(output.glyph_positions())
+        .map(|(info, position)| ShapedGlyph {
+            glyph_id: info.glyph_id as u16,
+            cluster: info.cluster as usize,
+            x_advance: to_thousand(position.x_advance),
+            x_offset: to_thousand(position.x_offset),
+            y_offset: to_thousand(position.y_offset),
+        })
+        .collect())
+}
+
+/// Width of the shaped `text` set at `size` points, in points.
+///
+/// Only composite and Type 3 fonts are shaped when writing `Text`, so `None` is returned for
+/// simple fonts, which are measured character by character.
+pub fn shaped_width(font: &Font, text: &str, size: f32, rtl: bool, kerning: bool) -> Option<f32> {
+    if matches!(font.encoding, FontEncoding::Simple(_)) {
+        return None;
+    }
+    let advance: i32 = shape(font, text, rtl, kerning)?.iter().map(|glyph| glyph.x_advance).sum();
+    Some(advance as f32 * size / 1000.0)
+}
+
+/// Text showing operators for the shaped `text` of a composite font set at `size` points.
+///
+/// Advances and horizontal offsets become `TJ` adjustments and vertical offsets a text rise
+/// added to `base_rise`, the rise of the text itself.
+/// Each cluster maps its first glyph to its text in `/ToUnicode`. When that is not enough
+/// to recover the text (a cluster with several glyphs or reordered clusters), the operators
+/// are wrapped in a marked-content sequence with the text as `/ActualText`.
+///
+/// Returns `None` when the font is not composite, as the glyph ids can't be addressed.
+pub fn show_shaped_text(font: &Font, text: &str, size: f32, rtl: bool, kerning: bool, base_rise: f32) -> Option<String> {
+    if font.encoding != FontEncoding::Identity {
+        return None;
+    }
+    let glyphs = shape(font, text, rtl, kerning)?;
+
+    let mut needs_actual_text = glyphs.windows(2).any(|pair| pair[1].cluster < pair[0].cluster);
+    let mut operators = String::new();
+    let mut array = String::new();
+    let mut run: Vec<(u16, String)> = Vec::new();
+    let mut rise = 0;
+
+    for (glyph, text) in glyphs.iter().zip(glyph_texts(text, &glyphs)) {
+        if text.is_empty() {
+            needs_actual_text = true;
+        }
+
+        if glyph.y_offset != rise {
+            flush_array(font, &mut operators, &mut array, &mut run);
+            rise = glyph.y_offset;
+            operators.push_str(&format!("{:.2} Ts\n", base_rise + rise as f32 * size / 1000.0));
+        }
+        if glyph.x_offset != 0 {
+            push_adjustment(font, &mut array, &mut run, -glyph.x_offset);
+        }
+        run.push((glyph.glyph_id, text));
+
+        // After the glyph the pen moved by its width (plus the offset), the shaper wants it
+        // moved by the advance.
+        let width = font.widths.get(glyph.glyph_id as usize).copied().unwrap_or(0) as i32;
+        let adjustment = width + glyph.x_offset - glyph.x_advance;
+        if adjustment != 0 {
+            push_adjustment(font, &mut array, &mut run, adjustment);
+        }
+    }
+    flush_array(font, &mut operators, &mut array, &mut run);
+    if rise != 0 {
+        operators.push_str(&format!("{:.2} Ts\n", base_rise));
+    }
+
+    let operators = operators.trim_end().to_string();
+    if needs_actual_text {
+        Some(with_actual_text(text, &operators))
+    } else {
+        Some(operators)
+    }
+}
+
+/// Shaped glyph ids of `text` in visual order, each with the text of its cluster (empty for
+/// the glyphs after the first one of a cluster), as shown on Type 3 fonts.
+pub fn shaped_glyphs(font: &Font, text: &str, rtl: bool, kerning: bool) -> Option<Vec<(u16, String)>> {
+    let glyphs = shape(font, text, rtl, kerning)?;
+    Some(glyphs.iter().map(|glyph| glyph.glyph_id).zip(glyph_texts(text, &glyphs)).collect())
+}
+
+// Text each glyph stands for: its whole cluster for the first glyph of a cluster, nothing for
+// the others.
+fn glyph_texts(text: &str, glyphs: &[ShapedGlyph]) -> Vec<String> {
+    let mut cluster_starts: Vec<usize> = glyphs.iter().map(|glyph| glyph.cluster).collect();
+    cluster_starts.sort_unstable();
+    cluster_starts.dedup();
+
+    glyphs.iter()
+        .enumerate()
+        .map(|(index, glyph)| {
+            if index > 0 && glyphs[index - 1].cluster == glyph.cluster {
+                return String::new();
+            }
+            let end = cluster_starts.iter()
+                .find(|start| **start > glyph.cluster)
+                .copied()
+                .unwrap_or(text.len());
+            text[glyph.cluster..end].to_string()
+        })
+        .collect()
+}
+
+fn push_adjustment(font: &Font, array: &mut String, run: &mut Vec<(u16, String)>, adjustment: i32) {
+    if !run.is_empty() {
+        array.push_str(&font.pdf_glyph_string(run));
+        run.clear();
+    }
+    array.push_str(&format!(" {} ", adjustment));
+}
+
+fn flush_array(font: &Font, operators: &mut String, array: &mut String, run: &mut Vec<(u16, String)>) {
+    if !run.is_empty() {
+        array.push_str(&font.pdf_glyph_string(run));
+        run.clear();
+    }
+    if !array.is_empty() {
+        operators.push_str(&format!("[{}] TJ\n", array.trim()));
+        array.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{shape, shaped_width, show_shaped_text};
+    use crate::font::{Font, FontOptions};
+    use crate::reference::Ref;
+    use crate::standard_font::StandardFont;
+
+    fn composite_font() -> Font {
+        let options = FontOptions { composite: true, ..FontOptions::default() };
+        Font::new_embedded("F1", Ref::new(1), include_bytes!("../assets/Helvetica.ttf"), &options).unwrap()
+    }
+
+    #[test]
+    fn it_forms_ligatures_and_keeps_their_text() {
+        let font = composite_font();
+        let glyphs = shape(&font, "fi", false, true).unwrap();
+        assert_eq!(glyphs.len(), 1);
+        assert_ne!(Some(glyphs[0].glyph_id), font.glyph_id('f'));
+
+        let operators = show_shaped_text(&font, "fi", 12.0, false, true, 0.0).unwrap();
+        assert_eq!(operators, format!("[<{:04X}>] TJ", glyphs[0].glyph_id));
+        assert_eq!(font.used_glyphs().get(&glyphs[0].glyph_id).map(String::as_str), Some("fi"));
+    }
+
+    #[test]
+    fn it_applies_kerning_through_gpos() {
+        let font = composite_font();
+        let kerned = shaped_width(&font, "AV", 10.0, false, true).unwrap();
+        let unkerned = shaped_width(&font, "AV", 10.0, false, false).unwrap();
+        assert!(kerned < unkerned);
+        assert!(show_shaped_text(&font, "AV", 10.0, false, true, 0.0).unwrap().starts_with('['));
+    }
+
+    #[test]
+    fn it_leaves_simple_fonts_unshaped() {
+        let simple = Font::new_embedded("F1", Ref::new(1), include_bytes!("../assets/Helvetica.ttf"), &FontOptions::default()).unwrap();
+        assert!(show_shaped_text(&simple, "fi", 12.0, false, true, 0.0).is_none());
+        let standard = Font::new_standard("F2", Ref::new(2), StandardFont::Helvetica);
+        assert!(shape(&standard, "fi", false, true).is_none());
+    }
+}
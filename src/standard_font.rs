@@ -0,0 +1,132 @@
+use crate::afm::AfmMetrics;
+use crate::encoding::Encoding;
+
+/// The 14 standard Type 1 fonts every PDF viewer provides, used without embedding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StandardFont {
+    Helvetica,
+    HelveticaBold,
+    HelveticaOblique,
+    HelveticaBoldOblique,
+    TimesRoman,
+    TimesBold,
+    TimesItalic,
+    TimesBoldItalic,
+    Courier,
+    CourierBold,
+    CourierOblique,
+    CourierBoldOblique,
+    Symbol,
+    ZapfDingbats,
+}
+
+impl StandardFont {
+
+    pub fn base_font(&self) -> &'static str {
+        match self {
+            StandardFont::Helvetica => "Helvetica",
+            StandardFont::HelveticaBold => "Helvetica-Bold",
+            StandardFont::HelveticaOblique => "Helvetica-Oblique",
+            StandardFont::HelveticaBoldOblique => "Helvetica-BoldOblique",
+            StandardFont::TimesRoman => "Times-Roman",
+            StandardFont::TimesBold => "Times-Bold",
+            StandardFont::TimesItalic => "Times-Italic",
+            StandardFont::TimesBoldItalic => "Times-BoldItalic",
+            StandardFont::Courier => "Courier",
+            StandardFont::CourierBold => "Courier-Bold",
+            StandardFont::CourierOblique => "Courier-Oblique",
+            StandardFont::CourierBoldOblique => "Courier-BoldOblique",
+            StandardFont::Symbol => "Symbol",
+            StandardFont::ZapfDingbats => "ZapfDingbats",
+        }
+    }
+
+    pub fn encoding(&self) -> Encoding {
+        match self {
+            StandardFont::Symbol => Encoding::Symbol,
+            StandardFont::ZapfDingbats => Encoding::ZapfDingbats,
+            _ => Encoding::WinAnsi,
+        }
+    }
+
+    /// `/Flags` of the font descriptor (PDF 32000-1, table 123).
+    pub fn flags(&self) -> u32 {
+        const FIXED_PITCH: u32 = 1;
+        const SERIF: u32 = 1 << 1;
+        const SYMBOLIC: u32 = 1 << 2;
+        const NONSYMBOLIC: u32 = 1 << 5;
+        const ITALIC: u32 = 1 << 6;
+
+        match self {
+            StandardFont::Helvetica | StandardFont::HelveticaBold => NONSYMBOLIC,
+            StandardFont::HelveticaOblique | StandardFont::HelveticaBoldOblique => NONSYMBOLIC | ITALIC,
+            StandardFont::TimesRoman | StandardFont::TimesBold => NONSYMBOLIC | SERIF,
+            StandardFont::TimesItalic | StandardFont::TimesBoldItalic => NONSYMBOLIC | SERIF | ITALIC,
+            StandardFont::Courier | StandardFont::CourierBold => NONSYMBOLIC | FIXED_PITCH | SERIF,
+            StandardFont::CourierOblique | StandardFont::CourierBoldOblique => NONSYMBOLIC | FIXED_PITCH | SERIF | ITALIC,
+            StandardFont::Symbol | StandardFont::ZapfDingbats => SYMBOLIC,
+        }
+    }
+
+    pub fn metrics(&self) -> AfmMetrics {
+        let data = match self {
+            StandardFont::Helvetica => include_str!("../assets/afm/Helvetica.afm"),
+            StandardFont::HelveticaBold => include_str!("../assets/afm/Helvetica-Bold.afm"),
+            StandardFont::HelveticaOblique => include_str!("../assets/afm/Helvetica-Oblique.afm"),
+            StandardFont::HelveticaBoldOblique => include_str!("../assets/afm/Helvetica-BoldOblique.afm"),
+            StandardFont::TimesRoman => include_str!("../assets/afm/Times-Roman.afm"),
+            StandardFont::TimesBold => include_str!("../assets/afm/Times-Bold.afm"),
+            StandardFont::TimesItalic => include_str!("../assets/afm/Times-Italic.afm"),
+            StandardFont::TimesBoldItalic => include_str!("../assets/afm/Times-BoldItalic.afm"),
+            StandardFont::Courier => include_str!("../assets/afm/Courier.afm"),
+            StandardFont::CourierBold => include_str!("../assets/afm/Courier-Bold.afm"),
+            StandardFont::CourierOblique => include_str!("../assets/afm/Courier-Oblique.afm"),
+            StandardFont::CourierBoldOblique => include_str!("../assets/afm/Courier-BoldOblique.afm"),
+            StandardFont::Symbol => include_str!("../assets/afm/Symbol.afm"),
+            StandardFont::ZapfDingbats => include_str!("../assets/afm/ZapfDingbats.afm"),
+        };
+
+        AfmMetrics::parse(data).expect("bundled AFM metrics are valid")
+    }
+
+    /// Advance widths in 1/1000 em for every code of the font's encoding.
+    pub fn widths(&self) -> Vec<u16> {
+        let metrics = self.metrics();
+        let encoding = self.encoding();
+
+        (0..=255u8).map(|code| {
+            let width = match encoding.glyph_name(code) {
+                Some(name) => metrics.width_of_glyph(name),
+                None => metrics.width_of_code(code),
+            };
+            width.unwrap_or(0.0).round() as u16
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StandardFont;
+
+    #[test]
+    fn it_loads_all_bundled_metrics() {
+        let fonts = [
+            StandardFont::Helvetica, StandardFont::HelveticaBold, StandardFont::HelveticaOblique,
+            StandardFont::HelveticaBoldOblique, StandardFont::TimesRoman, StandardFont::TimesBold,
+            StandardFont::TimesItalic, StandardFont::TimesBoldItalic, StandardFont::Courier,
+            StandardFont::CourierBold, StandardFont::CourierOblique, StandardFont::CourierBoldOblique,
+            StandardFont::Symbol, StandardFont::ZapfDingbats,
+        ];
+        for font in fonts {
+            assert_eq!(font.metrics().font_name, font.base_font());
+        }
+    }
+
+    #[test]
+    fn it_maps_win_ansi_widths_by_glyph_name() {
+        let widths = StandardFont::Helvetica.widths();
+        assert_eq!(widths[b'A' as usize], 667);
+        assert_eq!(widths[0x80], 556);
+        assert!(StandardFont::Courier.widths()[32..127].iter().all(|width| *width == 600));
+    }
+}
@@ -0,0 +1,191 @@
+//! Made up font and image data shared by the tests of several modules.
+
+use std::io::Write;
+use ttf_parser::Tag;
+use crate::sfnt::{write_sfnt, TRUETYPE_VERSION};
+
+/// The patterns Liang's thesis uses to hyphenate "hyphenation".
+pub const HYPHENATION_PATTERNS: &str = "% Test patterns\n\\patterns{ % hyphenation\nhy3ph he2n hena4 hen5at 1na n2at 1tio 2io o2n\n}\n\\hyphenation{ as-so-ciate }\n";
+
+/// Thai words: language, Thai, easy and very, with ไท and ยง to mislead the segmentation.
+pub const THAI_WORDS: &str = "# Test words\nภาษา\nไทย\nไท\nยง\nง่าย\nมาก\n";
+
+/// AFM metrics of the Type 1 font of `pfb`: a space, 'A' and 'V', with an A V kerning pair.
+pub const TYPE1_AFM: &str = "StartFontMetrics 4.1\nFontName Test-Regular\nEncodingScheme AdobeStandardEncoding\nFontBBox -50 -200 900 800\nStartCharMetrics 3\nC 32 ; WX 250 ; N space ;\nC 65 ; WX 600 ; N A ;\nC 86 ; WX 700 ; N V ;\nEndCharMetrics\nStartKernPairs 1\nKPX A V -80\nEndKernPairs\nEndFontMetrics\n";
+
+pub const TYPE1_CLEARTEXT: &[u8] = b"%!PS-AdobeFont-1.0: Test-Regular 001.000\n/FontName /Test-Regular def\ncurrentfile eexec\n";
+pub const TYPE1_ENCRYPTED: &[u8] = &[0xD9, 0xD6, 0x6F, 0x63, 0x30, 0x0A, 0x20];
+
+/// The zeros and `cleartomark` ending Type 1 programs.
+pub fn type1_trailer() -> Vec<u8> {
+    let mut trailer = Vec::new();
+    for _ in 0..8 {
+        trailer.extend_from_slice(&[b'0'; 64]);
+        trailer.push(b'\n');
+    }
+    trailer.extend_from_slice(b"cleartomark\n");
+    trailer
+}
+
+/// A PFB font program with a tiny made up encrypted part.
+pub fn pfb() -> Vec<u8> {
+    let mut pfb = Vec::new();
+    for (segment_type, segment) in [(1u8, TYPE1_CLEARTEXT.to_vec()), (2, TYPE1_ENCRYPTED.to_vec()), (1, type1_trailer())] {
+        pfb.extend_from_slice(&[0x80, segment_type]);
+        pfb.extend_from_slice(&(segment.len() as u32).to_le_bytes());
+        pfb.extend_from_slice(&segment);
+    }
+    pfb.extend_from_slice(&[0x80, 3]);
+    pfb
+}
+
+/// An 8 bit PNG image of color type `color_type`, each row starting with its filter type.
+pub fn png(color_type: u8, rows: &[&[u8]], width: u32) -> Vec<u8> {
+    let mut raw = Vec::new();
+    for row in rows {
+        raw.extend_from_slice(row);
+    }
+    let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(&raw).unwrap();
+    let compressed = encoder.finish().unwrap();
+
+    let mut header = Vec::new();
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&(rows.len() as u32).to_be_bytes());
+    header.extend_from_slice(&[8, color_type, 0, 0, 0]);
+
+    let mut data = crate::png::SIGNATURE.to_vec();
+    for (kind, chunk) in [(b"IHDR", header), (b"IDAT", compressed), (b"IEND", Vec::new())] {
+        data.extend_from_slice(&(chunk.len() as u32).to_be_bytes());
+        data.extend_from_slice(kind);
+        data.extend_from_slice(&chunk);
+        // The CRC is not checked.
+        data.extend_from_slice(&[0; 4]);
+    }
+    data
+}
+
+/// Tables of the font `data`.
+pub fn font_tables(data: &[u8]) -> Vec<(Tag, Vec<u8>)> {
+    let face = ttf_parser::RawFace::parse(data, 0).unwrap();
+    face.table_records.into_iter()
+        .map(|record| (record.tag, data[record.offset as usize..(record.offset + record.length) as usize].to_vec()))
+        .collect()
+}
+
+fn noto_sans_mono_tables() -> Vec<(Tag, Vec<u8>)> {
+    font_tables(include_bytes!("../assets/NotoSansMono.ttf"))
+}
+
+/// Noto Sans Mono with `tables` added.
+fn noto_sans_mono_with(tables: Vec<(Tag, Vec<u8>)>) -> Vec<u8> {
+    let mut all_tables = noto_sans_mono_tables();
+    all_tables.extend(tables);
+    write_sfnt(TRUETYPE_VERSION, all_tables)
+}
+
+/// Noto Sans Mono without its `glyf` and `loca` tables: the metrics are all there but the
+/// font can't be subset.
+pub fn outlineless_font() -> Vec<u8> {
+    let tables = noto_sans_mono_tables().into_iter()
+        .filter(|(tag, _)| *tag != Tag::from_bytes(b"glyf") && *tag != Tag::from_bytes(b"loca"))
+        .collect();
+    write_sfnt(TRUETYPE_VERSION, tables)
+}
+
+/// A `ttcf` collection of faces sharing the table data, each face listing some tables.
+pub fn collection(tables: &[(Tag, &[u8])], faces: &[&[usize]]) -> Vec<u8> {
+    let header_length = 12 + 4 * faces.len();
+    let directories_length: usize = faces.iter().map(|face| 12 + 16 * face.len()).sum();
+    let mut table_offsets = Vec::new();
+    let mut offset = header_length + directories_length;
+    for (_, data) in tables {
+        table_offsets.push(offset);
+        offset += data.len().next_multiple_of(4);
+    }
+
+    let mut font = b"ttcf".to_vec();
+    font.extend_from_slice(&0x0001_0000u32.to_be_bytes());
+    font.extend_from_slice(&(faces.len() as u32).to_be_bytes());
+    let mut directory_offset = header_length;
+    for face in faces {
+        font.extend_from_slice(&(directory_offset as u32).to_be_bytes());
+        directory_offset += 12 + 16 * face.len();
+    }
+    for face in faces {
+        font.extend_from_slice(&TRUETYPE_VERSION.to_be_bytes());
+        font.extend_from_slice(&(face.len() as u16).to_be_bytes());
+        font.extend_from_slice(&[0; 6]);
+        for index in face.iter() {
+            let (tag, data) = tables[*index];
+            font.extend_from_slice(&tag.as_u32().to_be_bytes());
+            font.extend_from_slice(&[0; 4]);
+            font.extend_from_slice(&(table_offsets[*index] as u32).to_be_bytes());
+            font.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        }
+    }
+    for (_, data) in tables {
+        font.extend_from_slice(data);
+        font.resize(font.len().next_multiple_of(4), 0);
+    }
+    font
+}
+
+fn glyph_id(c: char) -> u16 {
+    let face = ttf_parser::Face::parse(include_bytes!("../assets/NotoSansMono.ttf"), 0).unwrap();
+    face.glyph_index(c).unwrap().0
+}
+
+/// Noto Sans Mono with a `COLR` version 0 glyph for 'A': the outline of 'A' in opaque red,
+/// the outline of 'B' in blue with alpha 128 and the outline of 'C' in the foreground color.
+pub fn colr_font() -> Vec<u8> {
+    let mut colr = Vec::new();
+    // Version, one base glyph record at 14, layer records at 20, three layers.
+    for value in [0u16, 1, 0, 14, 0, 20, 3] {
+        colr.extend_from_slice(&value.to_be_bytes());
+    }
+    for value in [glyph_id('A'), 0, 3, glyph_id('A'), 0, glyph_id('B'), 1, glyph_id('C'), 0xFFFF] {
+        colr.extend_from_slice(&value.to_be_bytes());
+    }
+
+    let mut cpal = Vec::new();
+    // Version, two entries, one palette, two color records at 14, the palette starting at 0.
+    for value in [0u16, 2, 1, 2, 0, 14, 0] {
+        cpal.extend_from_slice(&value.to_be_bytes());
+    }
+    // BGRA color records.
+    cpal.extend_from_slice(&[0, 0, 255, 255, 255, 0, 0, 128]);
+
+    noto_sans_mono_with(vec![(Tag::from_bytes(b"COLR"), colr), (Tag::from_bytes(b"CPAL"), cpal)])
+}
+
+/// Noto Sans Mono with an `sbix` strike of 20 pixels per em holding a 2x1 PNG image for 'A',
+/// placed 1 pixel left and 2 pixels below the origin.
+pub fn sbix_font() -> Vec<u8> {
+    let face = ttf_parser::Face::parse(include_bytes!("../assets/NotoSansMono.ttf"), 0).unwrap();
+    let number_of_glyphs = face.number_of_glyphs() as u32;
+    let glyph = glyph_id('A') as u32;
+
+    let mut glyph_data = Vec::new();
+    glyph_data.extend_from_slice(&(-1i16).to_be_bytes());
+    glyph_data.extend_from_slice(&(-2i16).to_be_bytes());
+    glyph_data.extend_from_slice(b"png ");
+    glyph_data.extend_from_slice(&png(2, &[&[0, 255, 0, 0, 0, 0, 255]], 2));
+
+    let mut sbix = Vec::new();
+    // Version, flags, one strike at 12.
+    sbix.extend_from_slice(&[0, 1, 0, 1]);
+    sbix.extend_from_slice(&1u32.to_be_bytes());
+    sbix.extend_from_slice(&12u32.to_be_bytes());
+    // Strike: size, resolution and the offsets of each glyph data, relative to the strike.
+    sbix.extend_from_slice(&20u16.to_be_bytes());
+    sbix.extend_from_slice(&72u16.to_be_bytes());
+    let data_start = 4 + 4 * (number_of_glyphs + 1);
+    for index in 0..=number_of_glyphs {
+        let offset = if index <= glyph { data_start } else { data_start + glyph_data.len() as u32 };
+        sbix.extend_from_slice(&offset.to_be_bytes());
+    }
+    sbix.extend_from_slice(&glyph_data);
+
+    noto_sans_mono_with(vec![(Tag::from_bytes(b"sbix"), sbix)])
+}
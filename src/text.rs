@@ -1,14 +1,106 @@
 use std::rc::Rc;
 use crate::types::{Point, RGB};
 use crate::Font;
+use crate::bidi::{self, Direction};
+use crate::font_fallback::FontFallback;
+use crate::vertical;
+use crate::font::{with_actual_text, FontEncoding, FontFormat, SYNTHETIC_BOLD_STROKE, SYNTHETIC_OBLIQUE_SKEW};
+use crate::outline::{concat, PathBuilder, Transform};
+#[cfg(feature = "shaping")]
+use crate::shaping;
 
 pub struct Text {
     pub text: String,
     //use struct Font
     pub font: Rc<Font>,
     pub size: i32,
+    /// Top of the text at its aligned edge. Text set with a vertical font (see
+    /// `RsPdf::add_vertical_font`) runs down a column centered on the point.
     pub point: Point,
     pub color: RGB,
+    /// Whether the kerning pairs of the font are applied, on by default.
+    pub kerning: bool,
+    /// Base direction used to order mixed left to right and right to left text.
+    pub direction: Direction,
+    pub alignment: Alignment,
+    /// Fonts tried after `font` for characters it has no glyph for.
+    pub fallback: Option<FontFallback>,
+    /// Whether the glyphs are drawn as filled paths instead of text, see `with_outlines`.
+    pub outlines: bool,
+    /// How the glyphs are painted (`Tr`), filled by default.
+    pub rendering_mode: RenderingMode,
+    /// Color of stroked glyphs, `color` when not set.
+    pub stroke_color: Option<RGB>,
+    /// Line width of stroked glyphs, in points.
+    pub stroke_width: f32,
+    /// Extra space after each glyph, in points (`Tc`).
+    pub character_spacing: f32,
+    /// Extra space after each space character, in points, e.g. to justify a line.
+    pub word_spacing: f32,
+    /// Width of the glyphs and spacing in percent of their normal width (`Tz`).
+    pub horizontal_scaling: f32,
+    /// Distance the baseline is raised, in points (`Ts`), negative to lower it.
+    pub rise: f32,
+}
+
+/// Horizontal placement of a text relative to its point.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Alignment {
+    /// The point is where the text starts: its left edge for left to right text, its right
+    /// edge for right to left text.
+    #[default]
+    Start,
+    /// The point is where the text ends.
+    End,
+    Left,
+    Center,
+    Right,
+    /// Lines of a `Paragraph` are stretched to fill its width by widening their spaces, except
+    /// the last line of each paragraph. Lines without spaces, like CJK text broken between
+    /// ideographs, and other text are placed as with `Start`.
+    Justify,
+}
+
+/// How the glyphs of a `Text` are painted, set with `Tr`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RenderingMode {
+    #[default]
+    Fill,
+    Stroke,
+    FillStroke,
+    /// Neither filled nor stroked, e.g. for a searchable layer over a scanned page.
+    Invisible,
+    /// Not painted, the glyphs are added to the clipping path of the content that follows.
+    Clip,
+}
+
+impl RenderingMode {
+
+    /// Operand of `Tr` for the mode.
+    pub fn value(self) -> u8 {
+        match self {
+            RenderingMode::Fill => 0,
+            RenderingMode::Stroke => 1,
+            RenderingMode::FillStroke => 2,
+            RenderingMode::Invisible => 3,
+            RenderingMode::Clip => 7,
+        }
+    }
+
+    // Path painting operator of glyphs drawn as outlines.
+    fn paint_operator(self) -> &'static str {
+        match self {
+            RenderingMode::Fill => "f",
+            RenderingMode::Stroke => "S",
+            RenderingMode::FillStroke => "B",
+            RenderingMode::Invisible => "n",
+            RenderingMode::Clip => "W n",
+        }
+    }
+
+    fn strokes(self) -> bool {
+        matches!(self, RenderingMode::Stroke | RenderingMode::FillStroke)
+    }
 }
 
 impl Text {
@@ -19,6 +111,18 @@ impl Text {
             point,
             size,
             color,
+            kerning: true,
+            direction: Direction::Auto,
+            alignment: Alignment::Start,
+            fallback: None,
+            outlines: false,
+            rendering_mode: RenderingMode::Fill,
+            stroke_color: None,
+            stroke_width: 1.0,
+            character_spacing: 0.0,
+            word_spacing: 0.0,
+            horizontal_scaling: 100.0,
+            rise: 0.0,
         }
     }
     pub fn new_with_default_color(text: &str, font: Rc<Font>, size: i32, point: Point) -> Text {
@@ -28,6 +132,478 @@ impl Text {
             point,
             size,
             color: RGB(0, 0, 0),
+            kerning: true,
+            direction: Direction::Auto,
+            alignment: Alignment::Start,
+            fallback: None,
+            outlines: false,
+            rendering_mode: RenderingMode::Fill,
+            stroke_color: None,
+            stroke_width: 1.0,
+            character_spacing: 0.0,
+            word_spacing: 0.0,
+            horizontal_scaling: 100.0,
+            rise: 0.0,
+        }
+    }
+
+    pub fn with_kerning(mut self, kerning: bool) -> Text {
+        self.kerning = kerning;
+        self
+    }
+
+    pub fn with_direction(mut self, direction: Direction) -> Text {
+        self.direction = direction;
+        self
+    }
+
+    pub fn with_alignment(mut self, alignment: Alignment) -> Text {
+        self.alignment = alignment;
+        self
+    }
+
+    /// Draws the text with a chain of fonts, each character with the first font that covers
+    /// it. The primary font of the chain replaces `font`.
+    pub fn with_fallback(mut self, fallback: FontFallback) -> Text {
+        self.font = Rc::clone(fallback.primary());
+        self.fallback = Some(fallback);
+        self
+    }
+
+    /// Draws the glyphs as filled paths made of their outlines, so the page shows them without
+    /// any font. Quadratic TrueType curves are converted to cubic ones. Only horizontal text
+    /// is outlined, and runs in fonts without outlines (standard, Type 1 and Type 3 fonts)
+    /// are still shown as text.
+    pub fn with_outlines(mut self, outlines: bool) -> Text {
+        self.outlines = outlines;
+        self
+    }
+
+    /// Paints the glyphs with `rendering_mode`. Fonts with a synthetic bold face stroke
+    /// filled glyphs with the fill color, and widen the strokes of stroked glyphs, see
+    /// `RsPdf::add_synthetic_font`.
+    pub fn with_rendering_mode(mut self, rendering_mode: RenderingMode) -> Text {
+        self.rendering_mode = rendering_mode;
+        self
+    }
+
+    /// Color and line width of the glyphs drawn with `RenderingMode::Stroke` or `FillStroke`.
+    pub fn with_stroke(mut self, color: RGB, width: f32) -> Text {
+        self.stroke_color = Some(color);
+        self.stroke_width = width;
+        self
+    }
+
+    /// Adds `character_spacing` points after each glyph. Like word spacing and horizontal
+    /// scaling, it only applies to horizontal text.
+    pub fn with_character_spacing(mut self, character_spacing: f32) -> Text {
+        self.character_spacing = character_spacing;
+        self
+    }
+
+    /// Widens each space by `word_spacing` points. `Tw` only widens the single byte spaces of
+    /// simple fonts: in composite fonts the gaps are written as `TJ` adjustments between the
+    /// words instead.
+    pub fn with_word_spacing(mut self, word_spacing: f32) -> Text {
+        self.word_spacing = word_spacing;
+        self
+    }
+
+    /// Stretches or narrows the glyphs and spacing to `horizontal_scaling` percent of their
+    /// width.
+    pub fn with_horizontal_scaling(mut self, horizontal_scaling: f32) -> Text {
+        self.horizontal_scaling = horizontal_scaling;
+        self
+    }
+
+    /// Raises the text `rise` points above its baseline, e.g. for superscripts, or lowers it
+    /// for subscripts when negative. The width of the text is unchanged.
+    pub fn with_rise(mut self, rise: f32) -> Text {
+        self.rise = rise;
+        self
+    }
+
+    /// Characters that neither `font` nor the fallback fonts cover, drawn as `.notdef` boxes.
+    pub fn missing_chars(&self) -> Vec<char> {
+        self.font_chain().missing_chars(&self.text)
+    }
+
+    /// Fails when some character has no glyph in `font` nor in the fallback fonts.
+    pub fn check_glyphs(&self) -> Result<(), String> {
+        self.font_chain().check(&self.text)
+    }
+
+    /// Width of the text in points, measured with the font metrics. Text set with a vertical
+    /// font returns the length of its column instead, see `Font::measure`.
+    pub fn width(&self) -> f32 {
+        if self.is_vertical() {
+            return self.vertical_runs().iter()
+                .map(|(font, text, _)| font.measure(text, self.size as f32, self.kerning))
+                .sum();
+        }
+        self.runs().iter()
+            .map(|(font, text, rtl)| self.run_width(font, text, *rtl))
+            .sum()
+    }
+
+    fn font_chain(&self) -> FontFallback {
+        self.fallback.clone().unwrap_or_else(|| FontFallback::new(Rc::clone(&self.font)))
+    }
+
+    // Runs with a single direction and font, in visual order. Runs are measured and shown
+    // on their own: kerning and shaping don't cross direction or font changes.
+    fn runs(&self) -> Vec<(Rc<Font>, &str, bool)> {
+        let chain = self.font_chain();
+        let mut runs = Vec::new();
+        for bidi_run in bidi::visual_runs(&self.text, self.direction) {
+            let mut font_runs: Vec<(Rc<Font>, &str, bool)> = chain.runs(bidi_run.text).into_iter()
+                .map(|run| (run.font, run.text, bidi_run.rtl))
+                .collect();
+            if bidi_run.rtl {
+                font_runs.reverse();
+            }
+            runs.extend(font_runs);
+        }
+        runs
+    }
+
+    fn is_vertical(&self) -> bool {
+        self.font.vertical.is_some()
+    }
+
+    // Runs of vertical text in logical order, each upright or sideways. Fallback fonts without
+    // a vertical variant set all their runs sideways.
+    fn vertical_runs(&self) -> Vec<(Rc<Font>, &str, bool)> {
+        let mut runs = Vec::new();
+        for run in self.font_chain().runs(&self.text) {
+            if run.font.vertical.is_some() {
+                runs.extend(vertical::orientation_runs(run.text).into_iter()
+                    .map(|(text, upright)| (Rc::clone(&run.font), text, upright)));
+            } else {
+                runs.push((run.font, run.text, false));
+            }
+        }
+        runs
+    }
+
+    #[cfg_attr(not(feature = "shaping"), allow(unused_variables))]
+    fn run_width(&self, font: &Font, text: &str, rtl: bool) -> f32 {
+        let mut spacing = self.word_spacing * text.matches(' ').count() as f32;
+        if self.character_spacing != 0.0 {
+            spacing += self.character_spacing * self.glyph_count(font, text, rtl) as f32;
+        }
+        let scale = self.horizontal_scaling / 100.0;
+        #[cfg(feature = "shaping")]
+        if let Some(width) = shaping::shaped_width(font, text, self.size as f32, rtl, self.kerning) {
+            return (width + spacing) * scale;
+        }
+        (font.measure(text, self.size as f32, self.kerning) + spacing) * scale
+    }
+
+    // Glyphs shown for a run, each followed by the character spacing.
+    #[cfg_attr(not(feature = "shaping"), allow(unused_variables))]
+    fn glyph_count(&self, font: &Font, text: &str, rtl: bool) -> usize {
+        #[cfg(feature = "shaping")]
+        if !matches!(font.encoding, FontEncoding::Simple(_)) {
+            if let Some(glyphs) = shaping::shape(font, text, rtl, self.kerning) {
+                return glyphs.len();
+            }
+        }
+        text.chars().count()
+    }
+
+    // Rendering mode with the synthetic bold of the font: filled glyphs are stroked as well.
+    fn effective_rendering_mode(&self) -> RenderingMode {
+        match self.rendering_mode {
+            RenderingMode::Fill if self.font.synthetic.bold => RenderingMode::FillStroke,
+            mode => mode,
+        }
+    }
+
+    // Color and line width of the strokes of the glyphs, widened by the stroke of synthetic
+    // bold.
+    fn stroke(&self) -> (RGB, f32) {
+        let bold = if self.font.synthetic.bold { self.size as f32 * SYNTHETIC_BOLD_STROKE } else { 0.0 };
+        if self.rendering_mode == RenderingMode::Fill {
+            (self.color, bold)
+        } else {
+            (self.stroke_color.unwrap_or(self.color), self.stroke_width + bold)
+        }
+    }
+
+    // Text state operators that differ from their defaults, except word spacing which
+    // `show_text` sets for each run.
+    fn text_state(&self) -> String {
+        let mut operators = String::new();
+        let mode = self.effective_rendering_mode();
+        if mode != RenderingMode::Fill {
+            operators.push_str(&format!("{} Tr\n", mode.value()));
+        }
+        if !self.is_vertical() && self.character_spacing != 0.0 {
+            operators.push_str(&format!("{:.3} Tc\n", self.character_spacing));
+        }
+        if !self.is_vertical() && self.horizontal_scaling != 100.0 {
+            operators.push_str(&format!("{:.2} Tz\n", self.horizontal_scaling));
+        }
+        if self.rise != 0.0 {
+            operators.push_str(&format!("{:.2} Ts\n", self.rise));
+        }
+        operators
+    }
+
+    // Operators putting back the text state `text_state` and `show_text` changed. Text state
+    // outlives `ET`, this is used where it can't be kept inside `q`/`Q`.
+    fn reset_text_state(&self) -> String {
+        let mut operators = String::new();
+        if self.effective_rendering_mode() != RenderingMode::Fill {
+            operators.push_str("0 Tr\n");
+        }
+        if !self.is_vertical() {
+            if self.character_spacing != 0.0 {
+                operators.push_str("0 Tc\n");
+            }
+            if self.word_spacing != 0.0 {
+                operators.push_str("0 Tw\n");
+            }
+            if self.horizontal_scaling != 100.0 {
+                operators.push_str("100 Tz\n");
+            }
+        }
+        if self.rise != 0.0 {
+            operators.push_str("0 Ts\n");
+        }
+        operators
+    }
+
+    // Word spacing set with `Tw` for a run, which only applies to the single byte code 32:
+    // the spaces of simple fonts.
+    fn tw_word_spacing(&self, font: &Font) -> f32 {
+        if matches!(font.encoding, FontEncoding::Simple(_)) && font.encode_char(' ') == Some(32) {
+            self.word_spacing
+        } else {
+            0.0
+        }
+    }
+
+    // Text showing operators for the runs in visual order, shaped when the `shaping` feature
+    // is enabled and the font is composite. Right to left runs are otherwise drawn reversed,
+    // with their logical text as `/ActualText`. `Tf` switches fonts between runs.
+    fn show_text(&self) -> String {
+        let mut current_font = self.font.name.clone();
+        let mut current_word_spacing = 0.0;
+        let mut operators: Vec<String> = Vec::new();
+
+        for (font, text, rtl) in self.runs() {
+            if font.name != current_font {
+                operators.push(format!("/{} {} Tf", font.name, self.size));
+                current_font = font.name.clone();
+            }
+            let word_spacing = self.tw_word_spacing(&font);
+            if word_spacing != current_word_spacing {
+                operators.push(format!("{:.3} Tw", word_spacing));
+                current_word_spacing = word_spacing;
+            }
+            operators.push(self.show_spaced_run(&font, text, rtl));
+        }
+        operators.join("\n")
+    }
+
+    // `show_run` with `word_spacing` added after each space. Unless `Tw` does it, the words
+    // are shown one by one, with a `TJ` holding only the gap between them.
+    fn show_spaced_run(&self, font: &Font, text: &str, rtl: bool) -> String {
+        if self.word_spacing == 0.0 || !text.contains(' ') || self.tw_word_spacing(font) != 0.0 {
+            return self.show_run(font, text, rtl);
+        }
+        let gap = format!("[{}] TJ", (-self.word_spacing * 1000.0 / self.size as f32).round() as i32);
+        let words: Vec<&str> = if rtl { text.split_inclusive(' ').rev().collect() } else { text.split_inclusive(' ').collect() };
+
+        let mut operators = Vec::new();
+        for word in words {
+            // Right to left words are reversed, their space is drawn first.
+            let spaced = word.ends_with(' ');
+            if spaced && rtl {
+                operators.push(gap.clone());
+            }
+            operators.push(self.show_run(font, word, rtl));
+            if spaced && !rtl {
+                operators.push(gap.clone());
+            }
+        }
+        operators.join("\n")
+    }
+
+    fn show_run(&self, font: &Font, text: &str, rtl: bool) -> String {
+        if font.encoding == FontEncoding::Type3 {
+            return self.show_type3_run(font, text, rtl);
+        }
+        #[cfg(feature = "shaping")]
+        if let Some(operators) = shaping::show_shaped_text(font, text, self.size as f32, rtl, self.kerning, self.rise) {
+            return operators;
+        }
+        if rtl {
+            with_actual_text(text, &font.show_text(&bidi::reverse_mirrored(text), self.kerning))
+        } else {
+            font.show_text(text, self.kerning)
+        }
+    }
+
+    // Type 3 fonts show their glyphs one byte each, shaped when the `shaping` feature is enabled
+    // so emoji sequences joined with ZWJ become a single glyph.
+    fn show_type3_run(&self, font: &Font, text: &str, rtl: bool) -> String {
+        #[cfg(feature = "shaping")]
+        if let Some(glyphs) = shaping::shaped_glyphs(font, text, rtl, self.kerning) {
+            let operators = font.show_type3_glyphs(&glyphs, self.size as f32);
+            return if rtl { with_actual_text(text, &operators) } else { operators };
+        }
+        if rtl {
+            let glyphs = font.glyphs(&bidi::reverse_mirrored(text));
+            with_actual_text(text, &font.show_type3_glyphs(&glyphs, self.size as f32))
+        } else {
+            font.show_type3_glyphs(&font.glyphs(text), self.size as f32)
+        }
+    }
+
+    // Text showing operators of vertical text, from the top of the column down. Upright runs
+    // are shown with the `/Identity-V` font and the `vert` alternates, sideways runs are
+    // turned clockwise and shown with the horizontal font, centered on the column.
+    fn show_vertical_text(&self) -> String {
+        let size = self.size as f32;
+        let (x, mut y) = (self.point.0, self.point.1);
+        match self.alignment {
+            Alignment::Start | Alignment::Left | Alignment::Justify => {}
+            Alignment::End | Alignment::Right => y += self.width(),
+            Alignment::Center => y += self.width() / 2.0,
+        }
+
+        let mut current_font = self.font.name.clone();
+        let mut operators: Vec<String> = Vec::new();
+        for (font, text, upright) in self.vertical_runs() {
+            let name = match (&font.vertical, upright) {
+                (Some(layout), false) => &layout.horizontal_font,
+                _ => &font.name,
+            };
+            if *name != current_font {
+                operators.push(format!("/{} {} Tf", name, self.size));
+                current_font = name.clone();
+            }
+
+            match (&font.vertical, upright) {
+                (Some(layout), true) => {
+                    let glyphs: Vec<(u16, String)> = font.glyphs(text).into_iter()
+                        .map(|(glyph_id, text)| (layout.alternate(glyph_id), text))
+                        .collect();
+                    operators.push(format!("1 0 0 1 {:.2} {:.2} Tm\n{} Tj", x, y, font.pdf_glyph_string(&glyphs)));
+                }
+                _ => {
+                    let baseline = x - (font.descriptor.ascent + font.descriptor.descent) as f32 * size / 2000.0;
+                    operators.push(format!("0 -1 1 0 {:.2} {:.2} Tm\n{}", baseline, y, self.show_run(&font, text, false)));
+                }
+            }
+            y -= font.measure(text, size, self.kerning);
+        }
+        operators.join("\n")
+    }
+
+    // Path painting operators drawing the glyphs of each run from the left edge, as a single
+    // path painted once: a clipping path of each run would clip the others away. Runs in fonts
+    // without outlines are shown as text at the same place.
+    fn show_outlines(&self) -> String {
+        let size = self.size as f32;
+        let synthetic = self.font.synthetic;
+        let skew = if synthetic.oblique { SYNTHETIC_OBLIQUE_SKEW } else { 0.0 };
+        let paint = self.effective_rendering_mode().paint_operator();
+        let scale = self.horizontal_scaling / 100.0;
+        let (mut x, y) = (self.x(), self.point.1 - (self.font.height * size));
+
+        let mut output = String::new();
+        let mut path = String::new();
+        for (font, text, rtl) in self.runs() {
+            // Same as the text matrix scaled by the size and horizontal scaling, and raised.
+            let transform = [scale * size / 1000.0, 0.0, skew * size / 1000.0, size / 1000.0, x + skew * self.rise, y + self.rise];
+            match self.outline_run(&font, text, rtl, transform) {
+                Some(run_path) => path.push_str(&run_path),
+                None => {
+                    let word_spacing = self.tw_word_spacing(&font);
+                    output.push_str(&format!(
+                        "BT\n/{} {} Tf\n{}{}1 0 {} 1 {:.2} {:.2} Tm\n{}\nET\n",
+                        font.name, self.size,
+                        self.text_state(),
+                        if word_spacing != 0.0 { format!("{:.3} Tw\n", word_spacing) } else { String::new() },
+                        if synthetic.oblique { format!("{:.4}", skew) } else { String::from("0") },
+                        x, y,
+                        self.show_spaced_run(&font, text, rtl)
+                    ));
+                }
+            }
+            x += self.run_width(&font, text, rtl);
+        }
+        if !path.is_empty() {
+            output.push_str(&format!("{}{}\n", path, paint));
+        }
+        output
+    }
+
+    // Outlines of a run placed by `transform`, which maps 1/1000 em to the page. `None` when
+    // the font has no outlines to draw.
+    fn outline_run(&self, font: &Font, text: &str, rtl: bool, transform: Transform) -> Option<String> {
+        if !matches!(font.format, FontFormat::TrueType | FontFormat::OpenType | FontFormat::CidKeyedCff)
+            || font.encoding == FontEncoding::Type3 {
+            return None;
+        }
+        let face = ttf_parser::Face::parse(&font.data, 0).ok()?;
+        let scale = 1000.0 / face.units_per_em() as f32;
+
+        let mut path = String::new();
+        let mut pen = 0.0;
+        for (glyph_id, x_offset, y_offset, advance) in self.positioned_glyphs(font, text, rtl) {
+            let origin = [scale, 0.0, 0.0, scale, pen + x_offset as f32, y_offset as f32];
+            let mut builder = PathBuilder::new(concat(&origin, &transform));
+            if face.outline_glyph(ttf_parser::GlyphId(glyph_id), &mut builder).is_some() {
+                path.push_str(&builder.finish());
+            }
+            pen += advance as f32;
+        }
+        Some(path)
+    }
+
+    // Glyph ids in visual order with their offsets and advances in 1/1000 em, placed like
+    // `show_run` places them.
+    #[cfg_attr(not(feature = "shaping"), allow(unused_variables))]
+    fn positioned_glyphs(&self, font: &Font, text: &str, rtl: bool) -> Vec<(u16, i32, i32, i32)> {
+        let spacing = (self.word_spacing * 1000.0 / self.size as f32).round() as i32;
+        let character_spacing = (self.character_spacing * 1000.0 / self.size as f32).round() as i32;
+        #[cfg(feature = "shaping")]
+        if !matches!(font.encoding, FontEncoding::Simple(_)) {
+            if let Some(glyphs) = shaping::shape(font, text, rtl, self.kerning) {
+                return glyphs.iter()
+                    .map(|glyph| {
+                        let spacing = if text[glyph.cluster..].starts_with(' ') { spacing } else { 0 };
+                        (glyph.glyph_id, glyph.x_offset, glyph.y_offset, glyph.x_advance + spacing + character_spacing)
+                    })
+                    .collect();
+            }
+        }
+
+        let text = if rtl { bidi::reverse_mirrored(text) } else { text.to_string() };
+        let adjustments = if self.kerning { font.kerning(&text) } else { vec![0; text.chars().count()] };
+        text.chars().zip(adjustments)
+            .filter_map(|(c, adjustment)| {
+                let spacing = if c == ' ' { spacing } else { 0 };
+                let c = font.drawn_char(c);
+                let code = font.encode_char(c)?;
+                let width = font.widths.get(code as usize).copied().unwrap_or(0) as i32;
+                Some((font.glyph_id(c).unwrap_or(0), 0, 0, width + adjustment + spacing + character_spacing))
+            })
+            .collect()
+    }
+
+    // Horizontal position of the left edge of the text.
+    fn x(&self) -> f32 {
+        let rtl = self.direction.is_rtl(&self.text);
+        match (self.alignment, rtl) {
+            (Alignment::Left, _) | (Alignment::Start | Alignment::Justify, false) | (Alignment::End, true) => self.point.0,
+            (Alignment::Right, _) | (Alignment::Start | Alignment::Justify, true) | (Alignment::End, false) => self.point.0 - self.width(),
+            (Alignment::Center, _) => self.point.0 - self.width() / 2.0,
         }
     }
 }
@@ -37,14 +613,248 @@ impl From<Text> for Vec<u8> {
         let r = content.color.0 as f32 / 255.0;
         let g = content.color.1 as f32 / 255.0;
         let b = content.color.2 as f32 / 255.0;
+        let synthetic = content.font.synthetic;
+        let mode = content.effective_rendering_mode();
+        let (RGB(stroke_r, stroke_g, stroke_b), stroke_width) = content.stroke();
+        let stroke = format!(
+            "{:.3} {:.3} {:.3} RG\n{:.3} w\n",
+            stroke_r as f32 / 255.0, stroke_g as f32 / 255.0, stroke_b as f32 / 255.0,
+            stroke_width
+        );
+        let text_state = content.text_state();
+        // Text state and line width outlive ET, so they are kept inside q/Q. Clipping text
+        // must change the clipping path of what follows: its text state is put back instead.
+        let clip = mode == RenderingMode::Clip;
+
+        if content.outlines && !content.is_vertical() {
+            let mut output = if clip { String::new() } else { String::from("q\n") };
+            output.push_str(&format!("{:.3} {:.3} {:.3} rg\n", r, g, b));
+            if mode.strokes() {
+                output.push_str(&stroke);
+            }
+            output.push_str(&content.show_outlines());
+            output.push_str(if clip { "" } else { "Q\n" });
+            if clip && output.contains("BT\n") {
+                output.push_str(&content.reset_text_state());
+            }
+            return output.into_bytes();
+        }
+
+        let saved = !clip && (mode.strokes() || !text_state.is_empty() || content.word_spacing != 0.0);
+        let mut output = String::new();
+        if saved {
+            output.push_str("q\n");
+        }
+        if mode.strokes() {
+            output.push_str(&stroke);
+        }
 
-        format!(
-            "{:.3} {:.3} {:.3} rg\nBT\n/{} {} Tf\n1 0 0 1 {:.2} {:.2} Tm\n({}) Tj\nET\n",
+        output.push_str(&format!(
+            "{:.3} {:.3} {:.3} rg\nBT\n/{} {} Tf\n{}",
             r, g, b,
             content.font.name, content.size,
-            content.point.0, content.point.1 - (content.font.height * content.size as f32),
-            content.text
-        ).into_bytes()
+            text_state
+        ));
+
+        if content.is_vertical() {
+            output.push_str(&format!("{}\nET\n", content.show_vertical_text()));
+        } else {
+            let skew = if synthetic.oblique { format!("{:.4}", SYNTHETIC_OBLIQUE_SKEW) } else { String::from("0") };
+            output.push_str(&format!(
+                "1 0 {} 1 {:.2} {:.2} Tm\n{}\nET\n",
+                skew,
+                content.x(), content.point.1 - (content.font.height * content.size as f32),
+                content.show_text()
+            ));
+        }
+        if saved {
+            output.push_str("Q\n");
+        } else if clip {
+            output.push_str(&content.reset_text_state());
+        }
+
+        output.into_bytes()
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+    use super::{Alignment, RenderingMode, Text};
+    use crate::bidi::Direction;
+    use crate::font::{Font, FontOptions, SyntheticStyle};
+    use crate::font_fallback::FontFallback;
+    use crate::reference::Ref;
+    use crate::standard_font::StandardFont;
+    use crate::types::{Point, RGB};
+
+    fn helvetica() -> Rc<Font> {
+        Rc::new(Font::new_standard("F1", Ref::new(1), StandardFont::Helvetica))
+    }
+
+    #[test]
+    fn it_orders_runs_of_rtl_paragraphs() {
+        let text = Text::new_with_default_color("abc!", helvetica(), 10, Point(100.0, 100.0))
+            .with_direction(Direction::RightToLeft);
+        let width = text.width();
+        let output = String::from_utf8(text.into()).unwrap();
+
+        // The trailing "!" takes the paragraph direction and is drawn first, on the left.
+        assert!(output.contains("/Span << /ActualText <FEFF0021> >> BDC\n(!) Tj\nEMC\n(abc) Tj\n"));
+        // RTL paragraphs start at their right edge.
+        assert!(output.contains(&format!("1 0 0 1 {:.2} ", 100.0 - width)));
+    }
+
+    #[test]
+    fn it_switches_to_fallback_fonts() {
+        let symbol = Rc::new(Font::new_standard("F2", Ref::new(2), StandardFont::Symbol));
+        let text = Text::new_with_default_color("a = αβ 漢", helvetica(), 10, Point(0.0, 100.0))
+            .with_fallback(FontFallback::new(helvetica()).then(symbol));
+        assert_eq!(text.missing_chars(), vec!['漢']);
+        assert!(text.check_glyphs().is_err());
+
+        let output = String::from_utf8(text.into()).unwrap();
+        let (symbol_switch, back_switch) = (output.find("/F2 10 Tf").unwrap(), output.find("/F1 10 Tf\n(").unwrap());
+        assert!(symbol_switch < back_switch);
+        // Each run is encoded for its own font: no UTF-8 bytes end up in a simple font string.
+        assert!(output.is_ascii());
+        assert!(output.contains("(a = ) Tj\n/F2 10 Tf\n(ab) Tj\n/F1 10 Tf\n( ?) Tj"));
+    }
+
+    #[test]
+    fn it_aligns_around_the_point() {
+        let text = Text::new_with_default_color("abc", helvetica(), 10, Point(100.0, 100.0))
+            .with_alignment(Alignment::Center);
+        let width = text.width();
+        let output = String::from_utf8(text.into()).unwrap();
+        assert!(output.contains(&format!("1 0 0 1 {:.2} ", 100.0 - width / 2.0)));
+    }
+
+    #[test]
+    fn it_widens_spaces_with_word_spacing() {
+        let text = Text::new_with_default_color("a b  c", helvetica(), 10, Point(0.0, 100.0)).with_word_spacing(2.5);
+        let plain_width = helvetica().measure("a b  c", 10.0, true);
+        assert!((text.width() - (plain_width + 7.5)).abs() < 0.001);
+        let output = String::from_utf8(text.into()).unwrap();
+        assert!(output.starts_with("q\n") && output.contains("2.500 Tw\n(a b  c) Tj\n"));
+
+        // Tw doesn't apply to the two byte codes of composite fonts: the gaps go in TJ.
+        let options = FontOptions { composite: true, ..FontOptions::default() };
+        let font = Rc::new(Font::new_embedded("F2", Ref::new(2), include_bytes!("../assets/NotoSansMono.ttf"), &options).unwrap());
+        let output = String::from_utf8(Text::new_with_default_color("a b", font, 10, Point(0.0, 100.0)).with_word_spacing(2.5).into()).unwrap();
+        assert!(output.contains("\n[-250] TJ\n") && !output.contains(" Tw\n"));
+    }
+
+    #[test]
+    fn it_sets_rendering_mode_spacing_scaling_and_rise() {
+        let text = Text::new_with_default_color("ab c", helvetica(), 10, Point(0.0, 100.0))
+            .with_rendering_mode(RenderingMode::Stroke)
+            .with_stroke(RGB(255, 0, 0), 0.5)
+            .with_character_spacing(1.0)
+            .with_word_spacing(2.0)
+            .with_horizontal_scaling(50.0)
+            .with_rise(3.0);
+        // Spacing is added after each of the 4 glyphs and the space, then all is scaled.
+        let plain_width = helvetica().measure("ab c", 10.0, true);
+        assert!((text.width() - (plain_width + 4.0 + 2.0) * 0.5).abs() < 0.001);
+        let output = String::from_utf8(text.into()).unwrap();
+        assert!(output.starts_with("q\n1.000 0.000 0.000 RG\n0.500 w\n0.000 0.000 0.000 rg\nBT\n"));
+        assert!(output.contains("1 Tr\n1.000 Tc\n50.00 Tz\n3.00 Ts\n1 0 0 1 0.00 "));
+        assert!(output.contains("2.000 Tw\n(ab c) Tj\nET\nQ\n"));
+
+        // Clipping text changes the clipping path of what follows, its text state is reset.
+        let clip = Text::new_with_default_color("ab", helvetica(), 10, Point(0.0, 100.0))
+            .with_rendering_mode(RenderingMode::Clip)
+            .with_rise(-2.0);
+        let output = String::from_utf8(clip.into()).unwrap();
+        assert!(!output.starts_with("q\n") && output.contains("7 Tr\n-2.00 Ts\n"));
+        assert!(output.ends_with("ET\n0 Tr\n0 Ts\n"));
+        let invisible = Text::new_with_default_color("ab", helvetica(), 10, Point(0.0, 100.0)).with_rendering_mode(RenderingMode::Invisible);
+        assert!(String::from_utf8(invisible.into()).unwrap().contains("3 Tr\n"));
+    }
+
+    #[test]
+    fn it_draws_text_as_outlines() {
+        let font = Rc::new(Font::new_embedded("F2", Ref::new(2), include_bytes!("../assets/NotoSansMono.ttf"), &FontOptions::default()).unwrap());
+        let text = Text::new_with_default_color("Hi", Rc::clone(&font), 10, Point(100.0, 100.0)).with_outlines(true);
+        let output = String::from_utf8(text.into()).unwrap();
+        assert!(output.starts_with("q\n0.000 0.000 0.000 rg\n"));
+        assert!(output.ends_with(" c\nh\nf\nQ\n") || output.ends_with(" l\nh\nf\nQ\n"));
+        assert!(!output.contains("BT") && !output.contains(" Tf"));
+        assert!(font.used_glyphs().is_empty());
+
+        // The "i" starts one monospaced advance after the "H".
+        let advance = font.measure("H", 10.0, false);
+        let path_of = |text: &str, x: f32| {
+            let text = Text::new_with_default_color(text, Rc::clone(&font), 10, Point(x, 100.0)).with_outlines(true);
+            String::from_utf8(text.into()).unwrap()
+        };
+        assert!(path_of("Hi", 100.0).ends_with(&path_of("i", 100.0 + advance)["q\n0.000 0.000 0.000 rg\n".len()..]));
+
+        // Stroked outlines are painted with S in the stroke color.
+        let stroked = Text::new_with_default_color("Hi", Rc::clone(&font), 10, Point(100.0, 100.0))
+            .with_outlines(true)
+            .with_rendering_mode(RenderingMode::Stroke)
+            .with_stroke(RGB(0, 0, 255), 0.25);
+        let output = String::from_utf8(stroked.into()).unwrap();
+        assert!(output.starts_with("q\n0.000 0.000 0.000 rg\n0.000 0.000 1.000 RG\n0.250 w\n"));
+        assert!(output.ends_with("h\nS\nQ\n"));
+
+        // Standard fonts have no outlines and are still shown as text.
+        let text = Text::new_with_default_color("abc", helvetica(), 10, Point(0.0, 100.0))
+            .with_outlines(true);
+        let output = String::from_utf8(text.into()).unwrap();
+        assert!(output.contains("BT\n/F1 10 Tf\n1 0 0 1 0.00 "));
+
+        // The outlines of all the runs make one clipping path.
+        let clip = Text::new_with_default_color("ab \u{5D0}\u{5D1} cd", Rc::clone(&font), 10, Point(0.0, 100.0))
+            .with_direction(Direction::LeftToRight)
+            .with_outlines(true)
+            .with_rendering_mode(RenderingMode::Clip);
+        assert_eq!(clip.runs().len(), 3);
+        let output = String::from_utf8(clip.into()).unwrap();
+        assert_eq!(output.matches("W n").count(), 1);
+        assert!(output.ends_with("h\nW n\n"));
+    }
+
+    #[test]
+    fn it_widens_strokes_of_synthetic_bold() {
+        let font = Font::new_embedded("F1", Ref::new(1), include_bytes!("../assets/NotoSansMono.ttf"), &FontOptions::default()).unwrap();
+        let bold = Rc::new(font.synthesize("F2", Ref::new(2), SyntheticStyle { bold: true, oblique: false }));
+        let output = |mode: RenderingMode| {
+            let text = Text::new_with_default_color("ab", Rc::clone(&bold), 10, Point(0.0, 100.0))
+                .with_rendering_mode(mode)
+                .with_stroke(RGB(255, 0, 0), 0.5);
+            String::from_utf8(text.into()).unwrap()
+        };
+
+        // Filled glyphs are stroked in the fill color, stroked ones keep the requested mode
+        // and stroke color, with the bold stroke added to their width.
+        assert!(output(RenderingMode::Fill).starts_with("q\n0.000 0.000 0.000 RG\n0.300 w\n"));
+        assert!(output(RenderingMode::Fill).contains("2 Tr\n"));
+        let stroke = output(RenderingMode::Stroke);
+        assert!(stroke.starts_with("q\n1.000 0.000 0.000 RG\n0.800 w\n") && stroke.contains("1 Tr\n"));
+        let fill_stroke = output(RenderingMode::FillStroke);
+        assert!(fill_stroke.starts_with("q\n1.000 0.000 0.000 RG\n0.800 w\n") && fill_stroke.contains("2 Tr\n"));
+    }
+
+    #[test]
+    fn it_sets_vertical_text_down_a_column() {
+        let options = FontOptions { composite: true, ..FontOptions::default() };
+        let font = Font::new_embedded("F1", Ref::new(1), include_bytes!("../assets/NotoSansMono.ttf"), &options).unwrap();
+        let vertical = Rc::new(font.vertical("F2", Ref::new(2)).unwrap());
+        assert!(Font::new_standard("F3", Ref::new(3), StandardFont::Helvetica).vertical("F4", Ref::new(4)).is_err());
+
+        // Upright symbols advance one em, the Latin run its horizontal width.
+        let text = Text::new_with_default_color("©AB©", Rc::clone(&vertical), 10, Point(100.0, 500.0));
+        let latin_width = font.measure("AB", 10.0, true);
+        assert!((vertical.measure("©AB©", 10.0, true) - (20.0 + latin_width)).abs() < 0.001);
+        assert!((text.width() - (20.0 + latin_width)).abs() < 0.001);
+
+        let output = String::from_utf8(text.into()).unwrap();
+        assert!(output.contains("/F2 10 Tf\n1 0 0 1 100.00 500.00 Tm\n<"));
+        assert_eq!(output.matches("/F2 10 Tf").count(), 2);
+        assert!(output.contains("/F1 10 Tf\n0 -1 1 0 "));
+        assert!(output.contains(&format!("/F2 10 Tf\n1 0 0 1 100.00 {:.2} Tm\n<", 490.0 - latin_width)));
+    }
+}
@@ -0,0 +1,144 @@
+/// A PostScript Type 1 font program laid out as `/FontFile` streams expect it: the cleartext
+/// part, the binary eexec encrypted part and the trailer of zeros and `cleartomark`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Type1Program {
+    pub data: Vec<u8>,
+    /// Lengths of the cleartext, encrypted and trailer parts, `/Length1` to `/Length3`.
+    pub lengths: [usize; 3],
+}
+
+impl Type1Program {
+
+    /// Reads a PFB font (binary segments) or a PFA font (encrypted part in hexadecimal).
+    /// Programs that are already in the binary layout are read as they are.
+    pub fn parse(data: &[u8]) -> Result<Type1Program, String> {
+        if data.first() == Some(&0x80) {
+            return parse_pfb(data);
+        }
+        if !data.starts_with(b"%!") {
+            return Err("Type 1 font data must start with %! or a PFB segment".to_string());
+        }
+
+        // The cleartext part ends with `currentfile eexec` and the whitespace after it.
+        let eexec = find(data, b"eexec").ok_or("Type 1 font data has no eexec section")?;
+        let mut cleartext_end = eexec + b"eexec".len();
+        while data.get(cleartext_end).is_some_and(u8::is_ascii_whitespace) {
+            cleartext_end += 1;
+        }
+
+        let trailer_start = trailer_start(data).unwrap_or(data.len()).max(cleartext_end);
+        let encrypted = &data[cleartext_end..trailer_start];
+        // The first four bytes tell hexadecimal from binary encrypted parts.
+        let encrypted = if encrypted.len() >= 4 && encrypted[..4].iter().all(u8::is_ascii_hexdigit) {
+            decode_hex(encrypted)?
+        } else {
+            encrypted.to_vec()
+        };
+
+        Ok(Type1Program::new(&data[..cleartext_end], &encrypted, &data[trailer_start..]))
+    }
+
+    fn new(cleartext: &[u8], encrypted: &[u8], trailer: &[u8]) -> Type1Program {
+        Type1Program {
+            data: [cleartext, encrypted, trailer].concat(),
+            lengths: [cleartext.len(), encrypted.len(), trailer.len()],
+        }
+    }
+
+    /// `/FontName` defined by the cleartext part.
+    pub fn font_name(&self) -> Option<String> {
+        let cleartext = &self.data[..self.lengths[0]];
+        let start = find(cleartext, b"/FontName")? + b"/FontName".len();
+        let name: String = cleartext[start..].iter()
+            .skip_while(|byte| byte.is_ascii_whitespace())
+            .skip(1)
+            .take_while(|byte| !byte.is_ascii_whitespace() && !b"[](){}<>/%".contains(byte))
+            .map(|byte| *byte as char)
+            .collect();
+        Some(name).filter(|name| !name.is_empty())
+    }
+}
+
+// PFB files are a sequence of segments: 0x80, the segment type (1 text, 2 binary, 3 end of
+// file) and a little-endian length. Text after the binary part is the trailer.
+fn parse_pfb(data: &[u8]) -> Result<Type1Program, String> {
+    let mut parts: [Vec<u8>; 3] = Default::default();
+    let mut offset = 0;
+    while offset < data.len() {
+        let segment_type = match data.get(offset..offset + 2) {
+            Some([0x80, 3]) => break,
+            Some([0x80, segment_type]) => *segment_type,
+            _ => return Err("Invalid PFB segment header".to_string()),
+        };
+        let length = data.get(offset + 2..offset + 6).ok_or("Truncated PFB segment")?;
+        let length = u32::from_le_bytes([length[0], length[1], length[2], length[3]]) as usize;
+        let segment = data.get(offset + 6..offset + 6 + length).ok_or("Truncated PFB segment")?;
+        offset += 6 + length;
+
+        let part = match segment_type {
+            1 if parts[1].is_empty() => 0,
+            1 => 2,
+            2 => 1,
+            _ => return Err(format!("Unknown PFB segment type {}", segment_type)),
+        };
+        parts[part].extend_from_slice(segment);
+    }
+
+    if parts[1].is_empty() {
+        return Err("PFB data has no binary segment".to_string());
+    }
+    Ok(Type1Program::new(&parts[0], &parts[1], &parts[2]))
+}
+
+// Start of the trailer: the last `cleartomark` preceded by 512 zeros, whitespace between them.
+fn trailer_start(data: &[u8]) -> Option<usize> {
+    let cleartomark = data.windows(b"cleartomark".len()).rposition(|window| window == b"cleartomark")?;
+    let mut zeros = 0;
+    let mut start = cleartomark;
+    while zeros < 512 {
+        start = start.checked_sub(1)?;
+        match data[start] {
+            b'0' => zeros += 1,
+            byte if byte.is_ascii_whitespace() => {}
+            _ => return None,
+        }
+    }
+    Some(start)
+}
+
+fn find(data: &[u8], pattern: &[u8]) -> Option<usize> {
+    data.windows(pattern.len()).position(|window| window == pattern)
+}
+
+fn decode_hex(data: &[u8]) -> Result<Vec<u8>, String> {
+    let digits: Vec<u8> = data.iter()
+        .filter(|byte| !byte.is_ascii_whitespace())
+        .map(|byte| (*byte as char).to_digit(16).map(|digit| digit as u8))
+        .collect::<Option<Vec<u8>>>()
+        .ok_or("Invalid hexadecimal data in the eexec section")?;
+    Ok(digits.chunks(2).map(|pair| pair[0] << 4 | pair.get(1).copied().unwrap_or(0)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Type1Program;
+    use crate::test_fixtures::{pfb, type1_trailer, TYPE1_CLEARTEXT, TYPE1_ENCRYPTED};
+
+    #[test]
+    fn it_reads_pfb_and_pfa_programs() {
+        let program = Type1Program::parse(&pfb()).unwrap();
+        assert_eq!(program.lengths, [TYPE1_CLEARTEXT.len(), TYPE1_ENCRYPTED.len(), type1_trailer().len()]);
+        assert_eq!(program.data, [TYPE1_CLEARTEXT, TYPE1_ENCRYPTED, &type1_trailer()].concat());
+        assert_eq!(program.font_name().as_deref(), Some("Test-Regular"));
+
+        let hex: String = TYPE1_ENCRYPTED.iter().map(|byte| format!("{:02x}", byte)).collect();
+        let (first_line, second_line) = hex.as_bytes().split_at(6);
+        let pfa = [TYPE1_CLEARTEXT, first_line, b"\n", second_line, b"\n", &type1_trailer()].concat();
+        assert_eq!(Type1Program::parse(&pfa).unwrap(), program);
+        // Binary encrypted part ending with bytes that look like the trailer.
+        assert_eq!(Type1Program::parse(&program.data).unwrap(), program);
+
+        assert!(Type1Program::parse(b"%!PS-AdobeFont-1.0\n").is_err());
+        assert!(Type1Program::parse(&[0x80, 1, 0xFF, 0, 0, 0]).is_err());
+    }
+}
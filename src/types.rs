@@ -1,2 +1,5 @@
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct RGB(pub u8, pub u8, pub u8);
-pub struct Point(pub f32, pub f32);
\ No newline at end of file
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Point(pub f32, pub f32);
@@ -14,7 +14,7 @@ impl Pt {
 
 impl From<Px> for Pt {
     fn from(px: Px) -> Pt {
-        Pt(px.0 * 0.74999943307122)
+        Pt(px.0 * 0.749_999_4)
     }
 }
 
@@ -26,7 +26,7 @@ impl Px {
 
 impl From<Pt> for Px {
     fn from(pt: Pt) -> Px {
-        Px(pt.0 * 1.3333343412075)
+        Px(pt.0 * 1.333_334_3)
     }
 }
 
@@ -361,6 +361,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::excessive_precision)]
     fn it_convert_point_to_pixel_with_dpi_160() {
         let pt = Pt(10.0);
         let px: Px = pt.into_px(160.0);
@@ -0,0 +1,389 @@
+use ttf_parser::{GlyphId, Tag};
+use crate::sfnt;
+
+/// A design axis of a variable font, e.g. `wght` from 100 to 900.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VariationAxis {
+    pub tag: [u8; 4],
+    pub min: f32,
+    pub default: f32,
+    pub max: f32,
+}
+
+/// Lists the variation axes of a font, empty for fonts that are not variable.
+pub fn variation_axes(data: &[u8]) -> Result<Vec<VariationAxis>, String> {
+    let face = ttf_parser::Face::parse(data, 0)
+        .map_err(|_| "Error parsing font data".to_string())?;
+
+    Ok(face.variation_axes().into_iter()
+        .map(|axis| VariationAxis {
+            tag: axis.tag.to_bytes(),
+            min: axis.min_value,
+            default: axis.def_value,
+            max: axis.max_value,
+        })
+        .collect())
+}
+
+// Tables describing the variations themselves, or hinting data that no longer matches the
+// interpolated outlines.
+const DROPPED_TABLES: [&[u8; 4]; 13] = [
+    b"fvar", b"gvar", b"avar", b"cvar", b"HVAR", b"VVAR", b"MVAR", b"STAT",
+    b"cvt ", b"fpgm", b"prep", b"hdmx", b"LTSH",
+];
+
+/// Builds the static instance of a variable TrueType font at `coordinates`, given in user
+/// units (e.g. `(*b"wght", 700.0)`). Axes that are not listed keep their default value and
+/// values outside of an axis range are clamped to it.
+///
+/// Outlines are interpolated with `gvar`, advances with `HVAR` (or the `gvar` phantom points)
+/// and the `OS/2` metrics with `MVAR`. The variation tables and the hinting instructions are
+/// dropped, composite glyphs are flattened into simple ones.
+pub fn instantiate(data: &[u8], coordinates: &[([u8; 4], f32)]) -> Result<Vec<u8>, String> {
+    let mut face = ttf_parser::Face::parse(data, 0)
+        .map_err(|_| "Error parsing font data".to_string())?;
+
+    if !face.is_variable() {
+        return Err("Font is not a variable font".to_string());
+    }
+    if face.tables().glyf.is_none() {
+        return Err("Only variable fonts with TrueType outlines can be instantiated".to_string());
+    }
+
+    for (tag, value) in coordinates {
+        let tag = Tag::from_bytes(tag);
+        if !face.variation_axes().into_iter().any(|axis| axis.tag == tag) {
+            return Err(format!("Font has no {} axis", tag));
+        }
+        face.set_variation(tag, *value);
+    }
+
+    let number_of_glyphs = face.number_of_glyphs();
+    let mut glyf = Vec::new();
+    let mut loca = vec![0u32];
+    let mut metrics = Vec::with_capacity(number_of_glyphs as usize);
+    let mut bbox: Option<[i16; 4]> = None;
+    let (mut max_points, mut max_contours) = (0, 0);
+
+    for glyph_id in 0..number_of_glyphs {
+        let mut outline = OutlineRecorder::default();
+        face.outline_glyph(GlyphId(glyph_id), &mut outline);
+        let contours = outline.finish();
+
+        let glyph = encode_simple_glyph(&contours);
+        let advance = face.glyph_hor_advance(GlyphId(glyph_id)).unwrap_or(0);
+        let glyph_bbox = glyph_bbox(&contours);
+        metrics.push((advance, glyph_bbox));
+
+        if let Some([x_min, y_min, x_max, y_max]) = glyph_bbox {
+            bbox = Some(match bbox {
+                Some([a, b, c, d]) => [a.min(x_min), b.min(y_min), c.max(x_max), d.max(y_max)],
+                None => [x_min, y_min, x_max, y_max],
+            });
+        }
+        max_points = max_points.max(contours.iter().map(Vec::len).sum::<usize>());
+        max_contours = max_contours.max(contours.len());
+
+        glyf.extend_from_slice(&glyph);
+        glyf.resize(glyf.len().next_multiple_of(2), 0);
+        loca.push(glyf.len() as u32);
+    }
+
+    let raw_face = face.raw_face();
+    let table = |tag: &[u8; 4]| -> Result<Vec<u8>, String> {
+        raw_face.table(Tag::from_bytes(tag))
+            .map(|table| table.to_vec())
+            .ok_or_else(|| format!("Font has no {} table", String::from_utf8_lossy(tag)))
+    };
+
+    let mut head = table(b"head")?;
+    let [x_min, y_min, x_max, y_max] = bbox.unwrap_or_default();
+    write_i16(&mut head, 36, x_min);
+    write_i16(&mut head, 38, y_min);
+    write_i16(&mut head, 40, x_max);
+    write_i16(&mut head, 42, y_max);
+    // indexToLocFormat, long offsets.
+    write_i16(&mut head, 50, 1);
+
+    let mut hhea = table(b"hhea")?;
+    let mut hmtx = Vec::with_capacity(metrics.len() * 4);
+    let (mut advance_max, mut min_lsb, mut min_rsb, mut max_extent) = (0u16, i16::MAX, i16::MAX, i16::MIN);
+    for (advance, glyph_bbox) in &metrics {
+        let lsb = glyph_bbox.map(|[x_min, ..]| x_min).unwrap_or(0);
+        hmtx.extend_from_slice(&advance.to_be_bytes());
+        hmtx.extend_from_slice(&lsb.to_be_bytes());
+
+        advance_max = advance_max.max(*advance);
+        if let Some([x_min, _, x_max, _]) = glyph_bbox {
+            min_lsb = min_lsb.min(*x_min);
+            min_rsb = min_rsb.min((*advance as i32 - *x_max as i32) as i16);
+            max_extent = max_extent.max(*x_max);
+        }
+    }
+    write_u16(&mut hhea, 10, advance_max);
+    write_i16(&mut hhea, 12, if min_lsb == i16::MAX { 0 } else { min_lsb });
+    write_i16(&mut hhea, 14, if min_rsb == i16::MAX { 0 } else { min_rsb });
+    write_i16(&mut hhea, 16, if max_extent == i16::MIN { 0 } else { max_extent });
+    write_u16(&mut hhea, 34, number_of_glyphs);
+
+    let mut maxp = table(b"maxp")?;
+    if maxp.len() >= 32 {
+        write_u16(&mut maxp, 6, max_points as u16);
+        write_u16(&mut maxp, 8, max_contours as u16);
+        // No composite glyphs nor instructions are left.
+        for offset in [10, 12, 26, 28, 30] {
+            write_u16(&mut maxp, offset, 0);
+        }
+    }
+
+    let mut tables = vec![
+        (Tag::from_bytes(b"glyf"), glyf),
+        (Tag::from_bytes(b"loca"), loca.iter().flat_map(|offset| offset.to_be_bytes()).collect()),
+        (Tag::from_bytes(b"head"), head),
+        (Tag::from_bytes(b"hhea"), hhea),
+        (Tag::from_bytes(b"hmtx"), hmtx),
+        (Tag::from_bytes(b"maxp"), maxp),
+    ];
+
+    if let Some(mut os2) = raw_face.table(Tag::from_bytes(b"OS/2")).map(|table| table.to_vec()) {
+        apply_os2_variations(&face, coordinates, &mut os2);
+        tables.push((Tag::from_bytes(b"OS/2"), os2));
+    }
+
+    let replaced: Vec<Tag> = tables.iter().map(|(tag, _)| *tag).collect();
+    for record in raw_face.table_records {
+        let dropped = DROPPED_TABLES.iter().any(|tag| Tag::from_bytes(tag) == record.tag);
+        if dropped || replaced.contains(&record.tag) {
+            continue;
+        }
+        if let Some(data) = raw_face.table(record.tag) {
+            tables.push((record.tag, data.to_vec()));
+        }
+    }
+
+    Ok(sfnt::write_sfnt(sfnt::TRUETYPE_VERSION, tables))
+}
+
+// Updates the weight and width classes to the instance coordinates and applies the `MVAR`
+// deltas of the vertical metrics.
+fn apply_os2_variations(face: &ttf_parser::Face, coordinates: &[([u8; 4], f32)], os2: &mut [u8]) {
+    for axis in face.variation_axes() {
+        let value = coordinates.iter()
+            .find(|(tag, _)| Tag::from_bytes(tag) == axis.tag)
+            .map(|(_, value)| value.clamp(axis.min_value, axis.max_value))
+            .unwrap_or(axis.def_value);
+
+        if axis.tag == Tag::from_bytes(b"wght") {
+            write_u16(os2, 4, value.round().clamp(1.0, 1000.0) as u16);
+        } else if axis.tag == Tag::from_bytes(b"wdth") {
+            write_u16(os2, 6, width_class(value));
+        }
+    }
+
+    let Some(mvar) = face.tables().mvar else {
+        return;
+    };
+    // (MVAR tag, offset of the OS/2 field)
+    for (tag, offset) in [(b"hasc", 68), (b"hdsc", 70), (b"hlgp", 72), (b"hcla", 74), (b"hcld", 76), (b"xhgt", 86), (b"cpht", 88)] {
+        if offset + 2 > os2.len() {
+            continue;
+        }
+        if let Some(delta) = mvar.metric_offset(Tag::from_bytes(tag), face.variation_coordinates()) {
+            let value = i16::from_be_bytes([os2[offset], os2[offset + 1]]) as f32 + delta;
+            write_i16(os2, offset, value.round() as i16);
+        }
+    }
+}
+
+// usWidthClass for a `wdth` percentage, as mapped by the OpenType specification.
+fn width_class(width: f32) -> u16 {
+    const CLASSES: [f32; 9] = [50.0, 62.5, 75.0, 87.5, 100.0, 112.5, 125.0, 150.0, 200.0];
+    CLASSES.iter()
+        .enumerate()
+        .min_by(|(_, a), (_, b)| (*a - width).abs().total_cmp(&(*b - width).abs()))
+        .map(|(index, _)| index as u16 + 1)
+        .unwrap_or(5)
+}
+
+// A contour point: x, y and whether it is on the curve.
+type Point = (i16, i16, bool);
+
+// Collects the quadratic outline of a glyph as TrueType points.
+#[derive(Default)]
+struct OutlineRecorder {
+    contours: Vec<Vec<Point>>,
+    current: Vec<Point>,
+}
+
+impl OutlineRecorder {
+    fn push(&mut self, x: f32, y: f32, on_curve: bool) {
+        self.current.push((x.round() as i16, y.round() as i16, on_curve));
+    }
+
+    fn finish(mut self) -> Vec<Vec<Point>> {
+        ttf_parser::OutlineBuilder::close(&mut self);
+        self.contours
+    }
+}
+
+impl ttf_parser::OutlineBuilder for OutlineRecorder {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.close();
+        self.push(x, y, true);
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.push(x, y, true);
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        self.push(x1, y1, false);
+        self.push(x, y, true);
+    }
+
+    fn curve_to(&mut self, _: f32, _: f32, _: f32, _: f32, x: f32, y: f32) {
+        // glyf outlines are quadratic only.
+        self.push(x, y, true);
+    }
+
+    fn close(&mut self) {
+        let mut contour = std::mem::take(&mut self.current);
+        // Contours end with a segment back to their first point, which is implicit in glyf.
+        if contour.len() > 1 && contour.first() == contour.last() {
+            contour.pop();
+        }
+        if contour.len() > 1 {
+            self.contours.push(contour);
+        }
+    }
+}
+
+/// Bounding box `[xMin yMin xMax yMax]` of the points of a glyph, `None` without points.
+pub fn glyph_bbox(contours: &[Vec<(i16, i16, bool)>]) -> Option<[i16; 4]> {
+    let mut points = contours.iter().flatten();
+    let (x, y, _) = points.next()?;
+    Some(points.fold([*x, *y, *x, *y], |[x_min, y_min, x_max, y_max], (x, y, _)| {
+        [x_min.min(*x), y_min.min(*y), x_max.max(*x), y_max.max(*y)]
+    }))
+}
+
+/// Encodes a simple `glyf` glyph without instructions, empty for glyphs without contours.
+pub fn encode_simple_glyph(contours: &[Vec<(i16, i16, bool)>]) -> Vec<u8> {
+    match glyph_bbox(contours) {
+        Some(bbox) => encode_glyph(contours, bbox, &[]),
+        None => Vec::new(),
+    }
+}
+
+/// Encodes a simple `glyf` glyph with the given bounding box and hinting instructions.
+pub fn encode_glyph(contours: &[Vec<(i16, i16, bool)>], bbox: [i16; 4], instructions: &[u8]) -> Vec<u8> {
+    const ON_CURVE: u8 = 0x01;
+    const X_SHORT: u8 = 0x02;
+    const Y_SHORT: u8 = 0x04;
+    const X_SAME_OR_POSITIVE: u8 = 0x10;
+    const Y_SAME_OR_POSITIVE: u8 = 0x20;
+
+    let [x_min, y_min, x_max, y_max] = bbox;
+    let mut glyph = Vec::new();
+    glyph.extend_from_slice(&(contours.len() as i16).to_be_bytes());
+    for value in [x_min, y_min, x_max, y_max] {
+        glyph.extend_from_slice(&value.to_be_bytes());
+    }
+
+    let mut end_point = 0;
+    for contour in contours {
+        end_point += contour.len();
+        glyph.extend_from_slice(&(end_point as u16 - 1).to_be_bytes());
+    }
+    glyph.extend_from_slice(&(instructions.len() as u16).to_be_bytes());
+    glyph.extend_from_slice(instructions);
+
+    let mut flags = Vec::new();
+    let (mut xs, mut ys) = (Vec::new(), Vec::new());
+    let (mut previous_x, mut previous_y) = (0i32, 0i32);
+    for (x, y, on_curve) in contours.iter().flatten() {
+        let mut flag = if *on_curve { ON_CURVE } else { 0 };
+        let (dx, dy) = (*x as i32 - previous_x, *y as i32 - previous_y);
+        (previous_x, previous_y) = (*x as i32, *y as i32);
+
+        for (delta, short, same_or_positive, coordinates) in
+            [(dx, X_SHORT, X_SAME_OR_POSITIVE, &mut xs), (dy, Y_SHORT, Y_SAME_OR_POSITIVE, &mut ys)]
+        {
+            if delta == 0 {
+                flag |= same_or_positive;
+            } else if delta.abs() < 256 {
+                flag |= short;
+                if delta > 0 {
+                    flag |= same_or_positive;
+                }
+                coordinates.push(delta.unsigned_abs() as u8);
+            } else {
+                coordinates.extend_from_slice(&(delta as i16).to_be_bytes());
+            }
+        }
+        flags.push(flag);
+    }
+
+    glyph.extend_from_slice(&flags);
+    glyph.extend_from_slice(&xs);
+    glyph.extend_from_slice(&ys);
+    glyph
+}
+
+fn write_u16(data: &mut [u8], offset: usize, value: u16) {
+    if let Some(bytes) = data.get_mut(offset..offset + 2) {
+        bytes.copy_from_slice(&value.to_be_bytes());
+    }
+}
+
+fn write_i16(data: &mut [u8], offset: usize, value: i16) {
+    write_u16(data, offset, value as u16);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{instantiate, variation_axes, OutlineRecorder};
+    use ttf_parser::{GlyphId, Tag};
+
+    const NOTO_SANS_MONO: &[u8] = include_bytes!("../assets/NotoSansMono.ttf");
+
+    #[test]
+    fn it_lists_variation_axes() {
+        let axes = variation_axes(NOTO_SANS_MONO).unwrap();
+        let wght = axes.iter().find(|axis| &axis.tag == b"wght").unwrap();
+        assert_eq!((wght.min, wght.default, wght.max), (100.0, 400.0, 900.0));
+    }
+
+    fn contours(face: &ttf_parser::Face, glyph_id: GlyphId) -> Vec<Vec<(i16, i16, bool)>> {
+        let mut outline = OutlineRecorder::default();
+        face.outline_glyph(glyph_id, &mut outline);
+        outline.finish()
+    }
+
+    #[test]
+    fn it_interpolates_outlines_and_metrics() {
+        let bold = instantiate(NOTO_SANS_MONO, &[(*b"wght", 900.0)]).unwrap();
+        let default = ttf_parser::Face::parse(NOTO_SANS_MONO, 0).unwrap();
+        let face = ttf_parser::Face::parse(&bold, 0).unwrap();
+        let mut reference = ttf_parser::Face::parse(NOTO_SANS_MONO, 0).unwrap();
+        reference.set_variation(Tag::from_bytes(b"wght"), 900.0);
+
+        assert!(!face.is_variable());
+        assert_eq!(face.weight(), ttf_parser::Weight::Black);
+        assert!(face.raw_face().table(Tag::from_bytes(b"gvar")).is_none());
+
+        let glyph_id = face.glyph_index('l').unwrap();
+        let stem = |face: &ttf_parser::Face| {
+            let bbox = face.glyph_bounding_box(glyph_id).unwrap();
+            bbox.x_max - bbox.x_min
+        };
+        assert!(stem(&face) > stem(&default));
+        assert_eq!(contours(&face, glyph_id), contours(&reference, glyph_id));
+        assert_eq!(face.glyph_hor_advance(glyph_id), reference.glyph_hor_advance(glyph_id));
+    }
+
+    #[test]
+    fn it_rejects_unknown_axes() {
+        assert!(instantiate(NOTO_SANS_MONO, &[(*b"ital", 1.0)]).is_err());
+    }
+}
@@ -0,0 +1,186 @@
+use std::collections::HashMap;
+use ttf_parser::gsub::{SingleSubstitution, SubstitutionSubtable};
+use ttf_parser::opentype_layout::Coverage;
+use ttf_parser::{GlyphId, Tag};
+
+/// Vertical writing data of a font variant made by `Font::vertical`, in 1/1000 em.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerticalLayout {
+    /// Resource name of the horizontal font, which shows the runs set sideways.
+    pub horizontal_font: String,
+    /// Vertical advance and height of the vertical origin of every glyph, from `vmtx` and `VORG`.
+    pub metrics: Vec<(u16, i16)>,
+    /// Advance and origin height of glyphs past `metrics`, written as `/DW2`.
+    pub default_metrics: (u16, i16),
+    /// Vertical alternates of the `vert` feature, e.g. the rotated bracket for `「`.
+    pub alternates: HashMap<u16, u16>,
+}
+
+impl VerticalLayout {
+
+    /// Reads the vertical metrics and `vert` alternates of `face`.
+    pub fn new(face: &ttf_parser::Face, horizontal_font: &str) -> VerticalLayout {
+        let units_per_em = face.units_per_em() as f32;
+        let to_thousand = |value: f32| (value * 1000.0 / units_per_em).round() as i32;
+
+        // Fonts without vertical metrics get an advance of one em and the ascender as origin.
+        let default_metrics = (1000, to_thousand(face.ascender() as f32) as i16);
+        let metrics = (0..face.number_of_glyphs())
+            .map(|glyph_id| {
+                let glyph_id = GlyphId(glyph_id);
+                let advance = face.glyph_ver_advance(glyph_id).map_or(default_metrics.0, |advance| to_thousand(advance as f32) as u16);
+                // The origin is on top of the glyph box: VORG, or else the top side bearing above yMax.
+                let origin = face.glyph_y_origin(glyph_id)
+                    .or_else(|| Some(face.glyph_ver_side_bearing(glyph_id)? + face.glyph_bounding_box(glyph_id)?.y_max))
+                    .map_or(default_metrics.1, |origin| to_thousand(origin as f32) as i16);
+                (advance, origin)
+            })
+            .collect();
+
+        VerticalLayout {
+            horizontal_font: horizontal_font.to_string(),
+            metrics,
+            default_metrics,
+            alternates: vertical_alternates(face),
+        }
+    }
+
+    pub fn alternate(&self, glyph_id: u16) -> u16 {
+        self.alternates.get(&glyph_id).copied().unwrap_or(glyph_id)
+    }
+
+    pub fn advance(&self, glyph_id: u16) -> u16 {
+        self.metrics.get(glyph_id as usize).map_or(self.default_metrics.0, |metrics| metrics.0)
+    }
+
+    pub fn origin(&self, glyph_id: u16) -> i16 {
+        self.metrics.get(glyph_id as usize).map_or(self.default_metrics.1, |metrics| metrics.1)
+    }
+}
+
+// Single substitutions of the `vert` feature lookups.
+fn vertical_alternates(face: &ttf_parser::Face) -> HashMap<u16, u16> {
+    let mut alternates = HashMap::new();
+    let Some(gsub) = face.tables().gsub else {
+        return alternates;
+    };
+
+    let mut indices: Vec<u16> = gsub.features.into_iter()
+        .filter(|feature| feature.tag == Tag::from_bytes(b"vert"))
+        .flat_map(|feature| feature.lookup_indices)
+        .collect();
+    indices.sort_unstable();
+    indices.dedup();
+
+    for lookup in indices.into_iter().filter_map(|index| gsub.lookups.get(index)) {
+        for subtable in lookup.subtables.into_iter::<SubstitutionSubtable>() {
+            let SubstitutionSubtable::Single(single) = subtable else {
+                continue;
+            };
+            let substitutions: Vec<(u16, u16)> = match single {
+                SingleSubstitution::Format1 { coverage, delta } => covered_glyphs(&coverage).into_iter()
+                    .map(|(glyph_id, _)| (glyph_id, glyph_id.wrapping_add(delta as u16)))
+                    .collect(),
+                SingleSubstitution::Format2 { coverage, substitutes } => covered_glyphs(&coverage).into_iter()
+                    .filter_map(|(glyph_id, index)| Some((glyph_id, substitutes.get(index)?.0)))
+                    .collect(),
+            };
+            for (glyph_id, alternate) in substitutions {
+                // The first lookup that applies to a glyph wins.
+                alternates.entry(glyph_id).or_insert(alternate);
+            }
+        }
+    }
+    alternates
+}
+
+// Glyphs of a coverage table with their coverage index.
+fn covered_glyphs(coverage: &Coverage) -> Vec<(u16, u16)> {
+    match coverage {
+        Coverage::Format1 { glyphs } => glyphs.into_iter()
+            .enumerate()
+            .map(|(index, glyph_id)| (glyph_id.0, index as u16))
+            .collect(),
+        Coverage::Format2 { records } => records.into_iter()
+            .flat_map(|record| (record.start.0..=record.end.0)
+                .map(move |glyph_id| (glyph_id, record.value.wrapping_add(glyph_id - record.start.0))))
+            .collect(),
+    }
+}
+
+/// Whether `c` stays upright in vertical text, after the Vertical_Orientation property of
+/// UAX #50: CJK scripts, fullwidth forms and most symbols. Punctuation that turns in vertical
+/// text, like `ー` or `「`, is upright too and drawn with its `vert` alternate. Other
+/// characters, such as Latin letters and digits, are set sideways.
+pub fn is_upright(c: char) -> bool {
+    matches!(c as u32,
+        0x00A7 | 0x00A9 | 0x00AE | 0x00B1 | 0x00BC..=0x00BE | 0x00D7 | 0x00F7
+        | 0x1100..=0x11FF
+        | 0x2016 | 0x2020 | 0x2021 | 0x2030 | 0x2031 | 0x203B | 0x203C | 0x2042 | 0x2047..=0x2049 | 0x2051
+        | 0x20DD..=0x20E0 | 0x20E2..=0x20E4
+        | 0x2100..=0x2101 | 0x2103..=0x2109 | 0x210F | 0x2113..=0x2114 | 0x2116..=0x2117 | 0x211E..=0x2123
+        | 0x2125 | 0x2127 | 0x2129 | 0x212E | 0x2135..=0x213F | 0x2145..=0x214A | 0x214C..=0x214D | 0x214F..=0x2189
+        | 0x2460..=0x24FF | 0x25A0..=0x2619 | 0x2620..=0x2767 | 0x2776..=0x2793
+        | 0x2B12..=0x2B2F | 0x2B50..=0x2B59 | 0x2BB8..=0x2BFF
+        | 0x2E80..=0xA4CF
+        | 0xA960..=0xA97F | 0xAC00..=0xD7FF | 0xE000..=0xFAFF
+        | 0xFE10..=0xFE1F | 0xFE30..=0xFE48 | 0xFE50..=0xFE57 | 0xFE59..=0xFE62 | 0xFE67..=0xFE6F
+        | 0xFF01..=0xFF60 | 0xFFE0..=0xFFE7
+        | 0x1F000..=0x1FAFF | 0x20000..=0x3FFFD | 0xF0000..=0x10FFFD
+    )
+}
+
+/// Splits `text` into runs that are all upright or all sideways, see `is_upright`.
+/// Combining marks and joiners stay in the run of the character before them.
+pub fn orientation_runs(text: &str) -> Vec<(&str, bool)> {
+    let mut runs: Vec<(&str, bool)> = Vec::new();
+    let mut start = 0;
+    let mut current: Option<bool> = None;
+
+    for (index, c) in text.char_indices() {
+        let joins = matches!(c, '\u{0300}'..='\u{036F}' | '\u{200C}' | '\u{200D}' | '\u{3099}' | '\u{309A}' | '\u{FE00}'..='\u{FE0F}');
+        let upright = is_upright(c);
+        match current {
+            Some(previous) if previous != upright && !joins => {
+                runs.push((&text[start..index], previous));
+                start = index;
+                current = Some(upright);
+            }
+            None => current = Some(upright),
+            _ => {}
+        }
+    }
+    if let Some(upright) = current {
+        runs.push((&text[start..], upright));
+    }
+    runs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{orientation_runs, VerticalLayout};
+
+    #[test]
+    fn it_splits_upright_and_sideways_runs() {
+        assert_eq!(orientation_runs("縦書きでPDF、2024年"), vec![
+            ("縦書きで", true),
+            ("PDF", false),
+            ("、", true),
+            ("2024", false),
+            ("年", true),
+        ]);
+        // The voiced sound mark stays with its kana, the accent with its letter.
+        assert_eq!(orientation_runs("か\u{3099}e\u{0301}"), vec![("か\u{3099}", true), ("e\u{0301}", false)]);
+        assert!(orientation_runs("").is_empty());
+    }
+
+    #[test]
+    fn it_falls_back_to_default_vertical_metrics() {
+        let face = ttf_parser::Face::parse(include_bytes!("../assets/NotoSansMono.ttf"), 0).unwrap();
+        let layout = VerticalLayout::new(&face, "F1");
+        let glyph_a = face.glyph_index('A').unwrap().0;
+        assert_eq!(layout.advance(glyph_a), 1000);
+        assert_eq!(layout.origin(glyph_a), layout.default_metrics.1);
+        assert_eq!(layout.alternate(glyph_a), glyph_a);
+    }
+}
@@ -0,0 +1,596 @@
+use std::borrow::Cow;
+use std::io::Read;
+use ttf_parser::Tag;
+use crate::sfnt::write_sfnt;
+use crate::variation::{encode_glyph, glyph_bbox};
+
+// Tags of the WOFF2 known table list, selected by the low six bits of a directory entry.
+const KNOWN_TAGS: [&[u8; 4]; 63] = [
+    b"cmap", b"head", b"hhea", b"hmtx", b"maxp", b"name", b"OS/2", b"post", b"cvt ", b"fpgm",
+    b"glyf", b"loca", b"prep", b"CFF ", b"VORG", b"EBDT", b"EBLC", b"gasp", b"hdmx", b"kern",
+    b"LTSH", b"PCLT", b"VDMX", b"vhea", b"vmtx", b"BASE", b"GDEF", b"GPOS", b"GSUB", b"EBSC",
+    b"JSTF", b"MATH", b"CBDT", b"CBLC", b"COLR", b"CPAL", b"SVG ", b"sbix", b"acnt", b"avar",
+    b"bdat", b"bloc", b"bsln", b"cvar", b"fdsc", b"feat", b"fmtx", b"fvar", b"gvar", b"hsty",
+    b"just", b"lcar", b"mort", b"morx", b"opbd", b"prop", b"trak", b"Zapf", b"Silf", b"Glat",
+    b"Gloc", b"Feat", b"Sill",
+];
+
+/// Decodes WOFF (zlib) and WOFF2 (Brotli) web fonts into the TrueType or OpenType font they
+/// wrap. Any other data is returned unchanged.
+pub fn to_sfnt(data: &[u8]) -> Result<Cow<'_, [u8]>, String> {
+    match data.get(0..4) {
+        Some(b"wOFF") => decode_woff(data).map(Cow::Owned),
+        Some(b"wOF2") => decode_woff2(data).map(Cow::Owned),
+        _ => Ok(Cow::Borrowed(data)),
+    }
+}
+
+fn decode_woff(data: &[u8]) -> Result<Vec<u8>, String> {
+    let mut header = Reader::new(data);
+    header.skip(4)?;
+    let flavor = header.u32()?;
+    header.skip(4)?;
+    let num_tables = header.u16()?;
+
+    // Entries follow the 44 byte header.
+    let mut directory = Reader::new(data);
+    directory.skip(44)?;
+    let mut tables = Vec::new();
+    for _ in 0..num_tables {
+        let tag = Tag(directory.u32()?);
+        let offset = directory.u32()? as usize;
+        let compressed_length = directory.u32()? as usize;
+        let length = directory.u32()? as usize;
+        directory.skip(4)?;
+
+        let table = data.get(offset..offset + compressed_length)
+            .ok_or_else(|| format!("Table {} is out of bounds", tag))?;
+        let table = if compressed_length < length {
+            let mut inflated = Vec::with_capacity(length);
+            flate2::read::ZlibDecoder::new(table)
+                .read_to_end(&mut inflated)
+                .map_err(|error| format!("Error inflating table {}: {}", tag, error))?;
+            inflated
+        } else {
+            table.to_vec()
+        };
+        if table.len() != length {
+            return Err(format!("Table {} has the wrong length", tag));
+        }
+        tables.push((tag, table));
+    }
+
+    Ok(write_sfnt(flavor, tables))
+}
+
+struct Woff2Table {
+    tag: Tag,
+    transformed: bool,
+    // Length in the decompressed stream, transformed or not.
+    length: usize,
+}
+
+fn decode_woff2(data: &[u8]) -> Result<Vec<u8>, String> {
+    let mut reader = Reader::new(data);
+    reader.skip(4)?;
+    let flavor = reader.u32()?;
+    if flavor == u32::from_be_bytes(*b"ttcf") {
+        return Err("WOFF2 font collections are not supported".to_string());
+    }
+    reader.skip(4)?;
+    let num_tables = reader.u16()?;
+    reader.skip(6)?;
+    let compressed_length = reader.u32()? as usize;
+    // Version, metadata and private data.
+    reader.skip(24)?;
+
+    let mut directory = Vec::new();
+    for _ in 0..num_tables {
+        let flags = reader.u8()?;
+        let tag = match flags & 0x3F {
+            63 => Tag(reader.u32()?),
+            index => Tag::from_bytes(KNOWN_TAGS[index as usize]),
+        };
+        let version = flags >> 6;
+        let length = reader.base128()? as usize;
+        // glyf and loca are transformed by version 0, other tables by any other version.
+        let transformed = if tag == Tag::from_bytes(b"glyf") || tag == Tag::from_bytes(b"loca") {
+            version == 0
+        } else {
+            version != 0
+        };
+        let length = if transformed { reader.base128()? as usize } else { length };
+        directory.push(Woff2Table { tag, transformed, length });
+    }
+
+    let mut stream = Vec::new();
+    brotli_decompressor::Decompressor::new(reader.bytes(compressed_length)?, 4096)
+        .read_to_end(&mut stream)
+        .map_err(|error| format!("Error decompressing WOFF2 data: {}", error))?;
+
+    let mut tables: Vec<(Tag, Vec<u8>)> = Vec::new();
+    let mut x_mins: Vec<i16> = Vec::new();
+    let mut transformed_hmtx = None;
+    let mut offset = 0;
+    for table in &directory {
+        let table_data = stream.get(offset..offset + table.length)
+            .ok_or_else(|| format!("Table {} is out of bounds", table.tag))?;
+        offset += table.length;
+
+        match (&table.tag.to_bytes(), table.transformed) {
+            (b"glyf", true) => {
+                let glyf = reconstruct_glyf(table_data)?;
+                tables.push((Tag::from_bytes(b"glyf"), glyf.glyf));
+                tables.push((Tag::from_bytes(b"loca"), glyf.loca));
+                x_mins = glyf.x_mins;
+            }
+            // Rebuilt along with glyf.
+            (b"loca", true) => {}
+            (b"hmtx", true) => transformed_hmtx = Some(table_data),
+            (_, true) => return Err(format!("Unknown transform of table {}", table.tag)),
+            (_, false) => tables.push((table.tag, table_data.to_vec())),
+        }
+    }
+
+    if let Some(hmtx) = transformed_hmtx {
+        let read_u16 = |tag: &[u8; 4], offset: usize| tables.iter()
+            .find(|(table_tag, _)| *table_tag == Tag::from_bytes(tag))
+            .and_then(|(_, data)| data.get(offset..offset + 2))
+            .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+            .ok_or_else(|| format!("Missing {} table", String::from_utf8_lossy(tag)));
+        let num_glyphs = read_u16(b"maxp", 4)?;
+        let num_h_metrics = read_u16(b"hhea", 34)?;
+        tables.push((Tag::from_bytes(b"hmtx"), reconstruct_hmtx(hmtx, num_glyphs, num_h_metrics, &x_mins)?));
+    }
+
+    Ok(write_sfnt(flavor, tables))
+}
+
+// Tables rebuilt from a transformed glyf table, with the xMin of every glyph for the hmtx
+// transform.
+struct GlyfTables {
+    glyf: Vec<u8>,
+    loca: Vec<u8>,
+    x_mins: Vec<i16>,
+}
+
+// Rebuilds the glyf and loca tables out of the transformed glyf table of a WOFF2 font.
+fn reconstruct_glyf(data: &[u8]) -> Result<GlyfTables, String> {
+    const OVERLAP_SIMPLE: u8 = 0x40;
+    const ARG_1_AND_2_ARE_WORDS: u16 = 0x0001;
+    const WE_HAVE_A_SCALE: u16 = 0x0008;
+    const MORE_COMPONENTS: u16 = 0x0020;
+    const WE_HAVE_AN_X_AND_Y_SCALE: u16 = 0x0040;
+    const WE_HAVE_A_TWO_BY_TWO: u16 = 0x0080;
+    const WE_HAVE_INSTRUCTIONS: u16 = 0x0100;
+
+    let mut header = Reader::new(data);
+    header.skip(2)?;
+    let option_flags = header.u16()?;
+    let num_glyphs = header.u16()? as usize;
+    let index_format = header.u16()?;
+    let mut streams = Vec::new();
+    for _ in 0..7 {
+        streams.push(header.u32()? as usize);
+    }
+
+    let mut take = |length: usize| header.bytes(length).map(Reader::new);
+    let mut contour_counts = take(streams[0])?;
+    let mut point_counts = take(streams[1])?;
+    let mut flags = take(streams[2])?;
+    let mut glyphs = take(streams[3])?;
+    let mut composites = take(streams[4])?;
+    let mut bboxes = take(streams[5])?;
+    let mut instructions = take(streams[6])?;
+    let overlap_bitmap = if option_flags & 1 != 0 {
+        take(num_glyphs.div_ceil(8))?.data
+    } else {
+        &[]
+    };
+    let bbox_bitmap = bboxes.bytes(num_glyphs.div_ceil(32) * 4)?;
+    let bit_set = |bitmap: &[u8], glyph_id: usize| bitmap.get(glyph_id / 8).is_some_and(|byte| byte & (0x80 >> (glyph_id % 8)) != 0);
+
+    let mut glyf = Vec::new();
+    let mut offsets = Vec::with_capacity(num_glyphs + 1);
+    let mut x_mins = Vec::with_capacity(num_glyphs);
+    for glyph_id in 0..num_glyphs {
+        offsets.push(glyf.len());
+        let contour_count = contour_counts.i16()?;
+        let explicit_bbox = if bit_set(bbox_bitmap, glyph_id) {
+            Some([bboxes.i16()?, bboxes.i16()?, bboxes.i16()?, bboxes.i16()?])
+        } else {
+            None
+        };
+
+        let glyph = if contour_count == 0 {
+            Vec::new()
+        } else if contour_count < 0 {
+            let bbox = explicit_bbox.ok_or("Composite glyph without bounding box")?;
+            let start = composites.offset;
+            let mut has_instructions = false;
+            loop {
+                let component_flags = composites.u16()?;
+                let arguments = if component_flags & ARG_1_AND_2_ARE_WORDS != 0 { 4 } else { 2 };
+                let scale = if component_flags & WE_HAVE_A_SCALE != 0 {
+                    2
+                } else if component_flags & WE_HAVE_AN_X_AND_Y_SCALE != 0 {
+                    4
+                } else if component_flags & WE_HAVE_A_TWO_BY_TWO != 0 {
+                    8
+                } else {
+                    0
+                };
+                // Glyph index, arguments and scale.
+                composites.skip(2 + arguments + scale)?;
+                has_instructions |= component_flags & WE_HAVE_INSTRUCTIONS != 0;
+                if component_flags & MORE_COMPONENTS == 0 {
+                    break;
+                }
+            }
+
+            let mut glyph = Vec::new();
+            glyph.extend_from_slice(&(-1i16).to_be_bytes());
+            for value in bbox {
+                glyph.extend_from_slice(&value.to_be_bytes());
+            }
+            glyph.extend_from_slice(&composites.data[start..composites.offset]);
+            if has_instructions {
+                let length = glyphs.u255_16()? as usize;
+                glyph.extend_from_slice(&(length as u16).to_be_bytes());
+                glyph.extend_from_slice(instructions.bytes(length)?);
+            }
+            glyph
+        } else {
+            let mut contours = Vec::with_capacity(contour_count as usize);
+            let (mut x, mut y) = (0i32, 0i32);
+            for _ in 0..contour_count {
+                let point_count = point_counts.u255_16()?;
+                let mut contour = Vec::with_capacity(point_count as usize);
+                for _ in 0..point_count {
+                    let flag = flags.u8()?;
+                    let (dx, dy) = triplet(flag & 0x7F, &mut glyphs)?;
+                    (x, y) = (x + dx, y + dy);
+                    contour.push((x as i16, y as i16, flag & 0x80 == 0));
+                }
+                contours.push(contour);
+            }
+            let length = glyphs.u255_16()? as usize;
+            let glyph_instructions = instructions.bytes(length)?;
+
+            let bbox = explicit_bbox.or_else(|| glyph_bbox(&contours)).unwrap_or_default();
+            let mut glyph = encode_glyph(&contours, bbox, glyph_instructions);
+            if bit_set(overlap_bitmap, glyph_id) {
+                // The flag of the first point, after the header, the contour ends and the instructions.
+                let first_flag = 10 + 2 * contour_count as usize + 2 + length;
+                if let Some(flag) = glyph.get_mut(first_flag) {
+                    *flag |= OVERLAP_SIMPLE;
+                }
+            }
+            glyph
+        };
+
+        x_mins.push(if glyph.len() >= 4 { i16::from_be_bytes([glyph[2], glyph[3]]) } else { 0 });
+        glyf.extend_from_slice(&glyph);
+        glyf.resize(glyf.len().next_multiple_of(4), 0);
+    }
+    offsets.push(glyf.len());
+
+    let loca: Vec<u8> = match index_format {
+        0 => offsets.iter().flat_map(|offset| ((offset / 2) as u16).to_be_bytes()).collect(),
+        _ => offsets.iter().flat_map(|offset| (*offset as u32).to_be_bytes()).collect(),
+    };
+    Ok(GlyfTables { glyf, loca, x_mins })
+}
+
+// Decodes the coordinate deltas of a point from its flag (without the on curve bit) and the
+// bytes that follow it in the glyph stream.
+fn triplet(flag: u8, glyphs: &mut Reader) -> Result<(i32, i32), String> {
+    let with_sign = |flag: u8, value: i32| if flag & 1 != 0 { value } else { -value };
+    let length = match flag {
+        0..=83 => 1,
+        84..=119 => 2,
+        120..=123 => 3,
+        _ => 4,
+    };
+    let bytes: Vec<i32> = glyphs.bytes(length)?.iter().map(|byte| *byte as i32).collect();
+    let flag_value = flag as i32;
+
+    Ok(match flag {
+        0..=9 => (0, with_sign(flag, ((flag_value & 14) << 7) + bytes[0])),
+        10..=19 => (with_sign(flag, (((flag_value - 10) & 14) << 7) + bytes[0]), 0),
+        20..=83 => {
+            let b0 = flag_value - 20;
+            (
+                with_sign(flag, 1 + (b0 & 0x30) + (bytes[0] >> 4)),
+                with_sign(flag >> 1, 1 + ((b0 & 0x0C) << 2) + (bytes[0] & 0x0F)),
+            )
+        }
+        84..=119 => {
+            let b0 = flag_value - 84;
+            (
+                with_sign(flag, 1 + ((b0 / 12) << 8) + bytes[0]),
+                with_sign(flag >> 1, 1 + (((b0 % 12) >> 2) << 8) + bytes[1]),
+            )
+        }
+        120..=123 => (
+            with_sign(flag, (bytes[0] << 4) + (bytes[1] >> 4)),
+            with_sign(flag >> 1, ((bytes[1] & 0x0F) << 8) + bytes[2]),
+        ),
+        _ => (
+            with_sign(flag, (bytes[0] << 8) + bytes[1]),
+            with_sign(flag >> 1, (bytes[2] << 8) + bytes[3]),
+        ),
+    })
+}
+
+// Rebuilds an hmtx table whose left side bearings may have been dropped because they equal
+// the xMin of the glyphs.
+fn reconstruct_hmtx(data: &[u8], num_glyphs: u16, num_h_metrics: u16, x_mins: &[i16]) -> Result<Vec<u8>, String> {
+    let (num_glyphs, num_h_metrics) = (num_glyphs as usize, num_h_metrics as usize);
+    let num_bearings = num_glyphs.checked_sub(num_h_metrics).ok_or("More horizontal metrics than glyphs in WOFF2 data")?;
+    let mut reader = Reader::new(data);
+    let flags = reader.u8()?;
+    let mut advances = Vec::with_capacity(num_h_metrics);
+    for _ in 0..num_h_metrics {
+        advances.push(reader.u16()?);
+    }
+
+    let mut bearings = Vec::with_capacity(num_glyphs);
+    for glyph_id in 0..num_glyphs {
+        // Bit 0 drops the bearings of the glyphs with an advance, bit 1 the other ones.
+        let dropped = if glyph_id < num_h_metrics { flags & 1 != 0 } else { flags & 2 != 0 };
+        bearings.push(if dropped { x_mins.get(glyph_id).copied().unwrap_or(0) } else { reader.i16()? });
+    }
+
+    let mut hmtx = Vec::with_capacity(num_h_metrics * 4 + num_bearings * 2);
+    for (glyph_id, bearing) in bearings.iter().enumerate() {
+        if let Some(advance) = advances.get(glyph_id) {
+            hmtx.extend_from_slice(&advance.to_be_bytes());
+        }
+        hmtx.extend_from_slice(&bearing.to_be_bytes());
+    }
+    Ok(hmtx)
+}
+
+// Big-endian reads over WOFF data.
+struct Reader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+
+    fn new(data: &'a [u8]) -> Reader<'a> {
+        Reader { data, offset: 0 }
+    }
+
+    fn bytes(&mut self, length: usize) -> Result<&'a [u8], String> {
+        let bytes = self.data.get(self.offset..self.offset + length).ok_or("Truncated WOFF data")?;
+        self.offset += length;
+        Ok(bytes)
+    }
+
+    fn skip(&mut self, length: usize) -> Result<(), String> {
+        self.bytes(length).map(|_| ())
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn i16(&mut self) -> Result<i16, String> {
+        self.u16().map(|value| value as i16)
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        let bytes = self.bytes(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    // UIntBase128: up to five bytes of seven bits, most significant first.
+    fn base128(&mut self) -> Result<u32, String> {
+        let mut value: u32 = 0;
+        for index in 0..5 {
+            let byte = self.u8()?;
+            if (index == 0 && byte == 0x80) || value & 0xFE00_0000 != 0 {
+                return Err("Invalid UIntBase128 value".to_string());
+            }
+            value = (value << 7) | (byte & 0x7F) as u32;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err("Invalid UIntBase128 value".to_string())
+    }
+
+    // 255UInt16: one byte, or a marker byte followed by one or two bytes.
+    fn u255_16(&mut self) -> Result<u16, String> {
+        Ok(match self.u8()? {
+            253 => self.u16()?,
+            254 => self.u8()? as u16 + 506,
+            255 => self.u8()? as u16 + 253,
+            code => code as u16,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use ttf_parser::{RawFace, Tag};
+    use super::{reconstruct_glyf, reconstruct_hmtx, to_sfnt, KNOWN_TAGS};
+
+    fn tables(data: &[u8]) -> Vec<(Tag, Vec<u8>)> {
+        let face = RawFace::parse(data, 0).unwrap();
+        face.table_records.into_iter()
+            .map(|record| {
+                let mut table = face.table(record.tag).unwrap().to_vec();
+                // The checksum adjustment depends on the layout of the whole font.
+                if record.tag == Tag::from_bytes(b"head") {
+                    table[8..12].fill(0);
+                }
+                (record.tag, table)
+            })
+            .collect()
+    }
+
+    fn woff(data: &[u8]) -> Vec<u8> {
+        let tables = tables(data);
+        let mut directory = Vec::new();
+        let mut content = Vec::new();
+        let mut offset = 44 + tables.len() * 20;
+        for (tag, table) in &tables {
+            let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(table).unwrap();
+            let compressed = encoder.finish().unwrap();
+            // Tables that don't get smaller are stored as they are.
+            let stored = if compressed.len() < table.len() { compressed } else { table.clone() };
+            for value in [tag.0, offset as u32, stored.len() as u32, table.len() as u32, 0] {
+                directory.extend_from_slice(&value.to_be_bytes());
+            }
+            offset += stored.len().next_multiple_of(4);
+            content.extend_from_slice(&stored);
+            content.resize(content.len().next_multiple_of(4), 0);
+        }
+
+        let mut font = b"wOFF".to_vec();
+        font.extend_from_slice(&0x0001_0000u32.to_be_bytes());
+        font.extend_from_slice(&(offset as u32).to_be_bytes());
+        font.extend_from_slice(&(tables.len() as u16).to_be_bytes());
+        font.resize(44, 0);
+        font.extend_from_slice(&directory);
+        font.extend_from_slice(&content);
+        font
+    }
+
+    // Brotli stream of uncompressed meta-blocks.
+    fn brotli_store(data: &[u8]) -> Vec<u8> {
+        let mut stream = Vec::new();
+        for (index, block) in data.chunks(65536).enumerate() {
+            // WBITS 16 (one bit) on the first block, ISLAST 0, MNIBBLES 4, MLEN - 1, ISUNCOMPRESSED.
+            let shift = if index == 0 { 1 } else { 0 };
+            let header = ((block.len() as u32 - 1) << (3 + shift)) | (1 << (19 + shift));
+            stream.extend_from_slice(&header.to_le_bytes()[..3]);
+            stream.extend_from_slice(block);
+        }
+        // ISLAST and ISLASTEMPTY.
+        stream.push(0b11);
+        stream
+    }
+
+    fn woff2(data: &[u8]) -> Vec<u8> {
+        let tables = tables(data);
+        let mut directory = Vec::new();
+        let mut content = Vec::new();
+        for (tag, table) in &tables {
+            let known = KNOWN_TAGS.iter().position(|known| Tag::from_bytes(known) == *tag);
+            // glyf and loca are stored without transform (version 3).
+            let version: u8 = if *tag == Tag::from_bytes(b"glyf") || *tag == Tag::from_bytes(b"loca") { 3 << 6 } else { 0 };
+            match known {
+                Some(index) => directory.push(index as u8 | version),
+                None => {
+                    directory.push(63);
+                    directory.extend_from_slice(&tag.0.to_be_bytes());
+                }
+            }
+            let mut length = table.len() as u32;
+            let mut base128 = vec![(length & 0x7F) as u8];
+            while length > 0x7F {
+                length >>= 7;
+                base128.insert(0, (length & 0x7F) as u8 | 0x80);
+            }
+            directory.extend_from_slice(&base128);
+            content.extend_from_slice(table);
+        }
+        let compressed = brotli_store(&content);
+
+        let mut font = b"wOF2".to_vec();
+        font.extend_from_slice(&0x0001_0000u32.to_be_bytes());
+        font.extend_from_slice(&[0; 4]);
+        font.extend_from_slice(&(tables.len() as u16).to_be_bytes());
+        font.extend_from_slice(&[0; 6]);
+        font.extend_from_slice(&(compressed.len() as u32).to_be_bytes());
+        font.resize(48, 0);
+        font.extend_from_slice(&directory);
+        font.extend_from_slice(&compressed);
+        font
+    }
+
+    #[test]
+    fn it_decodes_woff_and_woff2_containers() {
+        let data = include_bytes!("../assets/NotoSansMono.ttf");
+        for web_font in [woff(data), woff2(data)] {
+            let sfnt = to_sfnt(&web_font).unwrap();
+            assert_eq!(tables(&sfnt), tables(data));
+            assert!(ttf_parser::Face::parse(&sfnt, 0).is_ok());
+        }
+        assert_eq!(to_sfnt(data).unwrap().as_ref(), &data[..]);
+        assert!(to_sfnt(b"wOF2").is_err());
+    }
+
+    #[test]
+    fn it_reconstructs_transformed_glyphs() {
+        let streams: [&[u8]; 7] = [
+            // One glyph of one contour.
+            &[0, 1],
+            &[3],
+            // On curve (0, 0), on curve +100 x and off curve -50 x +200 y.
+            &[0, 11, 0x80 | 86],
+            &[0, 100, 49, 199, 0],
+            &[],
+            // The bounding box is computed.
+            &[0, 0, 0, 0],
+            &[],
+        ];
+        let mut glyf = vec![0, 0, 0, 0, 0, 1, 0, 1];
+        for stream in streams {
+            glyf.extend_from_slice(&(stream.len() as u32).to_be_bytes());
+        }
+        for stream in streams {
+            glyf.extend_from_slice(stream);
+        }
+
+        let tables = reconstruct_glyf(&glyf).unwrap();
+        let expected = crate::variation::encode_simple_glyph(&[vec![(0, 0, true), (100, 0, true), (50, 200, false)]]);
+        assert_eq!(&tables.glyf[..expected.len()], expected.as_slice());
+        assert_eq!(tables.loca, [0u32.to_be_bytes(), (tables.glyf.len() as u32).to_be_bytes()].concat());
+        assert_eq!(tables.x_mins, vec![0]);
+    }
+
+    #[test]
+    fn it_reconstructs_transformed_horizontal_metrics() {
+        let data = include_bytes!("../assets/NotoSansMono.ttf");
+        let face = ttf_parser::Face::parse(data, 0).unwrap();
+        let hmtx = face.raw_face().table(Tag::from_bytes(b"hmtx")).unwrap();
+        let num_glyphs = face.number_of_glyphs();
+        let num_h_metrics = face.tables().hhea.number_of_metrics;
+        let x_mins: Vec<i16> = (0..num_glyphs)
+            .map(|glyph_id| face.glyph_bounding_box(ttf_parser::GlyphId(glyph_id)).map_or(0, |bbox| bbox.x_min))
+            .collect();
+
+        let metrics = &hmtx[..num_h_metrics as usize * 4];
+        let advances: Vec<u8> = metrics.chunks(4).flat_map(|metric| metric[..2].to_vec()).collect();
+        let bearings: Vec<u8> = metrics.chunks(4).flat_map(|metric| metric[2..].to_vec()).collect();
+        let other_bearings = &hmtx[metrics.len()..];
+        // Bit 0 derives the bearings of the glyphs with an advance from their xMin, bit 1 the
+        // bearings of the other glyphs.
+        for flags in 0..4u8 {
+            let mut transformed = vec![flags];
+            transformed.extend_from_slice(&advances);
+            if flags & 1 == 0 {
+                transformed.extend_from_slice(&bearings);
+            }
+            if flags & 2 == 0 {
+                transformed.extend_from_slice(other_bearings);
+            }
+            assert_eq!(reconstruct_hmtx(&transformed, num_glyphs, num_h_metrics, &x_mins).unwrap(), hmtx);
+        }
+
+        assert!(reconstruct_hmtx(&[3], 1, 2, &[]).is_err());
+    }
+}
@@ -0,0 +1,169 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+/// Words of a language written without spaces between them, like Thai, Lao, Khmer or
+/// Burmese, used to find where its lines can break.
+///
+/// Word lists hold one word per line, like those libthai and ICU build their dictionaries
+/// from. Empty lines and lines starting with `#` are skipped.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WordDictionary {
+    /// Language of the words, e.g. `th`.
+    pub language: String,
+    words: HashSet<String>,
+    // Length of the longest word, in characters.
+    max_word_length: usize,
+}
+
+// Best way found to split the text up to a character: characters left out of any word, then
+// the number of words, and where the last word or unknown character starts, after a split
+// ending with an unknown character or not.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Split {
+    unknown: usize,
+    words: usize,
+    previous: usize,
+    after_unknown: bool,
+}
+
+impl WordDictionary {
+
+    pub fn new(language: &str, source: &str) -> Result<WordDictionary, String> {
+        let words: HashSet<String> = source.lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(str::to_string)
+            .collect();
+        if words.is_empty() {
+            return Err(format!("No words found for {}", language));
+        }
+
+        Ok(WordDictionary {
+            language: language.to_string(),
+            max_word_length: words.iter().map(|word| word.chars().count()).max().unwrap_or(0),
+            words,
+        })
+    }
+
+    pub fn from_file(language: &str, path: &Path) -> Result<WordDictionary, String> {
+        let source = fs::read_to_string(path)
+            .map_err(|error| format!("Error reading word list {}: {}", path.display(), error))?;
+        WordDictionary::new(language, &source)
+    }
+
+    /// Byte offsets of `text` where its words start, the first one excluded. The text is split
+    /// leaving as few characters as possible out of the words of the dictionary, then into as
+    /// few words as possible (maximal matching). Runs of characters outside any word are kept
+    /// together, and no break is put inside a Thai syllable: before a vowel or tone mark
+    /// written above or below its consonant, nor after a vowel written before it.
+    pub fn word_breaks(&self, text: &str) -> Vec<usize> {
+        let offsets: Vec<usize> = text.char_indices().map(|(offset, _)| offset).chain([text.len()]).collect();
+        let length = offsets.len() - 1;
+
+        // Splits ending with a word, and ending with an unknown character.
+        let mut by_word: Vec<Option<Split>> = vec![None; length + 1];
+        let mut by_unknown: Vec<Option<Split>> = vec![None; length + 1];
+        by_word[0] = Some(Split { unknown: 0, words: 0, previous: 0, after_unknown: false });
+        let keep_best = |splits: &mut Vec<Option<Split>>, end: usize, split: Split| {
+            if splits[end].is_none_or(|best| split < best) {
+                splits[end] = Some(split);
+            }
+        };
+
+        for start in 0..length {
+            if let Some(split) = by_word[start] {
+                keep_best(&mut by_unknown, start + 1, Split { unknown: split.unknown + 1, words: split.words + 1, previous: start, after_unknown: false });
+            }
+            if let Some(split) = by_unknown[start] {
+                // An unknown character after another one goes on with the same run.
+                keep_best(&mut by_unknown, start + 1, Split { unknown: split.unknown + 1, words: split.words, previous: start, after_unknown: true });
+            }
+            let best = match (by_word[start], by_unknown[start]) {
+                (Some(word), Some(unknown)) if unknown < word => (unknown, true),
+                (Some(word), _) => (word, false),
+                (None, Some(unknown)) => (unknown, true),
+                (None, None) => continue,
+            };
+            for end in start + 1..=length.min(start + self.max_word_length) {
+                if self.words.contains(&text[offsets[start]..offsets[end]]) {
+                    keep_best(&mut by_word, end, Split { unknown: best.0.unknown, words: best.0.words + 1, previous: start, after_unknown: best.1 });
+                }
+            }
+        }
+
+        // Walks the best split back from the end: words and runs of unknown characters start
+        // where a line can break.
+        let mut breaks = Vec::new();
+        let mut end = length;
+        let mut unknown = match (by_word[length], by_unknown[length]) {
+            (Some(word), Some(unknown)) => unknown < word,
+            (word, _) => word.is_none(),
+        };
+        while end > 0 {
+            let Some(split) = (if unknown { by_unknown[end] } else { by_word[end] }) else {
+                break;
+            };
+            if split.previous > 0 && !(unknown && split.after_unknown) {
+                breaks.push(offsets[split.previous]);
+            }
+            end = split.previous;
+            unknown = split.after_unknown;
+        }
+        breaks.reverse();
+
+        breaks.retain(|offset| {
+            let before = text[..*offset].chars().next_back().is_some_and(is_thai_leading_vowel);
+            let after = text[*offset..].chars().next().is_some_and(is_thai_mark);
+            !before && !after
+        });
+        breaks
+    }
+}
+
+// Vowels and tone marks written above or below the consonant they follow.
+fn is_thai_mark(c: char) -> bool {
+    matches!(c, '\u{E31}' | '\u{E34}'..='\u{E3A}' | '\u{E47}'..='\u{E4E}')
+}
+
+// Vowels written before the consonant they follow.
+fn is_thai_leading_vowel(c: char) -> bool {
+    matches!(c, '\u{E40}'..='\u{E44}')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WordDictionary;
+    use crate::test_fixtures::THAI_WORDS;
+
+    fn words<'a>(dictionary: &WordDictionary, text: &'a str) -> Vec<&'a str> {
+        let mut words = Vec::new();
+        let mut start = 0;
+        for end in dictionary.word_breaks(text).into_iter().chain([text.len()]) {
+            words.push(&text[start..end]);
+            start = end;
+        }
+        words
+    }
+
+    #[test]
+    fn it_splits_text_into_the_fewest_words() {
+        let dictionary = WordDictionary::new("th", THAI_WORDS).unwrap();
+        // "Thai is very easy": ไท and ยง are not taken when ไทย and ง่าย cover the text.
+        assert_eq!(words(&dictionary, "ภาษาไทยง่ายมาก"), vec!["ภาษา", "ไทย", "ง่าย", "มาก"]);
+        // The fewest words: ภาษาไทย rather than ภาษา and ไทย.
+        let dictionary = WordDictionary::new("th", &format!("{}ภาษาไทย\n", THAI_WORDS)).unwrap();
+        assert_eq!(words(&dictionary, "ภาษาไทยง่าย"), vec!["ภาษาไทย", "ง่าย"]);
+        assert!(WordDictionary::new("th", "# no words\n\n").is_err());
+    }
+
+    #[test]
+    fn it_keeps_unknown_characters_together() {
+        let dictionary = WordDictionary::new("th", THAI_WORDS).unwrap();
+        // ลาว is not in the list.
+        assert_eq!(words(&dictionary, "ภาษาลาวง่าย"), vec!["ภาษา", "ลาว", "ง่าย"]);
+        // Tone marks and leading vowels stay with their consonant.
+        assert_eq!(words(&dictionary, "มาก่"), vec!["มาก่"]);
+        assert_eq!(words(&dictionary, "เมาก"), vec!["เมาก"]);
+    }
+}